@@ -1,11 +1,14 @@
 #[cfg(test)]
 mod test;
+pub mod definition;
 pub mod matcher;
 pub mod skip;
+pub mod trace;
 
 use unicode_reader::CodePoints;
 
-use crate::{Error, Result};
+use crate::{Error, ErrorKind, Lexeme, Result, Span, Spanned};
+use trace::{RuleState, ShadowedRule, Trace, TraceStep};
 
 #[derive(Clone)]
 pub enum State<T: Clone> {
@@ -27,6 +30,14 @@ pub trait MatchRule<T: Clone> {
 pub trait Matcher<T: Clone> {
     fn check(self: &mut Self, c: Char) -> State<T>;
     fn reset(self: &mut Self);
+
+    /// Texts matcher is made for, used to find shadowed rules
+    ///
+    /// Matchers without samples are checked on common token shapes only, see
+    /// [`Builder::shadowed`].
+    fn samples(self: &Self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub trait SkipRule {
@@ -37,76 +48,442 @@ pub trait Skip {
     fn is_skipping(self: &mut Self, c: char) -> bool;
 }
 
-pub struct Lexer<T> {
-    rules: Vec<Box<dyn MatchRule<T>>>,
-    skip: Option<Box<dyn SkipRule>>,
+/// Strategy of choosing token when several rules match
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// First completed rule wins, rule still matching before it is treated
+    /// as broken grammar
+    First,
+    /// Longest match wins, ties are resolved by priority and then by order.
+    /// `backtrack` limits how many characters after the longest found match
+    /// are buffered while waiting for longer one
+    Longest { backtrack: usize },
+}
+
+/// Context rules are added to by default and lexing starts in
+pub const DEFAULT_CONTEXT: &str = "default";
+
+/// Change of lexer context caused by matched token
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transition {
+    /// Enter named context, current one is restored by [`Transition::Pop`]
+    Push(&'static str),
+    /// Return to the previous context
+    Pop,
+}
+
+/// Position of token on its line a rule is restricted to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    Any,
+    /// Only the first token on a line, same as `^` in lex
+    LineStart,
+}
+
+impl Anchor {
+    fn allows(self: Self, line_start: bool) -> bool {
+        match self {
+            Anchor::Any => true,
+            Anchor::LineStart => line_start,
+        }
+    }
+}
+
+type Transitions<T> = std::sync::Arc<dyn Fn(&T) -> Option<Transition> + Send + Sync>;
+
+/// How [`TokenIterator`] chooses tokens and what it does besides
+struct Settings<T> {
+    mode: Mode,
+    recover: bool,
+    transitions: Option<Transitions<T>>,
+    trace: Option<Trace>,
+}
+
+impl<T> Settings<T> {
+    fn new() -> Self {
+        Self {
+            mode: Mode::First,
+            recover: false,
+            transitions: None,
+            trace: None,
+        }
+    }
+}
+
+impl<T> Clone for Settings<T> {
+    fn clone(&self) -> Self {
+        Self {
+            mode: self.mode,
+            recover: self.recover,
+            transitions: self.transitions.clone(),
+            trace: self.trace.clone(),
+        }
+    }
+}
+
+/// Rule as [`Builder`] keeps it, creates `P` for every input
+pub trait Stored<P> {
+    fn create(self: &mut Self) -> P;
+}
+
+/// Rule [`Builder`] keeping its rules as `R` accepts
+pub trait IntoStored<R> {
+    fn into_stored(self: Self) -> R;
+}
+
+impl<T: Clone> Stored<Box<dyn Matcher<T>>> for Box<dyn MatchRule<T>> {
+    fn create(self: &mut Self) -> Box<dyn Matcher<T>> {
+        self.as_mut().get()
+    }
+}
+
+impl Stored<Box<dyn Skip>> for Box<dyn SkipRule> {
+    fn create(self: &mut Self) -> Box<dyn Skip> {
+        self.as_mut().get()
+    }
+}
+
+impl<T: Clone, R: MatchRule<T> + 'static> IntoStored<Box<dyn MatchRule<T>>> for R {
+    fn into_stored(self: Self) -> Box<dyn MatchRule<T>> {
+        Box::new(self)
+    }
+}
+
+impl<R: SkipRule + 'static> IntoStored<Box<dyn SkipRule>> for R {
+    fn into_stored(self: Self) -> Box<dyn SkipRule> {
+        Box::new(self)
+    }
+}
+
+struct Rule<R> {
+    rule: R,
+    priority: isize,
+    context: &'static str,
+    anchor: Anchor,
+}
+
+impl<R> Rule<R> {
+    fn state<T: Clone>(self: &Self, matcher: Box<dyn Matcher<T>>) -> (&'static str, MatcherState<T>) {
+        (self.context, MatcherState::new_priority(matcher, self.priority).anchored(self.anchor))
+    }
+}
+
+impl<R: Clone> Clone for Rule<R> {
+    fn clone(&self) -> Self {
+        Self {
+            rule: self.rule.clone(),
+            priority: self.priority,
+            context: self.context,
+            anchor: self.anchor,
+        }
+    }
+}
+
+/// Rules and settings of a lexer, `R` and `S` are how match and skip rules
+/// are kept, see [`Lexer`] and [`Definition`](definition::Definition)
+pub struct Builder<T, R, S> {
+    rules: Vec<Rule<R>>,
+    skip: Vec<(&'static str, S)>,
+    context: &'static str,
+    settings: Settings<T>,
 }
 
+/// Lexer owning its rules, which may be stateful
+pub type Lexer<T> = Builder<T, Box<dyn MatchRule<T>>, Box<dyn SkipRule>>;
+
 impl<T: Clone> Lexer<T> {
+    pub fn new_filled(rules: Vec<Box<dyn MatchRule<T>>>, skip: Box<dyn SkipRule>) -> Self {
+        Self {
+            rules: rules.into_iter()
+                .map(|rule| Rule { rule, priority: 0, context: DEFAULT_CONTEXT, anchor: Anchor::Any })
+                .collect(),
+            skip: vec![(DEFAULT_CONTEXT, skip)],
+            ..Self::new()
+        }
+    }
+}
+
+impl<T: Clone, R: Stored<Box<dyn Matcher<T>>>, S: Stored<Box<dyn Skip>>> Builder<T, R, S> {
     pub fn new() -> Self {
         Self {
             rules: Vec::new(),
-            skip: None,
+            skip: Vec::new(),
+            context: DEFAULT_CONTEXT,
+            settings: Settings::new(),
         }
     }
 
-    pub fn new_filled(rules: Vec<Box<dyn MatchRule<T>>>, skip: Box<dyn SkipRule>) -> Self {
-        Self {
-            rules,
-            skip: Some(skip),
+    pub fn with_mode(self: &mut Self, mode: Mode) -> &mut Self {
+        self.settings.mode = mode;
+        self
+    }
+
+    /// In recovery mode unknown input is reported as
+    /// [`ErrorKind::UnknownToken`](crate::ErrorKind::UnknownToken) with its
+    /// text, following characters no rule can start with are skipped as part
+    /// of it
+    pub fn with_recovery(self: &mut Self, recover: bool) -> &mut Self {
+        self.settings.recover = recover;
+        self
+    }
+
+    /// Skip rule of the current context, contexts without own rule use the
+    /// default context one
+    pub fn with_skip(self: &mut Self, rule: impl IntoStored<S>) -> &mut Self {
+        let context = self.context;
+
+        self.skip.retain(|(c, _)| *c != context);
+        self.skip.push((context, rule.into_stored()));
+        self
+    }
+
+    /// Rules and skip rule added after this call belong to named context
+    /// (lexer mode), only rules of the current context are tried
+    pub fn in_context(self: &mut Self, context: &'static str) -> &mut Self {
+        self.context = context;
+        self
+    }
+
+    /// Context changes caused by matched tokens, see [`Transition`]
+    pub fn with_transitions(
+        self: &mut Self,
+        transitions: impl Fn(&T) -> Option<Transition> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.settings.transitions = Some(std::sync::Arc::new(transitions));
+        self
+    }
+
+    /// Record matcher states of every parsed character into `trace`
+    pub fn with_trace(self: &mut Self, trace: Trace) -> &mut Self {
+        self.settings.trace = Some(trace);
+        self
+    }
+
+    /// Rules, which are never chosen on texts they accept, the check is
+    /// approximate, see [`Matcher::samples`]
+    pub fn shadowed(self: &mut Self) -> Vec<ShadowedRule> {
+        let mode = self.settings.mode;
+
+        trace::shadowed(self.matchers(), mode)
+    }
+
+    /// Finish building, fails if there are [shadowed](Self::shadowed) rules
+    pub fn build(self: &mut Self) -> Result<&mut Self> {
+        match shadowed_error(self.shadowed()) {
+            Some(error) => Err(error),
+            None => Ok(self),
         }
     }
 
-    pub fn with_skip(self: &mut Self, rule: impl SkipRule + 'static) -> &mut Self {
-        self.skip = Some(Box::new(rule));
+    pub fn push_front(self: &mut Self, rule: impl IntoStored<R>) -> &mut Self {
+        self.push_front_priority(rule, 0)
+    }
+
+    pub fn push_back(self: &mut Self, rule: impl IntoStored<R>) -> &mut Self {
+        self.push_back_priority(rule, 0)
+    }
+
+    /// Add rule with priority used by [`Mode::Longest`], higher one wins
+    pub fn push_front_priority(
+        self: &mut Self,
+        rule: impl IntoStored<R>,
+        priority: isize,
+    ) -> &mut Self {
+        self.rules.insert(0, Rule { rule: rule.into_stored(), priority, context: self.context, anchor: Anchor::Any });
         self
     }
 
-    pub fn push_front(self: &mut Self, rule: impl MatchRule<T> + 'static) -> &mut Self {
-        self.rules.insert(0, Box::new(rule));
+    /// Add rule with priority used by [`Mode::Longest`], higher one wins
+    pub fn push_back_priority(
+        self: &mut Self,
+        rule: impl IntoStored<R>,
+        priority: isize,
+    ) -> &mut Self {
+        self.rules.push(Rule { rule: rule.into_stored(), priority, context: self.context, anchor: Anchor::Any });
         self
     }
 
-    pub fn push_back(self: &mut Self, rule: impl MatchRule<T> + 'static) -> &mut Self {
-        self.rules.push(Box::new(rule));
+    /// Add rule tried only for tokens at given position on their line
+    pub fn push_back_anchored(
+        self: &mut Self,
+        rule: impl IntoStored<R>,
+        anchor: Anchor,
+    ) -> &mut Self {
+        self.rules.push(Rule { rule: rule.into_stored(), priority: 0, context: self.context, anchor });
         self
     }
-}
 
-impl<T: Clone> crate::Lexer<T> for Lexer<T> {
-    fn parse(self: &mut Self, input: impl std::io::Read) -> impl Iterator<Item = Result<T>> {
-        TokenIterator::new(
-            &mut self.rules,
-            self.skip.as_mut(),
-            CodePoints::from(input),
+    fn matchers(self: &mut Self) -> Vec<Context<T>> {
+        contexts(self.rules.iter_mut().map(|rule| {
+            let matcher = rule.rule.create();
+
+            rule.state(matcher)
+        }))
+    }
+
+    fn skips(self: &mut Self, contexts: &[Context<T>]) -> Vec<Option<Box<dyn Skip>>> {
+        skips(contexts, |context| self.skip.iter_mut()
+            .find(|(c, _)| *c == context)
+            .map(|(_, rule)| rule.create())
         )
     }
+
+    /// Token iterator over `chars` with fresh matchers
+    fn tokens<I: Iterator<Item = std::io::Result<char>>>(
+        self: &mut Self,
+        chars: I,
+        source: Option<&str>,
+    ) -> TokenIterator<I, T> {
+        let contexts = self.matchers();
+        let skips = self.skips(&contexts);
+
+        TokenIterator::new(contexts, skips, chars, source.map(std::sync::Arc::from), self.settings.clone())
+    }
+}
+
+impl<T: Clone, R: Stored<Box<dyn Matcher<T>>>, S: Stored<Box<dyn Skip>>> Default for Builder<T, R, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R: Clone, S: Clone> Clone for Builder<T, R, S> {
+    fn clone(&self) -> Self {
+        Self {
+            rules: self.rules.clone(),
+            skip: self.skip.clone(),
+            context: self.context,
+            settings: self.settings.clone(),
+        }
+    }
+}
+
+type Context<T> = (&'static str, Vec<MatcherState<T>>);
+
+fn shadowed_error(shadowed: Vec<ShadowedRule>) -> Option<Error> {
+    (!shadowed.is_empty()).then(|| Error::broken_grammar_string(shadowed.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+    ))
+}
+
+/// Group matchers by context, default one goes first
+fn contexts<T: Clone>(
+    matchers: impl Iterator<Item = (&'static str, MatcherState<T>)>,
+) -> Vec<Context<T>> {
+    let mut out = vec![(DEFAULT_CONTEXT, Vec::new())];
+
+    matchers.for_each(|(context, matcher)| match out.iter_mut().find(|c| c.0 == context) {
+        Some((_, matchers)) => matchers.push(matcher),
+        None => out.push((context, vec![matcher])),
+    });
+
+    out
+}
+
+/// Skip of every context, `get` creates one for context with own rule
+fn skips<T: Clone>(
+    contexts: &[Context<T>],
+    mut get: impl FnMut(&'static str) -> Option<Box<dyn Skip>>,
+) -> Vec<Option<Box<dyn Skip>>> {
+    contexts.iter()
+        .map(|(context, _)| get(context).or_else(|| get(DEFAULT_CONTEXT)))
+        .collect()
+}
+
+impl<T: Clone, R: Stored<Box<dyn Matcher<T>>>, S: Stored<Box<dyn Skip>>> crate::Lexer<T> for Builder<T, R, S> {
+    fn parse_source(
+        self: &mut Self,
+        source: Option<&str>,
+        input: impl std::io::Read,
+    ) -> impl Iterator<Item = Result<Spanned<T>>> {
+        self.tokens(CodePoints::from(input), source)
+    }
+
+    fn parse_slice_source<'a>(
+        self: &mut Self,
+        source: Option<&str>,
+        input: &'a str,
+    ) -> impl Iterator<Item=Result<Lexeme<'a, T>>> {
+        self.tokens(input.chars().map(Ok), source)
+            .map(move |token| token.map(|token| Lexeme::new(input, token)))
+    }
 }
 
 struct MatcherState<T: Clone> {
     matcher: Box<dyn Matcher<T>>,
     last: State<T>,
+    priority: isize,
+    anchor: Anchor,
 }
 
 impl<T: Clone> MatcherState<T> {
     fn new(matcher: Box<dyn Matcher<T>>) -> Self {
+        Self::new_priority(matcher, 0)
+    }
+
+    fn new_priority(matcher: Box<dyn Matcher<T>>, priority: isize) -> Self {
         Self {
             matcher,
             last: State::Matching,
+            priority,
+            anchor: Anchor::Any,
         }
     }
+
+    fn anchored(self: Self, anchor: Anchor) -> Self {
+        Self { anchor, ..self }
+    }
+
+    /// Same as [`Matcher::check`], but without cloning matched value
+    fn step(self: &mut Self, c: Char) -> &State<T> {
+        self.last = self.matcher.check(c);
+        &self.last
+    }
 }
 
 impl<T: Clone> Matcher<T> for MatcherState<T> {
     fn check(self: &mut Self, c: Char) -> State<T> {
-        self.last = self.matcher.check(c);
-        self.last.clone()
+        self.step(c).clone()
     }
 
     fn reset(self: &mut Self) {
         self.last = State::Matching;
         self.matcher.reset();
     }
+
+    fn samples(self: &Self) -> Vec<String> {
+        self.matcher.samples()
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Location {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn new() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn advance(self: &mut Self, c: char) {
+        self.offset += c.len_utf8();
+
+        if '\n' == c {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
 }
 
 pub struct TokenIterator<I, T: Clone>
@@ -114,9 +491,18 @@ where
     I: Iterator<Item = std::io::Result<char>>,
 {
     matchers: Vec<MatcherState<T>>,
+    contexts: Vec<Context<T>>,
+    stack: Vec<usize>,
+    settings: Settings<T>,
     skip: Option<Box<dyn Skip>>,
+    skips: Vec<Option<Box<dyn Skip>>>,
     chars: I,
-    prev: Option<Char>,
+    pending: std::collections::VecDeque<(Char, Location)>,
+    location: Location,
+    file: Option<std::sync::Arc<str>>,
+    queued: Option<Result<Spanned<T>>>,
+    /// Nothing but skipped characters since the last line break
+    line_start: bool,
 }
 
 impl<I, T: Clone> TokenIterator<I, T>
@@ -124,33 +510,223 @@ where
     I: Iterator<Item = std::io::Result<char>>,
 {
     fn new(
-        matchers: &mut [Box<dyn MatchRule<T>>],
-        skip: Option<&mut Box<dyn SkipRule>>,
+        mut contexts: Vec<Context<T>>,
+        mut skips: Vec<Option<Box<dyn Skip>>>,
         iter: I,
+        file: Option<std::sync::Arc<str>>,
+        settings: Settings<T>,
     ) -> Self {
         Self {
-            matchers: matchers.iter_mut()
-                .map(|m| MatcherState::new(m.get()))
-                .collect(),
-            skip: skip.map(|r| r.get()),
+            matchers: std::mem::take(&mut contexts[0].1),
+            contexts,
+            stack: vec![0],
+            settings,
+            skip: skips[0].take(),
+            skips,
             chars: iter,
-            prev: None,
+            pending: std::collections::VecDeque::new(),
+            location: Location::new(),
+            file,
+            queued: None,
+            line_start: true,
         }
     }
 
-    fn next_char(self: &mut Self) -> std::io::Result<Char> {
-        if let Some(c) = self.prev.take() {
+    fn next_char(self: &mut Self) -> std::io::Result<(Char, Location)> {
+        if let Some(c) = self.pending.pop_front() {
             Ok(c)
         } else {
+            let location = self.location;
+
             self.chars.next()
-                .map(|r| r.map(Char::Char))
+                .map(|r| r.map(|c| {
+                    self.location.advance(c);
+                    Char::Char(c)
+                }))
                 .unwrap_or(Ok(Char::EOF))
+                .map(|c| (c, location))
+        }
+    }
+
+    fn span(self: &Self, start: Location, end: Location) -> Span {
+        Span {
+            file: self.file.clone(),
+            offset: start.offset,
+            line: start.line,
+            column: start.column,
+            length: end.offset - start.offset,
         }
     }
 
     fn reset(self: &mut Self) {
         self.matchers.iter_mut().for_each(MatcherState::reset)
     }
+
+    fn record(self: &Self, c: (Char, Location), token: Location, rules: Option<Vec<RuleState>>) {
+        if let (Some(trace), Some(rules)) = (&self.settings.trace, rules) {
+            trace.push(TraceStep {
+                context: self.contexts[*self.stack.last().expect("Default context is never left")].0,
+                token: token.offset,
+                char: match c.0 {
+                    Char::Char(c) => Some(c),
+                    Char::EOF => None,
+                },
+                offset: c.1.offset,
+                line: c.1.line,
+                column: c.1.column,
+                rules,
+            });
+        }
+    }
+
+    /// Apply context transition caused by the token
+    fn transit(self: &mut Self, transition: Transition) -> Result<()> {
+        let from = *self.stack.last().expect("Default context is never left");
+
+        match transition {
+            Transition::Push(name) => {
+                let context = self.contexts.iter()
+                    .position(|c| c.0 == name)
+                    .ok_or_else(|| Error::broken_grammar_string(
+                        format!("Unknown lexer context `{name}`")
+                    ))?;
+
+                self.stack.push(context);
+            },
+            Transition::Pop if 1 < self.stack.len() => {
+                self.stack.pop();
+            },
+            Transition::Pop => return Err(Error::broken_grammar_msg(
+                "Default lexer context can't be left"
+            )),
+        }
+
+        let to = *self.stack.last().expect("Default context is never left");
+
+        if from != to {
+            std::mem::swap(&mut self.matchers, &mut self.contexts[from].1);
+            std::mem::swap(&mut self.matchers, &mut self.contexts[to].1);
+            std::mem::swap(&mut self.skip, &mut self.skips[from]);
+            std::mem::swap(&mut self.skip, &mut self.skips[to]);
+        }
+
+        Ok(())
+    }
+
+    /// Maximal munch: feed characters while any rule is still matching,
+    /// then return characters after the chosen token back to the input
+    fn longest(self: &mut Self, start: Location, backtrack: usize) -> Result<Spanned<T>> {
+        let mut buffer: Vec<(Char, Location)> = Vec::new();
+        let mut best: Option<(usize, isize, T)> = None;
+        let mut active = self.matchers.iter()
+            .filter(|m| matches!(m.last, State::Matching))
+            .count();
+
+        while 0 != active {
+            let c = self.next_char().map_err(|err| Error::io(err)
+                .at(self.span(start, self.location))
+            )?;
+            let length = buffer.len();
+            buffer.push(c);
+
+            let mut row = self.settings.trace.as_ref().map(|_| vec![RuleState::Idle; self.matchers.len()]);
+
+            self.matchers.iter_mut()
+                .enumerate()
+                .filter(|(_, m)| matches!(m.last, State::Matching))
+                .for_each(|(i, m)| match trace::observe(&mut row, i, m.step(c.0)) {
+                    State::Rejected => active -= 1,
+                    State::Matched(_) => {
+                        active -= 1;
+                        best = match best.take() {
+                            Some(b) if b.0 > length || (b.0 == length && b.1 >= m.priority) =>
+                                Some(b),
+                            _ => match std::mem::replace(&mut m.last, State::Rejected) {
+                                State::Matched(v) => Some((length, m.priority, v)),
+                                _ => unreachable!("Was matched"),
+                            },
+                        };
+                    },
+                    State::Matching => {},
+                });
+
+            self.record(c, start, row);
+
+            if let Char::EOF = c.0 {
+                break;
+            }
+
+            if let Some((length, _, _)) = &best {
+                if buffer.len() - length - 1 > backtrack {   // Lookahead is free
+                    break;
+                }
+            }
+        }
+
+        match best {
+            Some((length, _, value)) => {
+                let end = buffer[length].1;
+                buffer.drain(length..).rev().for_each(|c| self.pending.push_front(c));
+
+                Ok(Spanned::new(value, self.span(start, end)))
+            },
+            None if self.settings.recover => Err(self.unknown(buffer, start)),
+            None => Err(match buffer.last() {
+                Some((Char::Char(c), location)) => {
+                    let mut end = *location;
+                    end.advance(*c);
+                    Error::unknown_token().at(self.span(start, end))
+                },
+                _ => Error::unexpected_eof().at(self.span(start, start)),
+            }),
+        }
+    }
+
+    /// Report rejected characters as error token, last of them is returned
+    /// to input as it may start next token
+    fn unknown(self: &mut Self, mut consumed: Vec<(Char, Location)>, start: Location) -> Error {
+        let mut end = match consumed.last() {
+            Some((Char::Char(_), location)) => *location,
+            Some((Char::EOF, location)) => return Error::unexpected_eof()
+                .at(self.span(start, *location)),
+            None => return Error::unexpected_eof().at(self.span(start, start)),
+        };
+
+        if 1 < consumed.len() {
+            self.pending.push_front(consumed.pop().expect("Was checked"));
+        } else if let Some((Char::Char(c), _)) = consumed.last() {
+            end.advance(*c);
+        }
+
+        Error::unknown_token_string(consumed.into_iter()
+            .filter_map(|(c, _)| match c {
+                Char::Char(c) => Some(c),
+                Char::EOF => None,
+            })
+            .collect()
+        ).at(self.span(start, end))
+    }
+
+    /// Merge adjacent unknown tokens into single error
+    fn merge_unknown(self: &mut Self, mut error: Error) -> Error {
+        while let Some(next) = self.next_token() {
+            match (&mut error, next) {
+                (
+                    Error { kind: ErrorKind::UnknownToken(Some(text)), span: Some(span) },
+                    Err(Error { kind: ErrorKind::UnknownToken(Some(next)), span: Some(next_span) }),
+                ) if span.end() == next_span.offset => {
+                    *text += &next;
+                    span.length += next_span.length;
+                },
+                (_, next) => {
+                    self.queued = Some(next);
+                    break;
+                },
+            }
+        }
+
+        error
+    }
 }
 
 enum MatchLock {
@@ -163,14 +739,49 @@ impl<I, T: Clone> Iterator for TokenIterator<I, T>
 where
     I: Iterator<Item = std::io::Result<char>>,
 {
-    type Item = Result<T>;
+    type Item = Result<Spanned<T>>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        match self.queued.take().or_else(|| self.next_token()) {
+            Some(Err(error @ Error { kind: ErrorKind::UnknownToken(_), .. })) if self.settings.recover =>
+                Some(Err(self.merge_unknown(error))),
+            item => item,
+        }
+    }
+}
+
+impl<I, T: Clone> TokenIterator<I, T>
+where
+    I: Iterator<Item = std::io::Result<char>>,
+{
+    fn next_token(self: &mut Self) -> Option<Result<Spanned<T>>> {
+        let token = self.lex_token();
+
+        // Character after the token is returned to input, token ended with
+        // line break if it's the first one on its line
+        self.line_start = self.pending.front().is_some_and(|c| 1 == c.1.column);
+
+        let transition = match (&token, &self.settings.transitions) {
+            (Some(Ok(token)), Some(transitions)) => transitions(&token.value),
+            _ => None,
+        };
+
+        match (token, transition) {
+            (Some(Ok(token)), Some(transition)) => Some(match self.transit(transition) {
+                Ok(()) => Ok(token),
+                Err(error) => Err(error.at(token.span)),
+            }),
+            (token, _) => token,
+        }
+    }
+
+    fn lex_token(self: &mut Self) -> Option<Result<Spanned<T>>> {
         let mut active = self.matchers.len();
         let mut matched = 0;
         let mut error: Option<Error> = None;
-        let mut last = Char::EOF;
+        let mut last = (Char::EOF, self.location);
         let mut empty = false;
+        let mut consumed = Vec::new();
 
         self.reset();
 
@@ -182,30 +793,36 @@ where
                     Err(err) => error = Some(Error::io(err)),
                     Ok(c) => {
                         last = c;
-                        start = match c {
+                        start = match c.0 {
                             Char::EOF => true,
-                            Char::Char(c) => !skip.is_skipping(c),
+                            Char::Char(c) => match skip.is_skipping(c) {
+                                true => {
+                                    self.line_start |= '\n' == c;
+                                    false
+                                },
+                                false => true,
+                            },
                         };
                     },
                 }
             }
 
             if error.is_none() {
-                self.prev = Some(last);
+                self.pending.push_front(last);
             }
 
             Some(skip)
         });
 
+        let mut start = self.location;
+
         if error.is_none() {
             match self.next_char() {
-                Err(err) => {
-                    self.prev = None;
-                    error = Some(Error::io(err));
-                }
+                Err(err) => error = Some(Error::io(err)),
                 Ok(c) => {
-                    self.prev = Some(c);
-                    if let Char::EOF = c {
+                    self.pending.push_front(c);
+                    start = c.1;
+                    if let Char::EOF = c.0 {
                         empty = true;
                     }
                 },
@@ -216,6 +833,18 @@ where
             }
         }
 
+        let line_start = self.line_start;
+        self.matchers.iter_mut()
+            .filter(|m| !m.anchor.allows(line_start))
+            .for_each(|m| {
+                m.last = State::Rejected;
+                active -= 1;
+            });
+
+        if let (None, Mode::Longest { backtrack }) = (&error, self.settings.mode) {
+            return Some(self.longest(start, backtrack));
+        }
+
         while error.is_none() && 0 == matched && 0 != active {
             let mut matching = MatchLock::None;
 
@@ -223,18 +852,20 @@ where
                 Err(err) => error = Some(Error::io(err)),
                 Ok(c) => {
                     last = c;
+                    if self.settings.recover {
+                        consumed.push(c);
+                    }
+                    let mut row = self.settings.trace.as_ref()
+                        .map(|_| vec![RuleState::Idle; self.matchers.len()]);
+
                     self.matchers.iter_mut()
-                        .filter(|m| match m.last {
-                            State::Rejected => false,
-                            _ => true,
-                        })
-                        .for_each(|m| match m.check(c) {
+                        .enumerate()
+                        .filter(|(_, m)| !matches!(m.last, State::Rejected))
+                        .for_each(|(i, m)| match trace::observe(&mut row, i, m.step(c.0)) {
                             State::Rejected => active -= 1,
                             State::Matched(_) => {
                                 if let MatchLock::Matching = matching {
-                                    error = Some(Error::broken_grammar_msg(
-                                        "Unreacheable higher order rule found"
-                                    ));
+                                    error = Some(Error::broken_grammar_msg(trace::UNREACHABLE));
                                 } else {
                                     matching = MatchLock::Matched;
                                     active -= 1;
@@ -247,25 +878,34 @@ where
                                 }
                             }
                         });
+
+                    self.record(c, start, row);
                 }
             }
         }
 
         if let Some(error) = error {
-            Some(Err(error))
+            Some(Err(error.at(self.span(start, last.1))))
+        } else if 0 == matched && self.settings.recover {
+            Some(Err(self.unknown(consumed, start)))
         } else if 0 == matched {
-            Some(Err(match last {
-                Char::EOF => Error::unexpected_eof(),
-                _ => Error::unknown_token(),
+            Some(Err(match last.0 {
+                Char::EOF => Error::unexpected_eof().at(self.span(start, last.1)),
+                Char::Char(c) => {
+                    let mut end = last.1;
+                    end.advance(c);
+                    Error::unknown_token().at(self.span(start, end))
+                },
             }))
         } else {
-            self.prev = Some(last);
-            Some(Ok(
-                self.matchers.iter().find_map(|m| match &m.last {
+            self.pending.push_front(last);
+            Some(Ok(Spanned::new(
+                self.matchers.iter_mut().find_map(|m| match std::mem::replace(&mut m.last, State::Rejected) {
                     State::Matched(v) => Some(v),
                     _ => None,
-                }).expect("Counter isn't 0").clone()
-            ))
+                }).expect("Counter isn't 0"),
+                self.span(start, last.1),
+            )))
         }
     }
 }
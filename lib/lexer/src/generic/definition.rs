@@ -0,0 +1,124 @@
+
+use std::sync::Arc;
+
+use unicode_reader::CodePoints;
+
+use crate::{Lexeme, Result, Spanned};
+use super::{
+    Builder, IntoStored, MatchRule, Matcher, Skip, SkipRule, Stored, TokenIterator, contexts, skips,
+};
+
+/// Factory of matchers which can be shared between threads
+pub trait SharedMatchRule<T: Clone>: Send + Sync {
+    fn get(self: &Self) -> Box<dyn Matcher<T>>;
+}
+
+/// Factory of skips which can be shared between threads
+pub trait SharedSkipRule: Send + Sync {
+    fn get(self: &Self) -> Box<dyn Skip>;
+}
+
+impl<T: Clone, F: Matcher<T> + 'static, C: Fn() -> F + Send + Sync> SharedMatchRule<T> for C {
+    fn get(self: &Self) -> Box<dyn Matcher<T>> {
+        Box::new(self())
+    }
+}
+
+impl<T: Skip + 'static, C: Fn() -> T + Send + Sync> SharedSkipRule for C {
+    fn get(self: &Self) -> Box<dyn Skip> {
+        Box::new(self())
+    }
+}
+
+/// Adapter sharing any [`MatchRule`] or [`SkipRule`] between threads,
+/// calls to the wrapped rule are serialized
+pub struct Shared<R>(std::sync::Mutex<R>);
+
+impl<R> Shared<R> {
+    pub fn new(rule: R) -> Self {
+        Self(std::sync::Mutex::new(rule))
+    }
+}
+
+impl<T: Clone, R: MatchRule<T> + Send> SharedMatchRule<T> for Shared<R> {
+    fn get(self: &Self) -> Box<dyn Matcher<T>> {
+        self.0.lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get()
+    }
+}
+
+impl<R: SkipRule + Send> SharedSkipRule for Shared<R> {
+    fn get(self: &Self) -> Box<dyn Skip> {
+        self.0.lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get()
+    }
+}
+
+impl<T: Clone> Stored<Box<dyn Matcher<T>>> for Arc<dyn SharedMatchRule<T>> {
+    fn create(self: &mut Self) -> Box<dyn Matcher<T>> {
+        self.get()
+    }
+}
+
+impl Stored<Box<dyn Skip>> for Arc<dyn SharedSkipRule> {
+    fn create(self: &mut Self) -> Box<dyn Skip> {
+        self.get()
+    }
+}
+
+impl<T: Clone, R: SharedMatchRule<T> + 'static> IntoStored<Arc<dyn SharedMatchRule<T>>> for R {
+    fn into_stored(self: Self) -> Arc<dyn SharedMatchRule<T>> {
+        Arc::new(self)
+    }
+}
+
+impl<R: SharedSkipRule + 'static> IntoStored<Arc<dyn SharedSkipRule>> for R {
+    fn into_stored(self: Self) -> Arc<dyn SharedSkipRule> {
+        Arc::new(self)
+    }
+}
+
+/// Immutable lexer definition
+///
+/// Unlike [`Lexer`](super::Lexer) rules only create matchers, so definition
+/// is cheap to clone and `&Definition` lexes from several threads at once.
+/// Every input gets fresh matchers.
+pub type Definition<T> = Builder<T, Arc<dyn SharedMatchRule<T>>, Arc<dyn SharedSkipRule>>;
+
+impl<T: Clone> Definition<T> {
+    /// Same as [`Builder::tokens`], but leaves definition untouched
+    fn shared_tokens<I: Iterator<Item = std::io::Result<char>>>(
+        self: &Self,
+        chars: I,
+        source: Option<&str>,
+    ) -> TokenIterator<I, T> {
+        let contexts = contexts(self.rules.iter().map(|rule| rule.state(rule.rule.get())));
+        let skips = skips(&contexts, |context| self.skip.iter()
+            .find(|(c, _)| *c == context)
+            .map(|(_, rule)| rule.get())
+        );
+
+        TokenIterator::new(contexts, skips, chars, source.map(Arc::from), self.settings.clone())
+    }
+}
+
+impl<T: Clone> crate::Lexer<T> for &Definition<T> {
+    fn parse_source(
+        self: &mut Self,
+        source: Option<&str>,
+        input: impl std::io::Read,
+    ) -> impl Iterator<Item = Result<Spanned<T>>> {
+        self.shared_tokens(CodePoints::from(input), source)
+    }
+
+    fn parse_slice_source<'a>(
+        self: &mut Self,
+        source: Option<&str>,
+        input: &'a str,
+    ) -> impl Iterator<Item=Result<Lexeme<'a, T>>> {
+        self.shared_tokens(input.chars().map(Ok), source)
+            .map(move |token| token.map(|token| Lexeme::new(input, token)))
+    }
+}
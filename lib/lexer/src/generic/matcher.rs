@@ -1,4 +1,9 @@
 
+use std::sync::Arc;
+
+use regex_automata::{Anchored, dfa::{Automaton, dense}, util::{primitives::StateID, start}};
+
+use crate::{Error, Result};
 use super::{Matcher, MatchRule, State, Char, MatcherState};
 
 impl<T: Clone, F: Matcher<T> + 'static, C: FnMut() -> F> MatchRule<T> for C {
@@ -17,6 +22,7 @@ impl<T: Clone, F: FnMut(Char) -> State<T>> Matcher<T> for F {
 
 pub struct GroupMatcher<T: Clone> {
     matchers: Vec<MatcherState<T>>,
+    ordered: bool,
     cooked: bool,
 }
 
@@ -24,6 +30,7 @@ impl<T: Clone> GroupMatcher<T> {
     pub fn new() -> Self {
         Self {
             matchers: Vec::new(),
+            ordered: false,
             cooked: false,
         }
     }
@@ -31,10 +38,20 @@ impl<T: Clone> GroupMatcher<T> {
     pub fn new_matchers(matchers: Vec<Box<dyn Matcher<T>>>) -> Self {
         Self {
             matchers: matchers.into_iter().map(MatcherState::new).collect(),
+            ordered: false,
             cooked: false,
         }
     }
 
+    /// Group, where matchers finished on the same char don't compete,
+    /// but the first added one wins
+    pub fn new_ordered(matchers: Vec<Box<dyn Matcher<T>>>) -> Self {
+        Self {
+            ordered: true,
+            ..Self::new_matchers(matchers)
+        }
+    }
+
     pub fn add(self: &mut Self, matcher: impl Matcher<T> + 'static) -> &mut Self {
         self.matchers.push(MatcherState::new(Box::new(matcher)));
         self
@@ -55,7 +72,7 @@ impl<T: Clone> Matcher<T> for GroupMatcher<T> {
                     match (&s, acc.2) {
                         (State::Matched(_), false) => {
                             if acc.0.is_some() {        // Matchers in same
-                                acc.2 = true;           // group can't compete,
+                                acc.2 = !self.ordered;  // group can't compete,
                             } else {                    // if so - it's broken
                                 acc.0 = Some(s);        // grammar
                             }
@@ -85,12 +102,19 @@ impl<T: Clone> Matcher<T> for GroupMatcher<T> {
         self.cooked = false;
         self.matchers.iter_mut().for_each(MatcherState::reset);
     }
+
+    fn samples(self: &Self) -> Vec<String> {
+        self.matchers.iter().flat_map(|m| m.samples()).collect()
+    }
 }
 
+/// Matchers applied one after another
+///
+/// Char, which finished previous matcher, is passed to the next one.
+/// Matched value is the value of the last matcher.
 pub struct SequenceMatcher<T: Clone> {
     matchers: Vec<Box<dyn Matcher<T>>>,
     last: usize,
-    status: Option<State<T>>,
     cooked: bool,
 }
 
@@ -99,7 +123,6 @@ impl<T: Clone> SequenceMatcher<T> {
         Self {
             matchers: Vec::new(),
             last: 0,
-            status: None,
             cooked: false,
         }
     }
@@ -108,7 +131,6 @@ impl<T: Clone> SequenceMatcher<T> {
         Self {
             matchers,
             last: 0,
-            status: None,
             cooked: false,
         }
     }
@@ -117,44 +139,32 @@ impl<T: Clone> SequenceMatcher<T> {
         self.matchers.push(Box::new(matcher));
         self
     }
-
-    fn clear(self: &mut Self) {
-        self.last = 0;
-        self.cooked = false;
-        self.status = None;
-        self.matchers.iter_mut().for_each(|m| m.reset());
-    }
 }
 
 impl<T: Clone> Matcher<T> for SequenceMatcher<T> {
     fn check(self: &mut Self, c: Char) -> State<T> {
-        self.status = Some(if self.cooked {
-            State::Rejected
-        } else if self.last >= self.matchers.len() {
-            self.cooked = true;
-            State::Rejected
-        } else {
-            let state = self.matchers.get_mut(self.last)
-                .expect("Index was checked")
-                .check(c);
-
-            match state {
-                State::Matched(_) => self.last += 1,
-                State::Rejected => self.cooked = true,
-                _ => {},
+        let res = loop {
+            let Some(matcher) = self.matchers.get_mut(self.last).filter(|_| !self.cooked) else {
+                break State::Rejected
+            };
+
+            match matcher.check(c) {
+                State::Matched(_) if self.last + 1 < self.matchers.len() => self.last += 1,
+                res => break res,
             }
+        };
 
-            state
-        });
-        self.status.as_ref().expect("Was set upper").clone()
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
     }
 
     fn reset(self: &mut Self) {
-        if self.cooked || self.last >= self.matchers.len() {
-            self.clear()
-        } else if let Some(State::Matching) = self.status {
-            self.clear()
-        }
+        self.last = 0;
+        self.cooked = false;
+        self.matchers.iter_mut().for_each(|m| m.reset());
     }
 }
 
@@ -235,7 +245,7 @@ macro_rules! sequence {
 
             $crate::sequence_unpack!(sequence, $($input)+);
 
-            group
+            sequence
         }
     };
 }
@@ -326,6 +336,10 @@ where
         self.last = 0;
         self.cooked = false;
     }
+
+    fn samples(self: &Self) -> Vec<String> {
+        vec![self.chars.iter().collect()]
+    }
 }
 
 pub struct AllowedCharMatcher<T, FG, FA>
@@ -409,23 +423,25 @@ impl RadixState {
     }
 }
 
+/// Matcher of integer with optional `-` and `0b`, `0o` or `0x` prefix, value
+/// saturates at `i128` bounds instead of wrapping
 pub struct IntegerMatcher<T, F>
 where
     T: Clone,
-    F: FnMut(i64) -> T
+    F: FnMut(i128) -> T
 {
     radix: RadixState,
     cooked: bool,
     any: bool,
-    number: i64,
+    number: i128,
     resf: F,
-    sign: i64,
+    sign: i128,
 }
 
 impl<T, F> IntegerMatcher<T, F>
 where
     T: Clone,
-    F: FnMut(i64) -> T
+    F: FnMut(i128) -> T
 {
     pub fn new(resf: F) -> Self {
         Self {
@@ -442,7 +458,7 @@ where
 impl<T, F> Matcher<T> for IntegerMatcher<T, F>
 where
     T: Clone,
-    F: FnMut(i64) -> T
+    F: FnMut(i128) -> T
 {
     fn check(self: &mut Self, c: Char) -> State<T> {
         if self.cooked {
@@ -478,7 +494,7 @@ where
                         _ => {
                             if let Some(n) = c.to_digit(r) {
                                 self.radix = RadixState::None;
-                                self.number = n as i64;
+                                self.number = n as i128;
                                 State::Matching
                             } else {
                                 State::Matched((self.resf)(0))
@@ -499,14 +515,14 @@ where
                         State::Matching
                     } else if let Some(n) = c.to_digit(r) {
                         self.any = true;
-                        self.number = n as i64;
+                        self.number = n as i128;
                         State::Matching
                     } else {
                         State::Rejected
                     }
                 } else {
                     if let Some(n) = c.to_digit(r) {
-                        self.number = self.number * (r as i64) + n as i64;
+                        self.number = self.number.saturating_mul(r as i128).saturating_add(n as i128);
                         State::Matching
                     } else {
                         State::Matched((self.resf)(self.number * self.sign))
@@ -531,3 +547,1048 @@ where
     }
 }
 
+/// Value of a word and characters which can't follow it
+type Word<T> = (T, fn(Char) -> bool);
+
+struct TrieNode<T: Clone> {
+    next: Vec<(char, usize)>,
+    value: Option<Word<T>>,
+}
+
+impl<T: Clone> TrieNode<T> {
+    fn new() -> Self {
+        Self {
+            next: Vec::new(),
+            value: None,
+        }
+    }
+
+    fn get(self: &Self, c: char) -> Option<usize> {
+        self.next.binary_search_by_key(&c, |(c, _)| *c)
+            .ok()
+            .map(|i| self.next[i].1)
+    }
+}
+
+/// Matcher of a fixed set of words compiled into a trie
+///
+/// Behaves like group of [`CharSequenceMatcher`]s, but walks single trie
+/// node per character instead of checking every word.
+pub struct KeywordSetMatcher<T: Clone> {
+    nodes: Vec<TrieNode<T>>,
+    current: Option<usize>,
+}
+
+impl<T: Clone> KeywordSetMatcher<T> {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![TrieNode::new()],
+            current: Some(0),
+        }
+    }
+
+    /// Add word, it's matched only if following character isn't `allowed`,
+    /// word added again keeps its first value like in a group of matchers
+    pub fn add(self: &mut Self, word: &str, value: T, allowed: fn(Char) -> bool) -> &mut Self {
+        let node = word.chars().fold(0, |node, c| match self.nodes[node].get(c) {
+            Some(next) => next,
+            None => {
+                let next = self.nodes.len();
+                self.nodes.push(TrieNode::new());
+
+                let edges = &mut self.nodes[node].next;
+                let i = edges.partition_point(|(e, _)| *e < c);
+                edges.insert(i, (c, next));
+
+                next
+            },
+        });
+
+        self.nodes[node].value.get_or_insert((value, allowed));
+        self
+    }
+}
+
+impl<T: Clone> Default for KeywordSetMatcher<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Matcher<T> for KeywordSetMatcher<T> {
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        let node = match self.current {
+            None => return State::Rejected,
+            Some(node) => &self.nodes[node],
+        };
+
+        let (res, next) = match (&node.value, c) {
+            (Some((value, allowed)), c) if !allowed(c) => (State::Matched(value.clone()), None),
+            (_, Char::Char(c)) => match node.get(c) {
+                Some(next) => (State::Matching, Some(next)),
+                None => (State::Rejected, None),
+            },
+            (_, Char::EOF) => (State::Rejected, None),
+        };
+
+        self.current = next;
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.current = Some(0);
+    }
+
+    fn samples(self: &Self) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut stack = vec![(0, String::new())];
+
+        while let Some((node, word)) = stack.pop() {
+            if self.nodes[node].value.is_some() {
+                out.push(word.clone());
+            }
+
+            self.nodes[node].next.iter()
+                .rev()
+                .for_each(|(c, next)| stack.push((*next, format!("{word}{c}"))));
+        }
+
+        out
+    }
+}
+
+enum Escape {
+    Backslash,
+    Hex(u32, u8),
+    UnicodeOpen,
+    Unicode(u32, u8),
+}
+
+enum Unescaped {
+    Pending(Escape),
+    Char(char),
+    Invalid,
+}
+
+impl Escape {
+    /// Feed character following backslash
+    fn check(self: Self, c: char) -> Unescaped {
+        match self {
+            Escape::Backslash => match c {
+                'n' => Unescaped::Char('\n'),
+                't' => Unescaped::Char('\t'),
+                'r' => Unescaped::Char('\r'),
+                '0' => Unescaped::Char('\0'),
+                '\\' | '\'' | '"' => Unescaped::Char(c),
+                'x' => Unescaped::Pending(Escape::Hex(0, 0)),
+                'u' => Unescaped::Pending(Escape::UnicodeOpen),
+                _ => Unescaped::Invalid,
+            },
+            Escape::Hex(value, n) => match c.to_digit(16) {
+                None => Unescaped::Invalid,
+                Some(d) if 1 == n => char::from_u32(value * 16 + d)
+                    .filter(char::is_ascii)
+                    .map(Unescaped::Char)
+                    .unwrap_or(Unescaped::Invalid),
+                Some(d) => Unescaped::Pending(Escape::Hex(value * 16 + d, n + 1)),
+            },
+            Escape::UnicodeOpen => match c {
+                '{' => Unescaped::Pending(Escape::Unicode(0, 0)),
+                _ => Unescaped::Invalid,
+            },
+            Escape::Unicode(value, n) => match (c, c.to_digit(16)) {
+                ('}', _) if 0 < n => char::from_u32(value)
+                    .map(Unescaped::Char)
+                    .unwrap_or(Unescaped::Invalid),
+                (_, Some(d)) if 6 > n => Unescaped::Pending(Escape::Unicode(value * 16 + d, n + 1)),
+                _ => Unescaped::Invalid,
+            },
+        }
+    }
+}
+
+enum QuotedState {
+    None,
+    Body,
+    Escape(Escape),
+    Closed,
+}
+
+/// Matcher of quoted string literal with escapes
+///
+/// Supported escapes are `\n`, `\t`, `\r`, `\0`, `\\`, `\'`, `\"`, `\xHH`
+/// (ASCII only) and `\u{H..}`, unescaped string is passed to `resf`. Line
+/// breaks inside of literal aren't allowed.
+pub struct StringMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    quote: char,
+    state: QuotedState,
+    content: String,
+    resf: F,
+    cooked: bool,
+}
+
+impl<T, F> StringMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    pub fn new(quote: char, resf: F) -> Self {
+        Self {
+            quote,
+            state: QuotedState::None,
+            content: String::new(),
+            resf,
+            cooked: false,
+        }
+    }
+}
+
+impl<T, F> Matcher<T> for StringMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let res = match (std::mem::replace(&mut self.state, QuotedState::None), c) {
+            (QuotedState::Closed, _) => State::Matched((self.resf)(&self.content)),
+            (_, Char::EOF) | (QuotedState::Body, Char::Char('\n')) => State::Rejected,
+            (QuotedState::None, Char::Char(c)) => {
+                if self.quote == c {
+                    self.state = QuotedState::Body;
+                    State::Matching
+                } else {
+                    State::Rejected
+                }
+            },
+            (QuotedState::Body, Char::Char(c)) => {
+                self.state = if self.quote == c {
+                    QuotedState::Closed
+                } else if '\\' == c {
+                    QuotedState::Escape(Escape::Backslash)
+                } else {
+                    self.content.push(c);
+                    QuotedState::Body
+                };
+                State::Matching
+            },
+            (QuotedState::Escape(escape), Char::Char(c)) => match escape.check(c) {
+                Unescaped::Pending(escape) => {
+                    self.state = QuotedState::Escape(escape);
+                    State::Matching
+                },
+                Unescaped::Char(c) => {
+                    self.content.push(c);
+                    self.state = QuotedState::Body;
+                    State::Matching
+                },
+                Unescaped::Invalid => State::Rejected,
+            },
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.state = QuotedState::None;
+        self.content.clear();
+        self.cooked = false;
+    }
+}
+
+enum CharState {
+    None,
+    Body,
+    Escape(Escape),
+    Close(char),
+    Closed(char),
+}
+
+/// Matcher of single quoted character literal, escapes are the same as for
+/// [`StringMatcher`]
+pub struct CharMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(char) -> T,
+{
+    state: CharState,
+    resf: F,
+    cooked: bool,
+}
+
+impl<T, F> CharMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(char) -> T,
+{
+    pub fn new(resf: F) -> Self {
+        Self {
+            state: CharState::None,
+            resf,
+            cooked: false,
+        }
+    }
+}
+
+impl<T, F> Matcher<T> for CharMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(char) -> T,
+{
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let (res, state) = match (std::mem::replace(&mut self.state, CharState::None), c) {
+            (CharState::Closed(value), _) => (State::Matched((self.resf)(value)), CharState::None),
+            (_, Char::EOF) => (State::Rejected, CharState::None),
+            (CharState::None, Char::Char('\'')) => (State::Matching, CharState::Body),
+            (CharState::Body, Char::Char('\\')) => (State::Matching, CharState::Escape(Escape::Backslash)),
+            (CharState::Body, Char::Char('\'' | '\n')) => (State::Rejected, CharState::None),
+            (CharState::Body, Char::Char(c)) => (State::Matching, CharState::Close(c)),
+            (CharState::Escape(escape), Char::Char(c)) => match escape.check(c) {
+                Unescaped::Pending(escape) => (State::Matching, CharState::Escape(escape)),
+                Unescaped::Char(c) => (State::Matching, CharState::Close(c)),
+                Unescaped::Invalid => (State::Rejected, CharState::None),
+            },
+            (CharState::Close(value), Char::Char('\'')) => (State::Matching, CharState::Closed(value)),
+            _ => (State::Rejected, CharState::None),
+        };
+
+        self.state = state;
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.state = CharState::None;
+        self.cooked = false;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum FloatState {
+    None,
+    Sign,
+    Integer,
+    Dot,
+    LeadingDot,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+/// Matcher of floating point literal
+///
+/// Literal must have fraction or exponent to be distinguished from integer:
+/// `1.5`, `1.`, `.5`, `-2e10`, `6.02E+23`.
+pub struct FloatMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(f64) -> T,
+{
+    state: FloatState,
+    text: String,
+    resf: F,
+    cooked: bool,
+}
+
+impl<T, F> FloatMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(f64) -> T,
+{
+    pub fn new(resf: F) -> Self {
+        Self {
+            state: FloatState::None,
+            text: String::new(),
+            resf,
+            cooked: false,
+        }
+    }
+}
+
+impl<T, F> Matcher<T> for FloatMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(f64) -> T,
+{
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let digit = match c {
+            Char::Char(c) => c.is_ascii_digit(),
+            Char::EOF => false,
+        };
+        let exponent = match c {
+            Char::Char('e' | 'E') => true,
+            _ => false,
+        };
+
+        let next = match (self.state, c) {
+            (FloatState::None, Char::Char('-')) => Some(FloatState::Sign),
+            (FloatState::None | FloatState::Sign, Char::Char('.')) => Some(FloatState::LeadingDot),
+            (FloatState::None | FloatState::Sign | FloatState::Integer, _) if digit =>
+                Some(FloatState::Integer),
+            (FloatState::Integer, Char::Char('.')) => Some(FloatState::Dot),
+            (FloatState::Dot | FloatState::LeadingDot | FloatState::Fraction, _) if digit =>
+                Some(FloatState::Fraction),
+            (FloatState::Integer | FloatState::Dot | FloatState::Fraction, _) if exponent =>
+                Some(FloatState::Exponent),
+            (FloatState::Exponent, Char::Char('+' | '-')) => Some(FloatState::ExponentSign),
+            (FloatState::Exponent | FloatState::ExponentSign | FloatState::ExponentDigits, _) if digit =>
+                Some(FloatState::ExponentDigits),
+            _ => None,
+        };
+
+        let res = match (next, self.state) {
+            (Some(state), _) => {
+                if let Char::Char(c) = c {
+                    self.text.push(c);
+                }
+                self.state = state;
+                State::Matching
+            },
+            (None, FloatState::Dot | FloatState::Fraction | FloatState::ExponentDigits) =>
+                match self.text.parse() {
+                    Ok(value) => State::Matched((self.resf)(value)),
+                    Err(_) => State::Rejected,
+                },
+            (None, _) => State::Rejected,
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.state = FloatState::None;
+        self.text.clear();
+        self.cooked = false;
+    }
+}
+
+/// Matcher of everything from `prefix` up to the end of line
+///
+/// Text after prefix is passed to `resf`, line break isn't part of the token.
+pub struct RestOfLineMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    prefix: Vec<char>,
+    last: usize,
+    content: String,
+    resf: F,
+    cooked: bool,
+}
+
+impl<T, F> RestOfLineMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    pub fn new(prefix: &str, resf: F) -> Self {
+        Self {
+            prefix: prefix.chars().collect(),
+            last: 0,
+            content: String::new(),
+            resf,
+            cooked: false,
+        }
+    }
+}
+
+impl<T, F> Matcher<T> for RestOfLineMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let res = if self.last < self.prefix.len() {
+            match c {
+                Char::Char(c) if self.prefix[self.last] == c => {
+                    self.last += 1;
+                    State::Matching
+                },
+                _ => State::Rejected,
+            }
+        } else {
+            match c {
+                Char::EOF | Char::Char('\n') => State::Matched(
+                    (self.resf)(self.content.strip_suffix('\r').unwrap_or(&self.content))
+                ),
+                Char::Char(c) => {
+                    self.content.push(c);
+                    State::Matching
+                },
+            }
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.last = 0;
+        self.content.clear();
+        self.cooked = false;
+    }
+}
+
+
+/// Conversion of pattern items into matchers
+///
+/// String and char items are matched literally.
+pub trait IntoPattern {
+    fn into_pattern(self: Self) -> Box<dyn Matcher<()>>;
+}
+
+impl IntoPattern for &str {
+    fn into_pattern(self: Self) -> Box<dyn Matcher<()>> {
+        Box::new(CharSequenceMatcher::new(self, || (), |_| false))
+    }
+}
+
+impl IntoPattern for char {
+    fn into_pattern(self: Self) -> Box<dyn Matcher<()>> {
+        Box::new(CharClassMatcher::new(move |c| c == self))
+    }
+}
+
+impl<M: Matcher<()> + 'static> IntoPattern for M {
+    fn into_pattern(self: Self) -> Box<dyn Matcher<()>> {
+        Box::new(self)
+    }
+}
+
+/// Matcher of single char accepted by `class`
+pub struct CharClassMatcher<F: FnMut(char) -> bool> {
+    class: F,
+    taken: bool,
+    cooked: bool,
+}
+
+impl<F: FnMut(char) -> bool> CharClassMatcher<F> {
+    pub fn new(class: F) -> Self {
+        Self {
+            class,
+            taken: false,
+            cooked: false,
+        }
+    }
+}
+
+impl CharClassMatcher<fn(char) -> bool> {
+    /// Matcher of any char
+    pub fn any() -> Self {
+        Self::new(|_| true)
+    }
+}
+
+impl<F: FnMut(char) -> bool> Matcher<()> for CharClassMatcher<F> {
+    fn check(self: &mut Self, c: Char) -> State<()> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let res = match c {
+            _ if self.taken => State::Matched(()),
+            Char::Char(c) if (self.class)(c) => {
+                self.taken = true;
+                State::Matching
+            },
+            _ => State::Rejected,
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.taken = false;
+        self.cooked = false;
+    }
+}
+
+/// Matcher of pattern or nothing
+///
+/// Like every other combinator it is greedy and never backtracks: once
+/// pattern consumed a char, it has to match.
+pub struct OptionalMatcher {
+    matcher: Box<dyn Matcher<()>>,
+    started: bool,
+    cooked: bool,
+}
+
+impl OptionalMatcher {
+    pub fn new(matcher: impl IntoPattern) -> Self {
+        Self {
+            matcher: matcher.into_pattern(),
+            started: false,
+            cooked: false,
+        }
+    }
+}
+
+impl Matcher<()> for OptionalMatcher {
+    fn check(self: &mut Self, c: Char) -> State<()> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let res = match self.matcher.check(c) {
+            State::Rejected if !self.started => State::Matched(()),
+            State::Matching => {
+                self.started = true;
+                State::Matching
+            },
+            res => res,
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.matcher.reset();
+        self.started = false;
+        self.cooked = false;
+    }
+}
+
+/// Matcher of pattern repeated from `min` to `max` times
+///
+/// Repetition is greedy, it stops on the first char pattern rejects
+/// without consuming anything.
+pub struct RepeatMatcher {
+    matcher: Box<dyn Matcher<()>>,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    started: bool,
+    cooked: bool,
+}
+
+impl RepeatMatcher {
+    pub fn new(matcher: impl IntoPattern, min: usize, max: Option<usize>) -> Self {
+        Self {
+            matcher: matcher.into_pattern(),
+            min,
+            max,
+            count: 0,
+            started: false,
+            cooked: false,
+        }
+    }
+
+    fn finish(self: &Self) -> State<()> {
+        if self.count >= self.min {
+            State::Matched(())
+        } else {
+            State::Rejected
+        }
+    }
+}
+
+impl Matcher<()> for RepeatMatcher {
+    fn check(self: &mut Self, c: Char) -> State<()> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let res = loop {
+            if !self.started && self.max.is_some_and(|max| self.count >= max) {
+                break self.finish()
+            }
+
+            match self.matcher.check(c) {
+                State::Matching => {
+                    self.started = true;
+                    break State::Matching
+                },
+                State::Matched(_) if self.started => {
+                    self.count += 1;
+                    self.started = false;
+                    self.matcher.reset();
+                },
+                State::Rejected if self.started => break State::Rejected,
+                _ => break self.finish(),
+            }
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.matcher.reset();
+        self.count = 0;
+        self.started = false;
+        self.cooked = false;
+    }
+}
+
+/// Matcher of pattern, unless `excluded` matches exactly the same text
+///
+/// Decision is made only when pattern finishes, so it suits for words like
+/// identifiers except keywords. Single chars are better excluded with
+/// [`CharClassMatcher`].
+pub struct ExceptMatcher {
+    matcher: Box<dyn Matcher<()>>,
+    excluded: MatcherState<()>,
+    cooked: bool,
+}
+
+impl ExceptMatcher {
+    pub fn new(matcher: impl IntoPattern, excluded: impl IntoPattern) -> Self {
+        Self {
+            matcher: matcher.into_pattern(),
+            excluded: MatcherState::new(excluded.into_pattern()),
+            cooked: false,
+        }
+    }
+}
+
+impl Matcher<()> for ExceptMatcher {
+    fn check(self: &mut Self, c: Char) -> State<()> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let excluded = match self.excluded.last {
+            State::Matching => matches!(self.excluded.step(c), State::Matched(_)),
+            _ => false,
+        };
+
+        let res = match self.matcher.check(c) {
+            State::Matched(_) if excluded => State::Rejected,
+            res => res,
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.matcher.reset();
+        self.excluded.reset();
+        self.cooked = false;
+    }
+}
+
+/// Matcher, which turns text matched by pattern into value with `resf`
+pub struct CaptureMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    matcher: Box<dyn Matcher<()>>,
+    text: String,
+    resf: F,
+    cooked: bool,
+}
+
+impl<T, F> CaptureMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    pub fn new(matcher: impl IntoPattern, resf: F) -> Self {
+        Self {
+            matcher: matcher.into_pattern(),
+            text: String::new(),
+            resf,
+            cooked: false,
+        }
+    }
+}
+
+impl<T, F> Matcher<T> for CaptureMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        let res = match self.matcher.check(c) {
+            State::Matching => {
+                if let Char::Char(c) = c {
+                    self.text.push(c);
+                }
+
+                State::Matching
+            },
+            State::Matched(_) => State::Matched((self.resf)(&self.text)),
+            State::Rejected => State::Rejected,
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.matcher.reset();
+        self.text.clear();
+        self.cooked = false;
+    }
+}
+
+#[macro_export]
+/// Pattern DSL: sequence of items, each is a string, a char or a matcher
+///
+/// # Usage
+/// ```,ignore
+/// capture!(|text| Token::Hex(text.into()) =>
+///     "0x",
+///     some!(CharClassMatcher::new(|c| c.is_ascii_hexdigit())),
+///     optional!(one_of!('u', 'l')),
+/// )
+/// ```
+macro_rules! pattern {
+    ( $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::SequenceMatcher::<()>::new_matchers(vec![
+            $( $crate::generic::matcher::IntoPattern::into_pattern($item) ),+
+        ])
+    };
+}
+
+#[macro_export]
+/// Pattern, which turns matched text into value, see [`pattern!`]
+macro_rules! capture {
+    ( $resf:expr => $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::CaptureMatcher::new($crate::pattern!($($item),+), $resf)
+    };
+}
+
+#[macro_export]
+/// Alternation, where the earlier item wins on tie, see [`pattern!`]
+macro_rules! one_of {
+    ( $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::GroupMatcher::<()>::new_ordered(vec![
+            $( $crate::generic::matcher::IntoPattern::into_pattern($item) ),+
+        ])
+    };
+}
+
+#[macro_export]
+/// Pattern or nothing, see [`pattern!`]
+macro_rules! optional {
+    ( $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::OptionalMatcher::new($crate::pattern!($($item),+))
+    };
+}
+
+#[macro_export]
+/// Pattern repeated zero or more times, see [`pattern!`]
+macro_rules! many {
+    ( $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::RepeatMatcher::new($crate::pattern!($($item),+), 0, None)
+    };
+}
+
+#[macro_export]
+/// Pattern repeated one or more times, see [`pattern!`]
+macro_rules! some {
+    ( $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::RepeatMatcher::new($crate::pattern!($($item),+), 1, None)
+    };
+}
+
+#[macro_export]
+/// Pattern repeated from `min` to `max` times, see [`pattern!`]
+///
+/// # Usage
+/// ```,ignore
+/// repeat!(1, 3; CharClassMatcher::new(|c| c.is_ascii_digit()))
+/// ```
+macro_rules! repeat {
+    ( $min:expr, $max:expr; $( $item:expr ),+ $(,)? ) => {
+        $crate::generic::matcher::RepeatMatcher::new(
+            $crate::pattern!($($item),+), $min, Some($max),
+        )
+    };
+}
+
+#[macro_export]
+/// Pattern, unless `excluded` matches the same text, see [`pattern!`]
+macro_rules! except {
+    ( $item:expr, $excluded:expr $(,)? ) => {
+        $crate::generic::matcher::ExceptMatcher::new($item, $excluded)
+    };
+}
+
+/// Matcher of the longest text matched by regular expression
+///
+/// Automaton is compiled once and shared between clones, so a rule can be
+/// made with `move || matcher.clone()`. Alternations prefer the leftmost
+/// branch like in `regex` crate, but matching never backtracks: when the text
+/// can't be extended any more, it has to be a match as a whole. Empty
+/// matches are rejected.
+pub struct RegexMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    dfa: Arc<dense::DFA<Vec<u32>>>,
+    state: StateID,
+    text: String,
+    resf: F,
+    cooked: bool,
+}
+
+impl<T, F> RegexMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    pub fn new(pattern: &str, resf: F) -> Result<Self> {
+        let dfa = dense::DFA::new(pattern)
+            .map_err(|err| Error::broken_grammar_string(format!("Regex `{pattern}`: {err}")))?;
+        let state = Self::start(&dfa)?;
+
+        Ok(Self {
+            dfa: Arc::new(dfa),
+            state,
+            text: String::new(),
+            resf,
+            cooked: false,
+        })
+    }
+
+    fn start(dfa: &dense::DFA<Vec<u32>>) -> Result<StateID> {
+        dfa.start_state(&start::Config::new().anchored(Anchored::Yes))
+            .map_err(|err| Error::broken_grammar_string(err.to_string()))
+    }
+
+    /// Whether text leading to `state` may still be matched
+    ///
+    /// Entered match state reports previous text, so it is viable only if it
+    /// can go further.
+    fn is_viable(self: &Self, state: StateID) -> bool {
+        if self.dfa.is_dead_state(state) || self.dfa.is_quit_state(state) {
+            false
+        } else if self.dfa.is_match_state(state) {
+            self.dfa.is_match_state(self.dfa.next_eoi_state(state))
+                || (0..=u8::MAX).any(|byte| !self.dfa.is_dead_state(self.dfa.next_state(state, byte)))
+        } else {
+            true
+        }
+    }
+}
+
+impl<T, F> Clone for RegexMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T + Clone,
+{
+    fn clone(self: &Self) -> Self {
+        Self {
+            dfa: self.dfa.clone(),
+            state: self.state,
+            text: self.text.clone(),
+            resf: self.resf.clone(),
+            cooked: self.cooked,
+        }
+    }
+}
+
+impl<T, F> Matcher<T> for RegexMatcher<T, F>
+where
+    T: Clone,
+    F: FnMut(&str) -> T,
+{
+    fn check(self: &mut Self, c: Char) -> State<T> {
+        if self.cooked {
+            return State::Rejected
+        }
+
+        // Match states are delayed by one byte, so the first byte of `c`
+        // tells, whether text before it is matched
+        let (matched, next) = match c {
+            Char::Char(c) => {
+                let mut buffer = [0; 4];
+                let mut bytes = c.encode_utf8(&mut buffer).bytes();
+                let mut state = self.dfa.next_state(self.state, bytes.next().expect("Char isn't empty"));
+                let matched = self.dfa.is_match_state(state);
+
+                for byte in bytes {
+                    state = self.dfa.next_state(state, byte);
+                }
+
+                (matched, Some((c, state)))
+            },
+            Char::EOF => (self.dfa.is_match_state(self.dfa.next_eoi_state(self.state)), None),
+        };
+
+        let res = match next {
+            Some((c, state)) if self.is_viable(state) => {
+                self.state = state;
+                self.text.push(c);
+                State::Matching
+            },
+            _ if matched && !self.text.is_empty() => State::Matched((self.resf)(&self.text)),
+            _ => State::Rejected,
+        };
+
+        if let State::Rejected | State::Matched(_) = res {
+            self.cooked = true;
+        }
+
+        res
+    }
+
+    fn reset(self: &mut Self) {
+        self.state = Self::start(&self.dfa).expect("Start state was built with automaton");
+        self.text.clear();
+        self.cooked = false;
+    }
+}
+
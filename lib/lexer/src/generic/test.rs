@@ -6,6 +6,11 @@ enum Token {
     Keyword(String),
     Identifier(String),
     Operator(String),
+    Str(String),
+    Char(char),
+    Float(f64),
+    Number(String),
+    Comment(String),
 }
 
 fn lexer() -> Lexer<Token> {
@@ -115,11 +120,11 @@ fn simple_test() {
 
     let mut res = l.parse_str("a = b + c");
 
-    assert_eq!(Token::Identifier("a".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("b".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("c".to_owned()), res.next().unwrap().unwrap());
+    assert_eq!(Token::Identifier("a".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("b".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("c".to_owned()), res.next().unwrap().unwrap().value);
     assert!(res.next().is_none());
 }
 
@@ -129,11 +134,11 @@ fn longer_ids() {
 
     let mut res = l.parse_str("sum = first + second");
 
-    assert_eq!(Token::Identifier("sum".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap());
+    assert_eq!(Token::Identifier("sum".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap().value);
     assert!(res.next().is_none());
 }
 
@@ -143,11 +148,11 @@ fn more_skips() {
 
     let mut res = l.parse_str("   sum  =first+ second   ");
 
-    assert_eq!(Token::Identifier("sum".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap());
+    assert_eq!(Token::Identifier("sum".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap().value);
     assert!(res.next().is_none());
 }
 
@@ -157,12 +162,12 @@ fn with_kerword() {
 
     let mut res = l.parse_str("let lets = first + second");
 
-    assert_eq!(Token::Keyword("let".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("lets".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap());
+    assert_eq!(Token::Keyword("let".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("lets".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap().value);
     assert!(res.next().is_none());
 }
 
@@ -172,16 +177,644 @@ fn unknown_token() {
 
     let mut res = l.parse_str("let aboba? = first + second");
 
-    assert_eq!(Token::Keyword("let".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("aboba".to_owned()), res.next().unwrap().unwrap());
-    assert!(match res.next().unwrap().unwrap_err() {
-        Error::UnknownToken(None) => true,
+    assert_eq!(Token::Keyword("let".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("aboba".to_owned()), res.next().unwrap().unwrap().value);
+    let error = res.next().unwrap().unwrap_err();
+    assert!(match error.kind {
+        crate::ErrorKind::UnknownToken(None) => true,
         _ => false,
     });
-    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap());
-    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap());
+    assert_eq!((9, 1, 10, 1), {
+        let span = error.span.unwrap();
+        (span.offset, span.line, span.column, span.length)
+    });
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("first".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("second".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn spans() {
+    let mut l = lexer_with_kw();
+
+    let mut res = l.parse_source(Some("test"), std::io::Cursor::new("let a\n  = bc"));
+
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Keyword("let".to_owned()), token.value);
+    assert_eq!((0, 1, 1, 3), (token.span.offset, token.span.line, token.span.column, token.span.length));
+    assert_eq!(Some("test"), token.span.file.as_deref());
+
+    let token = res.next().unwrap().unwrap();
+    assert_eq!((4, 1, 5, 1), (token.span.offset, token.span.line, token.span.column, token.span.length));
+
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Operator("=".to_owned()), token.value);
+    assert_eq!((8, 2, 3, 1), (token.span.offset, token.span.line, token.span.column, token.span.length));
+
+    let token = res.next().unwrap().unwrap();
+    assert_eq!((10, 2, 5, 2), (token.span.offset, token.span.line, token.span.column, token.span.length));
+    assert_eq!("test:2:5", token.span.to_string());
+    assert!(res.next().is_none());
+}
+
+/// Matches exact string regardless of the following character
+struct Word {
+    chars: Vec<char>,
+    token: fn(String) -> Token,
+    i: usize,
+}
+
+impl Matcher<Token> for Word {
+    fn check(self: &mut Self, c: Char) -> State<Token> {
+        if self.chars.len() == self.i {
+            State::Matched((self.token)(self.chars.iter().collect()))
+        } else if let Char::Char(c) = c {
+            if self.chars[self.i] == c {
+                self.i += 1;
+                State::Matching
+            } else {
+                State::Rejected
+            }
+        } else {
+            State::Rejected
+        }
+    }
+
+    fn reset(self: &mut Self) {
+        self.i = 0;
+    }
+
+    fn samples(self: &Self) -> Vec<String> {
+        vec![self.chars.iter().collect()]
+    }
+}
+
+fn word(value: &'static str, token: fn(String) -> Token) -> impl Fn() -> Word + Send + Sync {
+    move || Word {
+        chars: value.chars().collect(),
+        token,
+        i: 0,
+    }
+}
+
+#[test]
+fn longest_match() {
+    let mut l = lexer();
+
+    l.with_mode(Mode::Longest { backtrack: 4 })
+     .push_front(word("int", Token::Keyword));
+
+    let mut res = l.parse_str("int integer");
+
+    assert_eq!(Token::Keyword("int".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("integer".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn longest_match_priority() {
+    let mut l = lexer();
+
+    l.with_mode(Mode::Longest { backtrack: 4 })
+     .push_back_priority(word("int", Token::Keyword), 1);
+
+    let mut res = l.parse_str("int intx");
+
+    assert_eq!(Token::Keyword("int".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("intx".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn longest_match_backtrack() {
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.with_mode(Mode::Longest { backtrack: 4 })
+     .push_back(word("+", Token::Operator))
+     .push_back(word("=", Token::Operator))
+     .push_back(word("+=+", Token::Operator));
+
+    let mut res = l.parse_str("+=+=");
+
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Operator("+=+".to_owned()), token.value);
+    assert_eq!((0, 3), (token.span.offset, token.span.length));
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+    drop(res);
+
+    let mut res = l.parse_str("+=-");
+
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Operator("=".to_owned()), token.value);
+    assert_eq!((1, 1, 2), (token.span.offset, token.span.line, token.span.column));
+    assert!(res.next().unwrap().is_err());
+    drop(res);
+
+    l.with_mode(Mode::Longest { backtrack: 0 });
+    let mut res = l.parse_str("+=+");
+
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn keyword_set() {
+    fn allowed(c: Char) -> bool {
+        match c {
+            Char::Char(c) => char::is_alphabetic(c),
+            Char::EOF => false,
+        }
+    }
+
+    let mut l = lexer();
+
+    l.push_front(|| {
+        let mut keywords = matcher::KeywordSetMatcher::new();
+
+        keywords.add("let", Token::Keyword("let".to_owned()), allowed)
+            .add("len", Token::Keyword("len".to_owned()), allowed)
+            .add("::", Token::Operator("::".to_owned()), |_| false)
+            .add("let", Token::Identifier("let".to_owned()), allowed);
+
+        keywords
+    });
+
+    let mut res = l.parse_str("let lets len::le");
+
+    assert_eq!(Token::Keyword("let".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("lets".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Keyword("len".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("::".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("le".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn lossless() {
+    let mut l = lexer();
+    let source = " a =\n b+ c ";
+
+    let items: Vec<_> = l.parse_lossless(source, |t| Token::Operator("+".to_owned()) == *t)
+        .map(|t| t.unwrap())
+        .collect();
+
+    let mut rebuilt = String::new();
+    items.iter().for_each(|item| item.write_text(&mut rebuilt));
+    assert_eq!(source, rebuilt);
+
+    assert_eq!(5, items.len());
+    assert_eq!(Some("b"), items[2].token.as_ref().map(|t| t.text));
+    assert_eq!((2, 2), {
+        let b = items[2].token.as_ref().unwrap();
+        (b.span.line, b.span.column)
+    });
+    assert_eq!(vec!["+", " "], items[3].leading.iter().map(|t| t.text()).collect::<Vec<_>>());
+    assert!(items[4].token.is_none());
+}
+
+#[test]
+fn lossless_unknown() {
+    let mut l = lexer_with_kw();
+    let source = "let a?? = b @c";
+
+    l.with_recovery(true);
+
+    let items: Vec<_> = l.parse_lossless(source, |_| false).collect();
+
+    assert_eq!(2, items.iter().filter(|t| t.is_err()).count());
+
+    let items: Vec<_> = items.into_iter().filter_map(|t| t.ok()).collect();
+    let mut rebuilt = String::new();
+    items.iter().for_each(|item| item.write_text(&mut rebuilt));
+    assert_eq!(source, rebuilt);
+
+    assert_eq!(vec!["??", " "], items[2].leading.iter().map(|t| t.text()).collect::<Vec<_>>());
+    assert!(match &items[2].leading[0] {
+        crate::Trivia::Unknown(unknown) => 5 == unknown.span.offset,
+        _ => false,
+    });
+    assert_eq!(Some("="), items[2].token.as_ref().map(|t| t.text));
+    assert_eq!(vec![" ", "@"], items[4].leading.iter().map(|t| t.text()).collect::<Vec<_>>());
+}
+
+#[test]
+fn recovery() {
+    let mut l = lexer_with_kw();
+
+    l.with_recovery(true);
+
+    let mut res = l.parse_str("let a?? = b @c");
+
+    assert_eq!(Token::Keyword("let".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("a".to_owned()), res.next().unwrap().unwrap().value);
+    let error = res.next().unwrap().unwrap_err();
+    assert!(match &error.kind {
+        crate::ErrorKind::UnknownToken(Some(text)) => "??" == text,
+        _ => false,
+    });
+    assert_eq!(Some((5, 2)), error.span.map(|span| (span.offset, span.length)));
+    assert_eq!(Token::Operator("=".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Identifier("b".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(match res.next().unwrap().unwrap_err().kind {
+        crate::ErrorKind::UnknownToken(Some(text)) => "@" == text,
+        _ => false,
+    });
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Identifier("c".to_owned()), token.value);
+    assert_eq!(13, token.span.offset);
+    assert!(res.next().is_none());
+    drop(res);
+
+    l.with_mode(Mode::Longest { backtrack: 4 });
+    let mut res = l.parse_str("a ?!b");
+
+    assert_eq!(Token::Identifier("a".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(match res.next().unwrap().unwrap_err().kind {
+        crate::ErrorKind::UnknownToken(Some(text)) => "?!" == text,
+        _ => false,
+    });
+    assert_eq!(Token::Identifier("b".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+#[test]
+fn definition() {
+    let mut d: definition::Definition<Token> = definition::Definition::new();
+
+    d.with_skip(|| char::is_whitespace)
+     .push_back(word("+", Token::Operator))
+     .push_back(|| matcher::AllowedCharMatcher::new(
+        |s: &str| Token::Identifier(s.to_owned()),
+        |c| match c {
+            Char::Char(c) => char::is_alphabetic(c),
+            Char::EOF => false,
+        },
+     ));
+
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let d = &d;
+        let handles: Vec<_> = ["a + b", "c+d"].into_iter().map(|input| {
+            scope.spawn(move || { d }.parse_str(input)
+                .map(|t| t.unwrap().value)
+                .collect::<Vec<_>>()
+            )
+        }).collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(Token::Identifier("b".to_owned()), results[0][2]);
+    assert_eq!(Token::Operator("+".to_owned()), results[1][1]);
+    assert_eq!(3, results[1].len());
+}
+
+#[test]
+fn definition_shared() {
+    let mut d: definition::Definition<Token> = definition::Definition::new();
+    let mut created = 0;
+
+    // Stateful rule, which is only `FnMut`
+    d.with_skip(definition::Shared::new(|| char::is_whitespace))
+     .push_back(definition::Shared::new(move || {
+        created += 1;
+        assert!(created <= 2, "Matcher is created once per input");
+        word("+", Token::Operator)()
+     }));
+
+    let counts: Vec<_> = std::thread::scope(|scope| {
+        let d = &d;
+        let handles: Vec<_> = ["+ +", "+"].into_iter().map(|input| {
+            scope.spawn(move || { d }.parse_str(input).count())
+        }).collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    assert_eq!(vec![2, 1], counts);
+}
+
+fn literals() -> Lexer<Token> {
+    let mut l = lexer();
+
+    l.push_front(|| matcher::StringMatcher::new('"', |s| Token::Str(s.to_owned())))
+     .push_front(|| matcher::CharMatcher::new(Token::Char))
+     .push_front(|| matcher::FloatMatcher::new(Token::Float))
+     .push_front(|| matcher::RestOfLineMatcher::new("%", |s| Token::Str(s.to_owned())));
+
+    l
+}
+
+#[test]
+fn string_literal() {
+    let mut l = literals();
+
+    let mut res = l.parse_str(r#"a "b \"c\" \n\t\x41\u{1F600}" "" + "\q" "#);
+
+    assert_eq!(Token::Identifier("a".to_owned()), res.next().unwrap().unwrap().value);
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Str("b \"c\" \n\tA\u{1F600}".to_owned()), token.value);
+    assert_eq!((2, 27), (token.span.offset, token.span.length));
+    assert_eq!(Token::Str(String::new()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Operator("+".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().unwrap().is_err());   // Unknown escape
+    drop(res);
+
+    let mut res = l.parse_str("\"open\nline\"");
+    assert!(res.next().unwrap().is_err());
+}
+
+#[test]
+fn char_literal() {
+    let mut l = literals();
+
+    let mut res = l.parse_str(r"'a' '\'' '\n' '\u{e9}' 'ab'");
+
+    assert_eq!(Token::Char('a'), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Char('\''), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Char('\n'), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Char('é'), res.next().unwrap().unwrap().value);
+    assert!(res.next().unwrap().is_err());
+}
+
+#[test]
+fn float_literal() {
+    let mut l = literals();
+
+    let mut res = l.parse_str("1.5 -0.25 .5 2. 6.02E+23 1e-3 x");
+
+    assert_eq!(Token::Float(1.5), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Float(-0.25), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Float(0.5), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Float(2.0), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Float(6.02e23), res.next().unwrap().unwrap().value);
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Float(1e-3), token.value);
+    assert_eq!(4, token.span.length);
+    assert_eq!(Token::Identifier("x".to_owned()), res.next().unwrap().unwrap().value);
     assert!(res.next().is_none());
+    drop(res);
+
+    let mut res = l.parse_str("12 1e");
+    assert!(res.next().unwrap().is_err());  // No integer rule
+    assert!(res.next().unwrap().is_err());
 }
 
+#[test]
+fn rest_of_line() {
+    let mut l = literals();
+
+    let mut res = l.parse_str("%#include <a.h>\r\na\n%last");
+
+    let token = res.next().unwrap().unwrap();
+    assert_eq!(Token::Str("#include <a.h>".to_owned()), token.value);
+    assert_eq!(16, token.span.length);
+    assert_eq!(Token::Identifier("a".to_owned()), res.next().unwrap().unwrap().value);
+    assert_eq!(Token::Str("last".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().is_none());
+}
+
+
+#[test]
+fn pattern_dsl() {
+    use crate::{capture, except, many, one_of, optional, pattern, repeat};
+    use matcher::CharClassMatcher;
+
+    let word = || pattern!(
+        CharClassMatcher::new(char::is_alphabetic),
+        many!(CharClassMatcher::new(char::is_alphanumeric)),
+    );
+
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.with_skip(|| char::is_whitespace)
+     .with_mode(Mode::Longest { backtrack: 4 })
+     .push_back(move || capture!(|text: &str| Token::Identifier(text.to_owned()) =>
+        except!(word(), one_of!("let", "fn")),
+     ))
+     .push_back(|| capture!(|text: &str| Token::Keyword(text.to_owned()) => one_of!("let", "fn")))
+     .push_back(|| capture!(|text: &str| Token::Operator(text.to_owned()) => '='))
+     .push_back(|| capture!(|text: &str| Token::Number(text.to_owned()) =>
+        "0x",
+        repeat!(1, 4; CharClassMatcher::new(|c| c.is_ascii_hexdigit())),
+        optional!(one_of!('u', 'l')),
+     ))
+     .push_back(|| capture!(|text: &str| Token::Comment(text.to_owned()) =>
+        "//",
+        many!(CharClassMatcher::new(|c| '\n' != c)),
+     ));
+
+    let res = l.parse_str("let letter = 0x1Fu // done\nfn 0xbeef")
+        .map(|t| t.unwrap().value)
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![
+        Token::Keyword("let".to_owned()),
+        Token::Identifier("letter".to_owned()),
+        Token::Operator("=".to_owned()),
+        Token::Number("0x1Fu".to_owned()),
+        Token::Comment("// done".to_owned()),
+        Token::Keyword("fn".to_owned()),
+        Token::Number("0xbeef".to_owned()),
+    ], res);
+
+    drop(res);
+    let mut res = l.parse_str("0x12345");
+
+    assert_eq!(Token::Number("0x1234".to_owned()), res.next().unwrap().unwrap().value);
+    assert!(res.next().unwrap().is_err());
+}
+
+#[test]
+fn regex() {
+    use matcher::RegexMatcher;
+
+    let identifier = RegexMatcher::new(r"[\p{L}_][\p{L}0-9_]*", |text: &str| Token::Identifier(text.to_owned())).unwrap();
+    let number = RegexMatcher::new(r"[0-9]+(\.[0-9]+)?([eE][+-]?[0-9]+)?", |text: &str| Token::Number(text.to_owned())).unwrap();
+
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.with_skip(|| char::is_whitespace)
+     .push_back(move || identifier.clone())
+     .push_back(move || number.clone());
+
+    let res = l.parse_str("λx_1 42 3.5e-2")
+        .map(|t| t.unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![
+        Token::Identifier("λx_1".to_owned()),
+        Token::Number("42".to_owned()),
+        Token::Number("3.5e-2".to_owned()),
+    ], res.iter().map(|t| t.value.clone()).collect::<Vec<_>>());
+    assert_eq!((6, 1, 6, 2), (res[1].span.offset, res[1].span.line, res[1].span.column, res[1].span.length));
+
+    let err = RegexMatcher::new("[a-", |text: &str| Token::Identifier(text.to_owned())).err().unwrap();
+    assert!(matches!(err.kind, crate::ErrorKind::BrokenGrammar(Some(_))));
+}
+
+#[test]
+fn contexts() {
+    use matcher::RestOfLineMatcher;
+
+    let mut l = lexer();
+
+    l.push_front(word("%", Token::Operator))
+     .in_context("raw")
+     .push_back(|| RestOfLineMatcher::new("", |text: &str| Token::Str(text.to_owned())))
+     .with_transitions(|token| match token {
+        Token::Operator(op) if "%" == op => Some(Transition::Push("raw")),
+        Token::Str(_) => Some(Transition::Pop),
+        _ => None,
+     });
+
+    let res = l.parse_str("a % b = c\nd")
+        .map(|t| t.unwrap().value)
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![
+        Token::Identifier("a".to_owned()),
+        Token::Operator("%".to_owned()),
+        Token::Str("b = c".to_owned()),
+        Token::Identifier("d".to_owned()),
+    ], res);
+
+    drop(res);
+    l.with_transitions(|_| Some(Transition::Push("nope")));
+    let mut res = l.parse_str("a");
+
+    let err = res.next().unwrap().unwrap_err();
+    assert!(matches!(err.kind, crate::ErrorKind::BrokenGrammar(Some(_))));
+    assert_eq!((0, 1), (err.span.as_ref().unwrap().offset, err.span.as_ref().unwrap().length));
+}
+
+#[test]
+fn trace() {
+    let trace = trace::Trace::new();
+    let mut l = lexer();
+
+    l.with_trace(trace.clone());
+    assert_eq!(2, l.parse_str("ab+").count());
+
+    let steps = trace.steps();
+    assert_eq!(
+        vec![(Some('a'), 0), (Some('b'), 0), (Some('+'), 0), (Some('+'), 2), (None, 2)],
+        steps.iter().map(|s| (s.char, s.token)).collect::<Vec<_>>(),
+    );
+    assert_eq!(vec![RuleState::Matched, RuleState::Idle], steps[2].rules);
+    assert_eq!(vec![RuleState::Rejected, RuleState::Matching], steps[3].rules);
+
+    let table = trace.table();
+    assert_eq!(6, table.lines().count());
+    assert!(table.lines().nth(3).unwrap().ends_with("'+'      +    "));
+
+    let graph = trace.graphviz();
+    assert!(graph.starts_with("digraph trace {"));
+    assert_eq!(2, graph.matches("subgraph").count());
+    assert!(graph.contains("s0 -> s1;"));
+    assert!(!graph.contains("s2 -> s3;"));
+}
+
+#[test]
+fn shadowed() {
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.push_back(word("+", Token::Operator))
+     .push_back(word("+=", Token::Operator))
+     .push_back(word("=", Token::Operator));
+
+    assert_eq!(vec![trace::ShadowedRule {
+        context: DEFAULT_CONTEXT,
+        rule: 1,
+        by: 0,
+        sample: "+=".to_owned(),
+        broken: false,
+    }], l.shadowed());
+    assert_eq!(
+        "rule #1 in context `default` is shadowed by rule #0 on `+=`",
+        l.shadowed()[0].to_string(),
+    );
+    assert!(l.build().is_err());
+
+    l.with_mode(Mode::Longest { backtrack: 2 });
+    assert!(l.shadowed().is_empty());
+    assert!(l.build().is_ok());
+}
+
+#[test]
+fn shadowed_unreachable() {
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.push_back(|| matcher::CharSequenceMatcher::new("+=", || Token::Operator("+=".to_owned()), |_| false))
+     .push_back(|| matcher::CharSequenceMatcher::new("+", || Token::Operator("+".to_owned()), |_| false));
+
+    let shadowed = l.shadowed();
+    assert_eq!(1, shadowed.len());
+    assert_eq!((0, 1, true), (shadowed[0].rule, shadowed[0].by, shadowed[0].broken));
+    assert_eq!(
+        "Unreacheable higher order rule found: rule #0 in context `default` is still matching on `+=` when rule #1 matches",
+        shadowed[0].to_string(),
+    );
+    assert!(l.parse_str("+=").next().unwrap().is_err());
+}
+
+#[test]
+fn shadowed_without_samples() {
+    use matcher::{RegexMatcher, RestOfLineMatcher};
+
+    let number = RegexMatcher::new("[0-9]+", |text: &str| Token::Number(text.to_owned())).unwrap();
+    let word = RegexMatcher::new("[a-z]+", |text: &str| Token::Identifier(text.to_owned())).unwrap();
+
+    let mut l = lexer();
+
+    l.push_back(move || number.clone())
+     .push_back(move || word.clone());
+
+    // Identifier rule goes first and takes all words
+    assert_eq!(
+        vec![(DEFAULT_CONTEXT, 3, 0, "a".to_owned())],
+        l.shadowed().into_iter().map(|s| (s.context, s.rule, s.by, s.sample)).collect::<Vec<_>>(),
+    );
+
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.push_back_anchored(|| RestOfLineMatcher::new("#", |text: &str| Token::Str(text.to_owned())), Anchor::LineStart)
+     .push_back(|| matcher::CharSequenceMatcher::new("#", || Token::Operator("#".to_owned()), |_| false));
+
+    assert!(l.shadowed().is_empty());
+
+    let mut l: Lexer<Token> = Lexer::new();
+
+    l.push_back(|| RestOfLineMatcher::new("#", |text: &str| Token::Str(text.to_owned())))
+     .push_back(|| matcher::CharSequenceMatcher::new("#", || Token::Operator("#".to_owned()), |_| false));
+
+    assert_eq!(vec![(1, 0)], l.shadowed().into_iter().map(|s| (s.rule, s.by)).collect::<Vec<_>>());
+}
+
+
+#[test]
+fn anchors() {
+    use matcher::RestOfLineMatcher;
+
+    let mut l = lexer();
+
+    l.with_mode(Mode::Longest { backtrack: 16 })
+     .push_back_anchored(|| RestOfLineMatcher::new("#", |text: &str| Token::Str(text.to_owned())), Anchor::LineStart)
+     .push_back(word("#", Token::Operator));
+
+    let res = l.parse_str("#one\na # b\n  #two")
+        .map(|t| t.unwrap().value)
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![
+        Token::Str("one".to_owned()),
+        Token::Identifier("a".to_owned()),
+        Token::Operator("#".to_owned()),
+        Token::Identifier("b".to_owned()),
+        Token::Str("two".to_owned()),
+    ], res);
+}
@@ -0,0 +1,366 @@
+
+use std::sync::{Arc, Mutex};
+
+use super::{Char, Context, Matcher, MatcherState, Mode, State};
+
+/// State of a single rule after a character was fed to the matchers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RuleState {
+    /// Rule wasn't asked, it's finished already
+    Idle,
+    Matching,
+    Matched,
+    Rejected,
+}
+
+impl RuleState {
+    fn symbol(self: Self) -> char {
+        match self {
+            RuleState::Idle => ' ',
+            RuleState::Matching => '~',
+            RuleState::Matched => '+',
+            RuleState::Rejected => 'x',
+        }
+    }
+}
+
+impl<T: Clone> From<&State<T>> for RuleState {
+    fn from(state: &State<T>) -> Self {
+        match state {
+            State::Rejected => RuleState::Rejected,
+            State::Matching => RuleState::Matching,
+            State::Matched(_) => RuleState::Matched,
+        }
+    }
+}
+
+/// Single character fed to the matchers of current context
+#[derive(Clone, Debug)]
+pub struct TraceStep {
+    pub context: &'static str,
+    /// Offset of the token being matched
+    pub token: usize,
+    /// `None` for end of input
+    pub char: Option<char>,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    /// States of the context rules in order they were added
+    pub rules: Vec<RuleState>,
+}
+
+/// Record of matcher states, shared between lexer and its user
+///
+/// # Usage
+/// ```,ignore
+/// let trace = Trace::new();
+///
+/// lexer.with_trace(trace.clone());
+/// lexer.parse_str(input).for_each(drop);
+///
+/// eprintln!("{}", trace.table());
+/// ```
+#[derive(Clone, Default)]
+pub struct Trace {
+    steps: Arc<Mutex<Vec<TraceStep>>>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn steps(self: &Self) -> Vec<TraceStep> {
+        self.lock().clone()
+    }
+
+    pub fn clear(self: &Self) {
+        self.lock().clear()
+    }
+
+    pub(crate) fn push(self: &Self, step: TraceStep) {
+        self.lock().push(step)
+    }
+
+    fn lock(self: &Self) -> std::sync::MutexGuard<'_, Vec<TraceStep>> {
+        self.steps.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Text table with a row per character and a column per rule
+    ///
+    /// Rule states are `~` matching, `+` matched, `x` rejected and blank
+    /// for rules, which are finished already.
+    pub fn table(self: &Self) -> String {
+        let steps = self.lock();
+        let rules = steps.iter().map(|s| s.rules.len()).max().unwrap_or(0);
+        let width = steps.iter().map(|s| s.context.len()).max().unwrap_or(0).max(7);
+
+        let mut out = format!("{:width$} {:>6} {:>9} {:6}", "context", "token", "position", "char");
+        (0..rules).for_each(|i| out += &format!(" {:>3}", format!("#{i}")));
+        out += "\n";
+
+        steps.iter().for_each(|step| {
+            out += &format!(
+                "{:width$} {:>6} {:>9} {:6}",
+                step.context,
+                step.token,
+                format!("{}:{}", step.line, step.column),
+                display_char(step.char),
+            );
+            step.rules.iter().for_each(|rule| out += &format!(" {:>3}", rule.symbol()));
+            out += "\n";
+        });
+
+        out
+    }
+
+    /// Graphviz digraph with a cluster per token and a node per character
+    pub fn graphviz(self: &Self) -> String {
+        let steps = self.lock();
+        let mut out = String::from("digraph trace {\n    node [shape=record, fontname=monospace];\n");
+        let mut cluster = None;
+
+        steps.iter().enumerate().for_each(|(i, step)| {
+            if cluster != Some((step.context, step.token)) {
+                if cluster.is_some() {
+                    out += "    }\n";
+                }
+
+                out += &format!(
+                    "    subgraph cluster_{i} {{\n        label=\"token at {} ({})\";\n",
+                    step.token,
+                    escape(step.context),
+                );
+                cluster = Some((step.context, step.token));
+            } else {
+                out += &format!("        s{} -> s{i};\n", i - 1);
+            }
+
+            let rules = step.rules.iter()
+                .enumerate()
+                .map(|(rule, state)| format!("|#{rule} {}", escape(&state.symbol().to_string())))
+                .collect::<String>();
+
+            out += &format!(
+                "        s{i} [label=\"{{{} {}:{}{rules}}}\"];\n",
+                escape(&display_char(step.char)),
+                step.line,
+                step.column,
+            );
+        });
+
+        if cluster.is_some() {
+            out += "    }\n";
+        }
+
+        out + "}\n"
+    }
+}
+
+fn display_char(c: Option<char>) -> String {
+    match c {
+        Some(c) => format!("{c:?}"),
+        None => String::from("EOF"),
+    }
+}
+
+/// Escape text for record label
+fn escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' | ' ' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// Put state of `i`-th rule into trace row, if tracing
+pub(super) fn observe<'a, T: Clone>(
+    row: &mut Option<Vec<RuleState>>,
+    i: usize,
+    state: &'a State<T>,
+) -> &'a State<T> {
+    if let Some(row) = row {
+        row[i] = RuleState::from(state);
+    }
+
+    state
+}
+
+/// Rule which never wins on text it was made for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowedRule {
+    pub context: &'static str,
+    /// Index of rule in its context
+    pub rule: usize,
+    /// Index of rule winning instead
+    pub by: usize,
+    pub sample: String,
+    /// Lexer fails on the sample in [`Mode::First`], as rule `by` matches
+    /// while this one, added before it, is still matching
+    pub broken: bool,
+}
+
+impl std::fmt::Display for ShadowedRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.broken {
+            true => write!(
+                f,
+                "{UNREACHABLE}: rule #{} in context `{}` is still matching on `{}` when rule #{} matches",
+                self.rule, self.context, self.sample, self.by,
+            ),
+            false => write!(
+                f,
+                "rule #{} in context `{}` is shadowed by rule #{} on `{}`",
+                self.rule, self.context, self.by, self.sample,
+            ),
+        }
+    }
+}
+
+pub(super) const UNREACHABLE: &str = "Unreacheable higher order rule found";
+
+/// Texts of common token shapes tried on rules, which have no samples
+const PROBES: &[&str] = &[
+    "a", "abc", "a1", "_a", "A_B", "0", "1", "42", "-1", "0x1f", "0777", "1.5", "1e3",
+    "\"s\"", "'c'", "/* c */", "// c", "# c", "% c",
+];
+
+/// Check rules on texts they accept, whether they can ever be chosen
+///
+/// The check is approximate. Rules are tried on their own samples (see
+/// [`Matcher::samples`](super::Matcher::samples)) and every lost sample is
+/// reported. Rules without samples are tried on samples of other rules and
+/// on a fixed set of common token shapes, they are reported only if they
+/// lose on all texts they accept, and never if they accept none of them.
+/// Text is tried both at the start of a line and in the middle of it, so
+/// rule loses only if it loses wherever its anchor allows it.
+pub(super) fn shadowed<T: Clone>(mut contexts: Vec<Context<T>>, mode: Mode) -> Vec<ShadowedRule> {
+    let probes = contexts.iter()
+        .flat_map(|(_, rules)| rules.iter().flat_map(|m| m.samples()))
+        .chain(PROBES.iter().map(|probe| probe.to_string()))
+        .collect::<Vec<_>>();
+    let mut out = Vec::new();
+
+    for (context, rules) in contexts.iter_mut() {
+        for rule in 0..rules.len() {
+            let samples = rules[rule].samples();
+
+            if !samples.is_empty() {
+                out.extend(samples.into_iter()
+                    .filter_map(|sample| lost(context, rules, rule, sample, mode))
+                );
+                continue;
+            }
+
+            let accepted = probes.iter()
+                .filter(|probe| accepts(&mut rules[rule], probe))
+                .cloned()
+                .collect::<Vec<_>>();
+            let count = accepted.len();
+            let lost = accepted.into_iter()
+                .filter_map(|sample| lost(context, rules, rule, sample, mode))
+                .collect::<Vec<_>>();
+
+            if 0 != count && lost.len() == count {
+                out.extend(lost.into_iter().next());
+            }
+        }
+    }
+
+    out
+}
+
+/// Report of the rule, which isn't chosen on `sample` wherever its anchor
+/// allows it
+fn lost<T: Clone>(
+    context: &'static str,
+    rules: &mut [MatcherState<T>],
+    rule: usize,
+    sample: String,
+    mode: Mode,
+) -> Option<ShadowedRule> {
+    let mut report = None;
+
+    for line_start in [true, false] {
+        if !rules[rule].anchor.allows(line_start) {
+            continue;
+        }
+
+        match winner(rules, &sample, mode, line_start) {
+            Ok(Some(by)) if by == rule => return None,
+            Ok(Some(by)) => report = report.or(Some((by, false))),
+            Err(by) => report = report.or(Some((by, true))),
+            Ok(None) => {},
+        }
+    }
+
+    report.map(|(by, broken)| ShadowedRule { context, rule, by, sample, broken })
+}
+
+/// Whether matcher alone takes the whole `text` as a token
+fn accepts<T: Clone>(matcher: &mut MatcherState<T>, text: &str) -> bool {
+    let length = text.chars().count();
+
+    matcher.reset();
+
+    for (i, c) in text.chars().map(Char::Char).chain([Char::EOF]).enumerate() {
+        match matcher.step(c) {
+            State::Matching => {},
+            State::Matched(_) => return i == length,
+            State::Rejected => return false,
+        }
+    }
+
+    false
+}
+
+/// Rule, which lexer would choose for token starting with `text`
+///
+/// In [`Mode::First`] lexer fails if a rule matches while the one added
+/// before it is still matching, index of the former is the error then.
+fn winner<T: Clone>(
+    rules: &mut [MatcherState<T>],
+    text: &str,
+    mode: Mode,
+    line_start: bool,
+) -> std::result::Result<Option<usize>, usize> {
+    let mut matched = Vec::new();
+
+    rules.iter_mut().for_each(|m| {
+        m.reset();
+        if !m.anchor.allows(line_start) {
+            m.last = State::Rejected;
+        }
+    });
+
+    for (length, c) in text.chars().map(Char::Char).chain([Char::EOF]).enumerate() {
+        let mut matching = false;
+
+        for (i, m) in rules.iter_mut().enumerate() {
+            if let State::Rejected | State::Matched(_) = m.last {
+                continue;
+            }
+
+            match m.step(c) {
+                State::Matching if matched.is_empty() => matching = true,
+                State::Matched(_) => match (mode, matching) {
+                    (Mode::First, true) => return Err(i),
+                    _ => matched.push((length, m.priority, i)),
+                },
+                _ => {},
+            }
+        }
+
+        if let (Mode::First, false) = (mode, matched.is_empty()) {
+            break;
+        }
+    }
+
+    Ok(match mode {
+        Mode::First => matched.first().map(|m| m.2),
+        Mode::Longest { .. } => matched.into_iter()
+            .max_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)).then(b.2.cmp(&a.2)))
+            .map(|m| m.2),
+    })
+}
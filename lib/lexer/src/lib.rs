@@ -1,8 +1,19 @@
 
 pub mod generic;
+mod lossless;
+mod span;
+
+pub use lossless::{LosslessToken, Trivia};
+pub use span::{Lexeme, Span, Spanned};
+
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Option<Span>,
+}
 
 #[derive(Debug)]
-pub enum Error {
+pub enum ErrorKind {
     BrokenGrammar(Option<String>),
     UnknownToken(Option<String>),
     UnexpectedEOF,
@@ -12,71 +23,147 @@ pub enum Error {
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub trait Lexer<T> {
-    fn parse(self: &mut Self, input: impl std::io::Read) -> impl Iterator<Item=Result<T>>;
+    /// Tokenize input, `source` is used as file name in token spans
+    fn parse_source(
+        self: &mut Self,
+        source: Option<&str>,
+        input: impl std::io::Read,
+    ) -> impl Iterator<Item=Result<Spanned<T>>>;
+
+    fn parse(self: &mut Self, input: impl std::io::Read) -> impl Iterator<Item=Result<Spanned<T>>> {
+        self.parse_source(None, input)
+    }
 
-    fn parse_str(self: &mut Self, string: &str) -> impl Iterator<Item=Result<T>> {
+    fn parse_str(self: &mut Self, string: &str) -> impl Iterator<Item=Result<Spanned<T>>> {
         self.parse(std::io::Cursor::new(string))
     }
+
+    /// Tokenize in-memory input, produced tokens borrow their text from it
+    ///
+    /// Only [`Lexeme::text`] is borrowed, it's a slice of `input` found by
+    /// span byte offsets. Token values are built by matchers the same way as
+    /// by [`Lexer::parse`], so owned values (e.g. identifier names) are still
+    /// allocated. By default `input` is read through
+    /// [`Cursor`](std::io::Cursor), [`generic::Builder`] overrides it to take
+    /// characters straight from the string.
+    fn parse_slice_source<'a>(
+        self: &mut Self,
+        source: Option<&str>,
+        input: &'a str,
+    ) -> impl Iterator<Item=Result<Lexeme<'a, T>>> {
+        self.parse_source(source, std::io::Cursor::new(input))
+            .map(move |token| token.map(|token| Lexeme::new(input, token)))
+    }
+
+    fn parse_slice<'a>(self: &mut Self, input: &'a str) -> impl Iterator<Item=Result<Lexeme<'a, T>>> {
+        self.parse_slice_source(None, input)
+    }
+
+    /// Tokenize in-memory input keeping skipped text and tokens accepted by
+    /// `is_trivia` attached to the following token
+    fn parse_lossless<'a>(
+        self: &mut Self,
+        input: &'a str,
+        is_trivia: impl FnMut(&T) -> bool,
+    ) -> impl Iterator<Item=Result<LosslessToken<'a, T>>> {
+        lossless::LosslessIterator::new(input, self.parse_slice(input), is_trivia)
+    }
 }
 
 impl Error {
+    fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            span: None,
+        }
+    }
+
+    /// Attach location to error, already present one is kept
+    pub fn at(self: Self, span: Span) -> Self {
+        Self {
+            span: self.span.or(Some(span)),
+            ..self
+        }
+    }
+
     pub fn broken_grammar() -> Self {
-        Self::BrokenGrammar(None)
+        Self::new(ErrorKind::BrokenGrammar(None))
     }
 
     pub fn broken_grammar_msg(msg: &str) -> Self {
-        Self::BrokenGrammar(Some(String::from(msg)))
+        Self::new(ErrorKind::BrokenGrammar(Some(String::from(msg))))
     }
 
     pub fn broken_grammar_string(msg: String) -> Self {
-        Self::BrokenGrammar(Some(msg))
+        Self::new(ErrorKind::BrokenGrammar(Some(msg)))
     }
 
     pub fn unknown_token() -> Self {
-        Self::UnknownToken(None)
+        Self::new(ErrorKind::UnknownToken(None))
     }
 
     pub fn unknown_token_str(token: &str) -> Self {
-        Self::UnknownToken(Some(String::from(token)))
+        Self::new(ErrorKind::UnknownToken(Some(String::from(token))))
     }
 
     pub fn unknown_token_string(token: String) -> Self {
-        Self::UnknownToken(Some(token))
+        Self::new(ErrorKind::UnknownToken(Some(token)))
     }
 
     pub fn unexpected_eof() -> Self {
-        Self::UnexpectedEOF
+        Self::new(ErrorKind::UnexpectedEOF)
     }
 
     pub fn io(err: std::io::Error) -> Self {
-        Self::IO(err)
+        Self::new(ErrorKind::IO(err))
+    }
+}
+
+impl ErrorKind {
+    /// Stable identifier of the error used in diagnostics
+    pub fn code(self: &Self) -> &'static str {
+        match self {
+            ErrorKind::BrokenGrammar(_) => "L0001",
+            ErrorKind::UnknownToken(_) => "L0002",
+            ErrorKind::UnexpectedEOF => "L0003",
+            ErrorKind::IO(_) => "L0004",
+        }
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Error::IO(err) => Some(err),
+        match &self.kind {
+            ErrorKind::IO(err) => Some(err),
             _ => None,
         }
     }
 }
 
 impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.span {
+            Some(span) if span.is_known() => write!(f, "{span}: {}", self.kind),
+            _ => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::BrokenGrammar(msg) => write!(f,
+            ErrorKind::BrokenGrammar(msg) => write!(f,
                 "Broken grammar: {}",
                 msg.as_ref().map(|msg| msg.as_str()).unwrap_or("<no info>")
             ),
-            Error::UnknownToken(token) => write!(f,
+            ErrorKind::UnknownToken(token) => write!(f,
                 "Unknown token found: {}",
                 token.as_ref().map(|token| token.as_str()).unwrap_or("<not provided>")
             ),
-            Error::UnexpectedEOF => write!(f,
+            ErrorKind::UnexpectedEOF => write!(f,
                 "Unexpected end of file"
             ),
-            Error::IO(error) => write!(f,
+            ErrorKind::IO(error) => write!(f,
                 "Error parsing buffer: {error}"
             ),
         }
@@ -0,0 +1,169 @@
+
+use crate::{Lexeme, Result, Span};
+
+/// Part of the source which doesn't take part in the grammar
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trivia<'a, T> {
+    /// Text thrown away by skip rule
+    Skipped(Lexeme<'a, ()>),
+    /// Token marked as trivia, e.g. comment
+    Token(Lexeme<'a, T>),
+    /// Text no rule matched, it was reported as error before the item
+    Unknown(Lexeme<'a, ()>),
+}
+
+/// Token with all trivia preceding it
+///
+/// Concatenation of `leading` and `token` texts of all produced items gives
+/// the original source, errors are skipped as their text is kept as
+/// [`Trivia::Unknown`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LosslessToken<'a, T> {
+    pub leading: Vec<Trivia<'a, T>>,
+    /// `None` for the end of input, which carries trailing trivia
+    pub token: Option<Lexeme<'a, T>>,
+}
+
+impl<'a, T> Trivia<'a, T> {
+    pub fn text(self: &Self) -> &'a str {
+        match self {
+            Trivia::Skipped(lexeme) => lexeme.text,
+            Trivia::Token(lexeme) => lexeme.text,
+            Trivia::Unknown(lexeme) => lexeme.text,
+        }
+    }
+}
+
+impl<'a, T> LosslessToken<'a, T> {
+    /// Write source text covered by the item
+    pub fn write_text(self: &Self, out: &mut String) {
+        self.leading.iter().for_each(|trivia| *out += trivia.text());
+
+        if let Some(token) = &self.token {
+            *out += token.text;
+        }
+    }
+}
+
+pub(crate) struct LosslessIterator<'a, T, I, F>
+where
+    I: Iterator<Item=Result<Lexeme<'a, T>>>,
+    F: FnMut(&T) -> bool,
+{
+    source: &'a str,
+    tokens: I,
+    is_trivia: F,
+    end: Span,
+    /// Trivia collected before error, it leads the next item
+    pending: Vec<Trivia<'a, T>>,
+    finished: bool,
+}
+
+impl<'a, T, I, F> LosslessIterator<'a, T, I, F>
+where
+    I: Iterator<Item=Result<Lexeme<'a, T>>>,
+    F: FnMut(&T) -> bool,
+{
+    pub(crate) fn new(source: &'a str, tokens: I, is_trivia: F) -> Self {
+        Self {
+            source,
+            tokens,
+            is_trivia,
+            end: Span {
+                line: 1,
+                column: 1,
+                ..Span::default()
+            },
+            pending: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Skipped text between the end of previous token and `offset`
+    fn skipped(self: &Self, offset: usize) -> Option<Trivia<'a, T>> {
+        (offset > self.end.offset).then(|| {
+            let span = Span {
+                length: offset - self.end.offset,
+                ..self.end.clone()
+            };
+
+            Trivia::Skipped(Lexeme {
+                value: (),
+                text: span.slice(self.source),
+                span,
+            })
+        })
+    }
+
+    /// Remember position right after the token
+    fn advance(self: &mut Self, span: &Span) {
+        let (line, column) = span.slice(self.source).chars()
+            .fold((span.line, span.column), |(line, column), c| match c {
+                '\n' => (line + 1, 1),
+                _ => (line, column + 1),
+            });
+
+        self.end = Span {
+            file: span.file.clone(),
+            offset: span.end(),
+            line,
+            column,
+            length: 0,
+        };
+    }
+}
+
+impl<'a, T, I, F> Iterator for LosslessIterator<'a, T, I, F>
+where
+    I: Iterator<Item=Result<Lexeme<'a, T>>>,
+    F: FnMut(&T) -> bool,
+{
+    type Item = Result<LosslessToken<'a, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut leading = std::mem::take(&mut self.pending);
+
+        loop {
+            match self.tokens.next() {
+                None => {
+                    self.finished = true;
+                    leading.extend(self.skipped(self.source.len()));
+
+                    return Some(Ok(LosslessToken { leading, token: None }));
+                },
+                Some(Err(error)) => {
+                    if let Some(span) = error.span.as_ref().filter(|span| span.offset >= self.end.offset && 0 != span.length)
+                    {
+                        leading.extend(self.skipped(span.offset));
+                        self.advance(span);
+                        leading.push(Trivia::Unknown(Lexeme {
+                            value: (),
+                            text: span.slice(self.source),
+                            span: span.clone(),
+                        }));
+                    }
+
+                    // Error can't carry trivia, so it's moved to the next item
+                    self.pending = leading;
+
+                    return Some(Err(error));
+                },
+                Some(Ok(lexeme)) => {
+                    leading.extend(self.skipped(lexeme.span.offset));
+                    self.advance(&lexeme.span);
+
+                    if (self.is_trivia)(&lexeme.value) {
+                        leading.push(Trivia::Token(lexeme));
+                    } else {
+                        return Some(Ok(LosslessToken { leading, token: Some(lexeme) }));
+                    }
+                },
+            }
+        }
+    }
+}
+
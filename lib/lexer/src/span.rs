@@ -0,0 +1,88 @@
+
+/// Location of a token in the source
+///
+/// `offset` and `length` are measured in bytes, `line` and `column` are
+/// 1-based and measured in characters. Zeroed span means that location is
+/// unknown (e.g. token wasn't produced by lexer).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: Option<std::sync::Arc<str>>,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+/// Token borrowing its text from the source it was produced from
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lexeme<'a, T> {
+    pub value: T,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl Span {
+    pub fn is_known(self: &Self) -> bool {
+        0 != self.line
+    }
+
+    pub fn end(self: &Self) -> usize {
+        self.offset + self.length
+    }
+
+    /// Text covered by the span in `source` it was produced from
+    pub fn slice<'a>(self: &Self, source: &'a str) -> &'a str {
+        &source[self.offset..self.end()]
+    }
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self: Self, f: F) -> Spanned<U> {
+        Spanned {
+            value: f(self.value),
+            span: self.span,
+        }
+    }
+}
+
+impl<'a, T> Lexeme<'a, T> {
+    pub fn new(source: &'a str, token: Spanned<T>) -> Self {
+        Self {
+            text: token.span.slice(source),
+            value: token.value,
+            span: token.span,
+        }
+    }
+}
+
+impl<'a, T> From<Lexeme<'a, T>> for Spanned<T> {
+    fn from(lexeme: Lexeme<'a, T>) -> Self {
+        Self::new(lexeme.value, lexeme.span)
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(value: T) -> Self {
+        Self::new(value, Span::default())
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file}:{}:{}", self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
@@ -1,10 +1,105 @@
 
 pub mod token;
 
+use std::ops::RangeInclusive;
+
+/// Values any constant should fit, either `hyper` or `unsigned hyper`
+pub const VALUE_RANGE: RangeInclusive<i128> = (i64::MIN as i128)..=(u64::MAX as i128);
+
+/// Parser folds expressions to [`Value::Number`], plain names of constants
+/// and enum items are kept
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Value {
-    Number(i64),
+    Number(i128),
     Identifier(String),
+    Unary(UnaryOperator, Box<Value>),
+    Binary(BinaryOperator, Box<Value>, Box<Value>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    ShiftLeft,
+    ShiftRight,
+    Or,
+    And,
+}
+
+impl BinaryOperator {
+    /// Binding strength, same as in C
+    pub fn precedence(self: &Self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 3,
+            BinaryOperator::Add | BinaryOperator::Subtract => 4,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => 5,
+        }
+    }
+
+    /// Result of the operation, `None` on division by zero or when it
+    /// doesn't fit [`VALUE_RANGE`]
+    pub fn apply(self: &Self, left: i128, right: i128) -> Option<i128> {
+        match self {
+            BinaryOperator::Add => left.checked_add(right),
+            BinaryOperator::Subtract => left.checked_sub(right),
+            BinaryOperator::Multiply => left.checked_mul(right),
+            BinaryOperator::Divide => left.checked_div(right),
+            BinaryOperator::Remainder => left.checked_rem(right),
+            BinaryOperator::ShiftLeft => u32::try_from(right).ok()
+                .and_then(|right| left.checked_shl(right))
+                .filter(|value| value >> right == left),
+            BinaryOperator::ShiftRight => u32::try_from(right).ok()
+                .and_then(|right| left.checked_shr(right)),
+            BinaryOperator::Or => Some(left | right),
+            BinaryOperator::And => Some(left & right),
+        }.filter(|value| VALUE_RANGE.contains(value))
+    }
+}
+
+impl UnaryOperator {
+    /// Result of the operation, `None` when it doesn't fit [`VALUE_RANGE`]
+    pub fn apply(self: &Self, value: i128) -> Option<i128> {
+        match self {
+            UnaryOperator::Negate => value.checked_neg(),
+            UnaryOperator::Not => Some(!value),
+        }.filter(|value| VALUE_RANGE.contains(value))
+    }
+}
+
+impl std::fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnaryOperator::Negate => write!(f, "-"),
+            UnaryOperator::Not => write!(f, "~"),
+        }
+    }
+}
+
+impl std::fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryOperator::Add => write!(f, "+"),
+            BinaryOperator::Subtract => write!(f, "-"),
+            BinaryOperator::Multiply => write!(f, "*"),
+            BinaryOperator::Divide => write!(f, "/"),
+            BinaryOperator::Remainder => write!(f, "%"),
+            BinaryOperator::ShiftLeft => write!(f, "<<"),
+            BinaryOperator::ShiftRight => write!(f, ">>"),
+            BinaryOperator::Or => write!(f, "|"),
+            BinaryOperator::And => write!(f, "&"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -13,6 +108,18 @@ pub enum Integer {
     Hyper,
 }
 
+impl Integer {
+    /// Values representable by the type
+    pub fn range(self: &Self, unsigned: bool) -> RangeInclusive<i128> {
+        match (self, unsigned) {
+            (Integer::Integer, false) => (i32::MIN as i128)..=(i32::MAX as i128),
+            (Integer::Integer, true) => 0..=(u32::MAX as i128),
+            (Integer::Hyper, false) => (i64::MIN as i128)..=(i64::MAX as i128),
+            (Integer::Hyper, true) => 0..=(u64::MAX as i128),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Float {
     Single,
@@ -27,6 +134,17 @@ pub enum SwitchingType {
     Enum(String),
 }
 
+impl SwitchingType {
+    /// Values allowed for union cases, enums are `int`
+    pub fn range(self: &Self) -> RangeInclusive<i128> {
+        match self {
+            SwitchingType::Integer(integer) => integer.range(false),
+            SwitchingType::Unsigned(integer) => integer.range(true),
+            SwitchingType::Enum(_) => Integer::Integer.range(false),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum NamedType {
     Typedef(String),
@@ -53,41 +171,115 @@ pub enum Type {
 #[derive(Debug, Clone)]
 pub struct Program {
     pub name: String,
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
+    /// Versions by their numbers, parser folds them to [`Value::Number`]
     pub versions: indexmap::IndexMap<Value, Version>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Version {
     pub name: String,
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
+    /// Procedures by their numbers, parser folds them to [`Value::Number`]
     pub procedures: indexmap::IndexMap<Value, Procedure>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Procedure {
     pub name: String,
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
     pub return_type: Type,
     pub arguments: Vec<Type>,
 }
 
-pub type Enum = Vec<(String, Option<Value>)>;
-pub type Struct = indexmap::IndexMap<String, Type>;
+#[derive(Debug, Clone)]
+pub struct Const {
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct Typedef {
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
+    pub tp: Type,
+}
+
+pub type Items = Vec<(String, Option<Value>)>;
+
+#[derive(Debug, Clone)]
+pub struct Enum {
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
+    pub items: Items,
+}
+
+pub type Fields = indexmap::IndexMap<String, Type>;
+
+#[derive(Debug, Clone)]
+pub struct Struct {
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
+    pub fields: Fields,
+}
+
+/// Union arm shared by its `case` labels
+#[derive(Debug, Clone)]
+pub struct Arm {
+    pub cases: Vec<Value>,
+    /// `None` for `void` arm
+    pub declaration: Option<(String, Type)>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Union {
+    /// Text of leading `/** */` or `///` comment
+    pub doc: Option<String>,
     pub value: String,
     pub switch_type: SwitchingType,
-    pub arms: indexmap::IndexMap<Value, (String, Type)>,
-    pub default: Option<(String, Type)>,
+    pub arms: Vec<Arm>,
+    /// Arm for the rest of values, it may share `case` labels too
+    pub default: Option<Arm>,
+}
+
+impl Union {
+    /// Fields of all non `void` arms including the default one
+    pub fn declarations(self: &Self) -> impl Iterator<Item=&(String, Type)> {
+        self.arms.iter()
+            .chain(self.default.iter())
+            .filter_map(|arm| arm.declaration.as_ref())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Definition {
-    Const(String, Value),
-    Typedef(String, Type),
+    Const(String, Const),
+    Typedef(String, Typedef),
     Enum(String, Enum),
     Struct(String, Struct),
     Union(String, Union),
     Program(Value, Program),
+    /// Text of `%` line to be copied into generated headers
+    Passthrough(String),
+}
+
+impl Definition {
+    /// Doc comment of the definition, `%` lines have none
+    pub fn doc_mut(self: &mut Self) -> Option<&mut Option<String>> {
+        match self {
+            Definition::Const(_, c) => Some(&mut c.doc),
+            Definition::Typedef(_, t) => Some(&mut t.doc),
+            Definition::Enum(_, en) => Some(&mut en.doc),
+            Definition::Struct(_, st) => Some(&mut st.doc),
+            Definition::Union(_, un) => Some(&mut un.doc),
+            Definition::Program(_, program) => Some(&mut program.doc),
+            Definition::Passthrough(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -96,18 +288,25 @@ pub struct Module {
 }
 
 pub fn new_enum() -> Enum {
-    Vec::new()
+    Enum {
+        doc: None,
+        items: Vec::new(),
+    }
 }
 
 pub fn new_struct() -> Struct {
-    indexmap::IndexMap::new()
+    Struct {
+        doc: None,
+        fields: indexmap::IndexMap::new(),
+    }
 }
 
 pub fn new_union() -> Union {
     Union {
+        doc: None,
         value: String::new(),
         switch_type: SwitchingType::Integer(Integer::Integer),
-        arms: indexmap::IndexMap::new(),
+        arms: Vec::new(),
         default: None,
     }
 }
@@ -115,6 +314,7 @@ pub fn new_union() -> Union {
 pub fn new_program() -> Program {
     Program {
         name: String::new(),
+        doc: None,
         versions: indexmap::IndexMap::new(),
     }
 }
@@ -122,6 +322,7 @@ pub fn new_program() -> Program {
 pub fn new_version() -> Version {
     Version {
         name: String::new(),
+        doc: None,
         procedures: indexmap::IndexMap::new(),
     }
 }
@@ -129,6 +330,7 @@ pub fn new_version() -> Version {
 pub fn new_procedure() -> Procedure {
     Procedure {
         name: String::new(),
+        doc: None,
         return_type: Type::Void,
         arguments: Vec::new(),
     }
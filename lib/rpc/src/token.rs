@@ -1,7 +1,9 @@
 
+pub use lexer::{Span, Spanned};
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Literal {
-    Integer(i64),
+    Integer(i128),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -41,6 +43,15 @@ pub enum Type {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Operator {
     Assign,
+    Add,
+    Subtract,
+    Divide,
+    Remainder,
+    ShiftLeft,
+    ShiftRight,
+    Or,
+    And,
+    Not,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -72,8 +83,15 @@ pub enum Token {
     Literal(Literal),
     Operator(Operator),
     Comment(String),
+    /// `%` at the start of a line, the rest of line follows as
+    /// [`Token::Passthrough`]
+    PassthroughStart,
+    /// Line after `%`, copied to output as is
+    Passthrough(String),
 }
 
+pub type SpannedToken = Spanned<Token>;
+
 impl std::fmt::Display for Bracket {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -114,6 +132,15 @@ impl std::fmt::Display for Operator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Operator::Assign => write!(f, "="),
+            Operator::Add => write!(f, "+"),
+            Operator::Subtract => write!(f, "-"),
+            Operator::Divide => write!(f, "/"),
+            Operator::Remainder => write!(f, "%"),
+            Operator::ShiftLeft => write!(f, "<<"),
+            Operator::ShiftRight => write!(f, ">>"),
+            Operator::Or => write!(f, "|"),
+            Operator::And => write!(f, "&"),
+            Operator::Not => write!(f, "~"),
         }
     }
 }
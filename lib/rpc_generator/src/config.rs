@@ -2,6 +2,7 @@
 pub struct Config<P: AsRef<std::path::Path>> {
     pub path: Option<P>,
     pub vla_limit: Option<usize>,
+    pub recursion_limit: Option<usize>,
 }
 
 impl<P: AsRef<std::path::Path>> Config<P> {
@@ -9,6 +10,7 @@ impl<P: AsRef<std::path::Path>> Config<P> {
         Self {
             path: None,
             vla_limit: None,
+            recursion_limit: None,
         }
     }
 }
@@ -33,3 +35,13 @@ pub(crate) fn vla_limit<P: AsRef<std::path::Path>>(cfg: &Option<Config<P>>) -> u
     }
 }
 
+
+pub(crate) fn recursion_limit<P: AsRef<std::path::Path>>(cfg: &Option<Config<P>>) -> usize {
+    match cfg {
+        None => 64,
+        Some(cfg) => match &cfg.recursion_limit {
+            None => 64,
+            Some(limit) => *limit,
+        },
+    }
+}
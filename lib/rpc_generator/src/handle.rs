@@ -3,54 +3,177 @@ pub(crate) enum Type {
     Typedef(String),
     Struct(String),
     Union(String),
+    /// `%` line placed between types
+    Passthrough(String),
 }
 
 pub(crate) struct TypeDefinitions {
-    pub typedefs: indexmap::IndexMap<String, rpc::Type>,
+    pub typedefs: indexmap::IndexMap<String, rpc::Typedef>,
     pub structs: indexmap::IndexMap<String, rpc::Struct>,
     pub unions: indexmap::IndexMap<String, rpc::Union>,
     pub enums: indexmap::IndexMap<String, rpc::Enum>,
 }
 
 pub(crate) struct Module {
-    pub constants: indexmap::IndexMap<String, rpc::Value>,
+    pub constants: indexmap::IndexMap<String, rpc::Const>,
     pub types: TypeDefinitions,
-    pub programs: indexmap::IndexMap<rpc::Value, rpc::Program>
+    pub programs: indexmap::IndexMap<rpc::Value, rpc::Program>,
+}
+
+/// `%` lines by names of definitions following them, lines before types are
+/// kept in [`DefinitionOrder::types`]
+pub(crate) struct Passthrough {
+    pub constants: std::collections::HashMap<String, Vec<String>>,
+    pub enums: std::collections::HashMap<String, Vec<String>>,
+    pub programs: std::collections::HashMap<String, Vec<String>>,
+    /// Lines after the last definition
+    pub trailing: Vec<String>,
 }
 
 pub(crate) struct DefinitionOrder {
     pub types: Vec<Type>,
+    pub passthrough: Passthrough,
+    /// Structs and unions used through pointers before their definitions
+    pub forward: Vec<String>,
+}
+
+/// Structs and unions which contain themselves through pointers or variable
+/// length arrays, types containing each other share the group
+pub(crate) struct Recursion {
+    pub groups: Vec<Vec<rpc::NamedType>>,
+    pub group: std::collections::HashMap<rpc::NamedType, usize>,
 }
 
 pub(crate) struct Handle {
     pub module: Module,
     pub order: DefinitionOrder,
+    pub recursion: Recursion,
+}
+
+/// Structs and unions reachable from type
+fn reachable(types: &TypeDefinitions, tp: &rpc::Type, out: &mut std::collections::HashSet<rpc::NamedType>) {
+    match tp {
+        rpc::Type::Pointer(tp) | rpc::Type::Array(tp, _) | rpc::Type::VArray(tp, _) =>
+            reachable(types, tp, out),
+        rpc::Type::Named(rpc::NamedType::Typedef(name)) =>
+            reachable(types, &types.typedefs.get(name).expect("Was added").tp, out),
+        rpc::Type::Named(named @ rpc::NamedType::Struct(name)) if out.insert(named.clone()) =>
+            types.structs.get(name).expect("Was added").fields.values()
+                .for_each(|tp| reachable(types, tp, out)),
+        rpc::Type::Named(named @ rpc::NamedType::Union(name)) if out.insert(named.clone()) =>
+            types.unions.get(name).expect("Was added").declarations()
+                .for_each(|(_, tp)| reachable(types, tp, out)),
+        _ => {},
+    }
 }
 
 impl Handle {
+    pub fn recursion_group(self: &Self, named: &rpc::NamedType) -> Option<usize> {
+        self.recursion.group.get(named).copied()
+    }
+
+    fn find_recursion(self: &mut Self) {
+        let types = &self.module.types;
+        let reach = self.order.types.iter().filter_map(|tp| match tp {
+            Type::Struct(name) => Some(rpc::NamedType::Struct(name.clone())),
+            Type::Union(name) => Some(rpc::NamedType::Union(name.clone())),
+            _ => None,
+        }).map(|named| {
+            let mut out = std::collections::HashSet::new();
+
+            match &named {
+                rpc::NamedType::Struct(name) => types.structs.get(name).expect("Was added").fields.values()
+                    .for_each(|tp| reachable(types, tp, &mut out)),
+                rpc::NamedType::Union(name) => types.unions.get(name).expect("Was added").declarations()
+                    .for_each(|(_, tp)| reachable(types, tp, &mut out)),
+                _ => panic!("Unexpected named type: {named:?}"),
+            }
+
+            (named, out)
+        }).filter(|(named, out)| out.contains(named)).collect::<Vec<_>>();
+
+        reach.iter().for_each(|(named, out)| if !self.recursion.group.contains_key(named) {
+            let index = self.recursion.groups.len();
+            let group = reach.iter()
+                .filter(|(other, other_out)| out.contains(other) && other_out.contains(named))
+                .map(|(other, _)| other.clone())
+                .collect::<Vec<_>>();
+
+            group.iter().for_each(|other| {
+                self.recursion.group.insert(other.clone(), index);
+            });
+            self.recursion.groups.push(group);
+        });
+    }
+
+    /// Parser puts types after the ones they contain, so struct or union
+    /// which is not defined yet is used by pointer
+    fn declare_used(self: &mut Self, tp: &rpc::Type, user: &str) {
+        match tp {
+            rpc::Type::Pointer(tp) | rpc::Type::Array(tp, _) | rpc::Type::VArray(tp, _) =>
+                self.declare_used(tp, user),
+            rpc::Type::Named(rpc::NamedType::Struct(name) | rpc::NamedType::Union(name)) => {
+                let types = &self.module.types;
+
+                if name != user && !types.structs.contains_key(name) && !types.unions.contains_key(name)
+                    && !self.order.forward.contains(name) {
+                    self.order.forward.push(name.clone());
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Put `%` lines read so far before the type
+    fn push_type(self: &mut Self, tp: Type) {
+        let lines = std::mem::take(&mut self.order.passthrough.trailing);
+
+        self.order.types.extend(lines.into_iter().map(Type::Passthrough));
+        self.order.types.push(tp);
+    }
+
     fn read_definition(self: &mut Self, def: rpc::Definition) {
+        let passthrough = &mut self.order.passthrough;
+
+        match &def {
+            rpc::Definition::Const(name, _) if !passthrough.trailing.is_empty() => {
+                passthrough.constants.insert(name.clone(), std::mem::take(&mut passthrough.trailing));
+            },
+            rpc::Definition::Enum(name, _) if !passthrough.trailing.is_empty() => {
+                passthrough.enums.insert(name.clone(), std::mem::take(&mut passthrough.trailing));
+            },
+            rpc::Definition::Program(_, program) if !passthrough.trailing.is_empty() => {
+                passthrough.programs.insert(program.name.clone(), std::mem::take(&mut passthrough.trailing));
+            },
+            _ => {},
+        }
+
         match def {
-            rpc::Definition::Const(name, value) => {
-                self.module.constants.insert(name, value);
+            rpc::Definition::Const(name, c) => {
+                self.module.constants.insert(name, c);
             },
-            rpc::Definition::Typedef(name, tp) => {
-                self.order.types.push(Type::Typedef(name.clone()));
-                self.module.types.typedefs.insert(name, tp);
+            rpc::Definition::Typedef(name, typedef) => {
+                self.declare_used(&typedef.tp, &name);
+                self.push_type(Type::Typedef(name.clone()));
+                self.module.types.typedefs.insert(name, typedef);
             },
             rpc::Definition::Enum(name, en) => {
                 self.module.types.enums.insert(name, en);
             },
             rpc::Definition::Struct(name, st) => {
-                self.order.types.push(Type::Struct(name.clone()));
+                st.fields.values().for_each(|tp| self.declare_used(tp, &name));
+                self.push_type(Type::Struct(name.clone()));
                 self.module.types.structs.insert(name, st);
             },
             rpc::Definition::Union(name, un) => {
-                self.order.types.push(Type::Union(name.clone()));
+                un.declarations().for_each(|(_, tp)| self.declare_used(tp, &name));
+                self.push_type(Type::Union(name.clone()));
                 self.module.types.unions.insert(name, un);
             },
             rpc::Definition::Program(value, program) => {
                 self.module.programs.insert(value, program);
             },
+            rpc::Definition::Passthrough(line) => self.order.passthrough.trailing.push(line),
         }
     }
 }
@@ -70,10 +193,22 @@ impl FromIterator<rpc::Definition> for Handle {
             },
             order: DefinitionOrder {
                 types: Vec::new(),
+                passthrough: Passthrough {
+                    constants: std::collections::HashMap::new(),
+                    enums: std::collections::HashMap::new(),
+                    programs: std::collections::HashMap::new(),
+                    trailing: Vec::new(),
+                },
+                forward: Vec::new(),
+            },
+            recursion: Recursion {
+                groups: Vec::new(),
+                group: std::collections::HashMap::new(),
             },
         };
 
         iter.into_iter().for_each(|def| handle.read_definition(def));
+        handle.find_recursion();
 
         handle
     }
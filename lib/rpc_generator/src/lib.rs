@@ -1,4 +1,6 @@
 
+#[cfg(test)]
+mod test;
 pub mod config;
 mod handle;
 mod file;
@@ -34,16 +36,27 @@ fn generate_constants(
 ) -> std::io::Result<()> {
     let mut file = file::HFile::new(config::path(cfg).join("constants.h"))
         .expect("Filename provided");
+    let passthrough = &handle.order.passthrough;
 
     types::misc_constants(&mut file, types::Constants::new(cfg));
     "".chain(IteratorPrinter::from(
-        handle.module.constants.iter()
+        handle.module.constants.iter().map(|(name, c)| misc::passthrough(
+            &passthrough.constants, name
+        ).chain(misc::kernel_doc(
+            name.clone(), c.doc.as_deref()
+        )).chain((name, &c.value)))
     )).chain("").chain(IteratorPrinter::from(
-        handle.module.programs.iter().map(|(v, progr)|
-            (&progr.name, v)
-        )
+        handle.module.programs.iter().map(|(v, progr)| misc::passthrough(
+            &passthrough.programs, &progr.name
+        ).chain(misc::kernel_doc(
+            progr.name.clone(), progr.doc.as_deref()
+        )).chain((&progr.name, v)))
     )).chain("").chain(IteratorPrinter::from(
-        handle.module.types.enums.iter()
+        handle.module.types.enums.iter().map(|(name, en)| misc::passthrough(
+            &passthrough.enums, name
+        ).chain(misc::kernel_doc(
+            format!("enum {name}"), en.doc.as_deref()
+        )).chain((name, &en.items)))
     )).print(&mut file);
 
     file.finish();
@@ -57,25 +70,49 @@ fn generate_types(
     let mut hfile = file::HFile::new(config::path(cfg).join("types.h"))
         .expect("Filename provided");
 
+    let recursive = !handle.recursion.groups.is_empty();
+
     IteratorPrinter::from([
         "#include <linux/module.h>",
+    ]).chain(recursive.then(|| IteratorPrinter::from([
+        "#include <linux/slab.h>",
+        "#include <linux/sunrpc/xdr.h>",
+    ]))).chain(IteratorPrinter::from([
         "",
         "#include \"constants.h\"",
         "",
-    ]).switch(types::misc_types).chain("").switch(|file| handle.order.types.iter().for_each(|tp| {
+    ])).switch(types::misc_types).chain("").chain((!handle.order.forward.is_empty()).then(||
+        IteratorPrinter::from(handle.order.forward.iter().map(|name| format!("struct {name};"))).chain("")
+    )).switch(|file| handle.order.types.iter().for_each(|tp| {
+        let types = &handle.module.types;
+
         match tp {
-            Type::Typedef(name) => (
-                name, handle.module.types.typedefs.get(name).expect("Was added")
-            ).print(file),
-            Type::Struct(name) => (
-                name, handle.module.types.structs.get(name).expect("Was added")
-            ).print(file),
-            Type::Union(name) => (
-                name, handle.module.types.unions.get(name).expect("Was added")
-            ).print(file),
+            Type::Typedef(name) => {
+                let typedef = types.typedefs.get(name).expect("Was added");
+
+                misc::kernel_doc(format!("typedef {name}"), typedef.doc.as_deref())
+                    .chain((name, &typedef.tp)).print(file)
+            },
+            Type::Struct(name) => {
+                let st = types.structs.get(name).expect("Was added");
+
+                misc::kernel_doc(format!("struct {name}"), st.doc.as_deref())
+                    .chain((name, &st.fields)).print(file)
+            },
+            Type::Union(name) => {
+                let un = types.unions.get(name).expect("Was added");
+
+                misc::kernel_doc(format!("struct {name}"), un.doc.as_deref())
+                    .chain((name, un)).print(file)
+            },
+            Type::Passthrough(line) => return line.as_str().print(file),
         }
         "".print(file);
-    })).print(&mut hfile);
+    })).chain((!handle.order.passthrough.trailing.is_empty()).then(|| IteratorPrinter::from(
+        handle.order.passthrough.trailing.iter().map(String::as_str)
+    ).chain(""))).switch(|file| if recursive {
+        types::generate_recursive_functions(handle, file)
+    }).print(&mut hfile);
 
     hfile.finish();
     hfile.result()
@@ -1,13 +1,32 @@
 
-use crate::handle;
-
-pub fn unwrap_value(handle: &handle::Handle, v: &rpc::Value) -> i64 {
-    match v {
-        rpc::Value::Number(num) => *num,
-        rpc::Value::Identifier(id) => unwrap_value(
-            handle,
-            handle.module.constants.get(id).expect("Was set")
-        ),
-    }
+use crate::file::{Printable, IteratorPrinter};
+
+/// Kernel-doc comment for `name`, first line of `doc` is the short
+/// description and the rest goes after a blank line
+pub fn kernel_doc(name: String, doc: Option<&str>) -> Option<impl Printable> {
+    doc.map(|doc| {
+        // Keep `*/` in the text from closing the comment
+        let doc = doc.replace("*/", "*\\/");
+        let mut lines = doc.lines();
+        let brief = format!(" * {name} - {}", lines.next().unwrap_or_default());
+        let rest = lines.skip_while(|line| line.is_empty()).map(|line| match line {
+            "" => String::from(" *"),
+            line => format!(" * {line}"),
+        }).collect::<Vec<_>>();
+
+        IteratorPrinter::from(
+            [String::from("/**"), brief].into_iter()
+                .chain((!rest.is_empty()).then(|| String::from(" *")))
+                .chain(rest)
+                .chain([String::from(" */")])
+        )
+    })
 }
 
+/// `%` lines going right before definition `name`
+pub fn passthrough<'a>(
+    lines: &'a std::collections::HashMap<String, Vec<String>>,
+    name: &str,
+) -> Option<impl Printable + 'a> {
+    lines.get(name).map(|lines| IteratorPrinter::from(lines.iter().map(String::as_str)))
+}
@@ -78,7 +78,9 @@ pub fn generate_program_version_array(_: &handle::Handle, file: &mut dyn File, p
 
 pub fn generate_program_constants(_: &handle::Handle, file: &mut dyn File, program: &rpc::Program) {
     IteratorPrinter::from(
-        program.versions.iter().map(|(value, version)| (&version.name, value))
+        program.versions.iter().map(|(value, version)| crate::misc::kernel_doc(
+            version.name.clone(), version.doc.as_deref()
+        ).chain((&version.name, value)))
     ).print(file)
 }
 
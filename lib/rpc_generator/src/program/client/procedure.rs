@@ -1,6 +1,7 @@
 
 use crate::{
     handle,
+    misc,
     types,
     file::{
         File,
@@ -52,8 +53,11 @@ pub fn generate_procedure_api_definition(_: &handle::Handle, file: &mut dyn File
         format!("    int error;"),
         format!("}} {}_result_t;", proc.name),
         format!(""),
-        format!("{0}_result_t {prog}_{ver}_{0}({1});", proc.name, expand_arguments(proc)),
-    ])).print(file)
+    ])).chain(misc::kernel_doc(
+        format!("{prog}_{ver}_{}()", proc.name), proc.doc.as_deref()
+    )).chain(
+        format!("{0}_result_t {prog}_{ver}_{0}({1});", proc.name, expand_arguments(proc))
+    ).print(file)
 }
 
 pub fn generate_procedure_api_declaration(handle: &handle::Handle, file: &mut dyn File, proc: &rpc::Procedure, ver: &str, prog: &str) {
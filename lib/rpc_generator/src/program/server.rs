@@ -31,9 +31,12 @@ pub fn generate_program_auth_declaraion(_: &handle::Handle, file: &mut dyn File,
     ]).print(file)
 }
 
-pub fn generate_program_declaraion(handle: &handle::Handle, file: &mut dyn File, progr: &rpc::Program) {
+pub fn generate_program_declaraion(_: &handle::Handle, file: &mut dyn File, progr: &rpc::Program) {
     let (min_v, max_v) = progr.versions.keys()
-        .map(|v| crate::misc::unwrap_value(handle, v))
+        .map(|v| match v {
+            rpc::Value::Number(num) => *num,
+            _ => panic!("Version number isn't folded: {v:?}"),
+        })
         .fold((-1, -1), |(mut min, mut max), c| {
             if -1 == min || min > c {
                 min = c
@@ -126,6 +129,8 @@ pub fn generate_program_version_array(_: &handle::Handle, file: &mut dyn File, p
 
 pub fn generate_program_constants(_: &handle::Handle, file: &mut dyn File, program: &rpc::Program) {
     IteratorPrinter::from(
-        program.versions.iter().map(|(value, version)| (&version.name, value))
+        program.versions.iter().map(|(value, version)| crate::misc::kernel_doc(
+            version.name.clone(), version.doc.as_deref()
+        ).chain((&version.name, value)))
     ).print(file)
 }
@@ -1,6 +1,7 @@
 
 use crate::{
     handle,
+    misc,
     types,
     file::{
         File,
@@ -50,7 +51,8 @@ pub fn generate_procedure_handler_definition(_: &handle::Handle, file: &mut dyn
 }
 
 pub fn generate_procedure_handler_declaration(handle: &handle::Handle, file: &mut dyn File, proc: &rpc::Procedure, ver: &str) {
-    format!("__be32 {ver}_{}_handler(struct svc_rqst *rqstp) {{", proc.name)
+    misc::kernel_doc(format!("{ver}_{}_handler()", proc.name), proc.doc.as_deref())
+        .chain(format!("__be32 {ver}_{}_handler(struct svc_rqst *rqstp) {{", proc.name))
         .chain(match proc.return_type {
             rpc::Type::Void => None,
             _ => Some({
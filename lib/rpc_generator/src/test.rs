@@ -0,0 +1,288 @@
+use lexer::Lexer;
+
+use super::*;
+
+/// Program every generated module needs, it goes before `spec`
+const PROGRAM: &str = "program TEST_PROG { version TEST_VERS { int ECHO(int) = 1; } = 1; } = 0x20000001;\n";
+
+fn definitions(spec: &str) -> Vec<rpc::Definition> {
+    rpc_parser::parse(rpc_lexer::lexer().parse_str(spec).map(|t| t.unwrap())).unwrap().definitions
+}
+
+/// Generate files for specification into a fresh directory
+fn generate_spec(name: &str, spec: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rpc_generator_{}_{name}", std::process::id()));
+    let mut cfg = config::Config::new();
+
+    cfg.path = Some(dir.clone());
+    generate(definitions(&(PROGRAM.to_string() + spec)).into_iter(), Some(cfg)).unwrap();
+
+    dir
+}
+
+fn read(dir: &std::path::Path, file: &str) -> String {
+    std::fs::read_to_string(dir.join(file)).unwrap()
+}
+
+/// Lines of `text` from the one equal to `first` up to the one equal to `last`
+fn section<'a>(text: &'a str, first: &str, last: &str) -> Vec<&'a str> {
+    let lines = text.lines().skip_while(|line| *line != first).collect::<Vec<_>>();
+    let end = lines.iter().position(|line| *line == last).unwrap();
+
+    lines[..=end].to_vec()
+}
+
+#[test]
+fn passthrough() {
+    let dir = generate_spec("passthrough", "
+const A = 1;
+%#define AFTER_A
+const B = 2;
+struct outer {
+    inner x;
+};
+%#define BEFORE_INNER
+struct inner {
+    int y;
+};
+%#define LAST
+");
+    let constants = read(&dir, "constants.h");
+    let types = read(&dir, "types.h");
+
+    assert_eq!(
+        vec!["#define A 1", "#define AFTER_A", "#define B 2"],
+        section(&constants, "#define A 1", "#define B 2"),
+    );
+
+    let inner = types.find("#define BEFORE_INNER").unwrap();
+    assert_eq!(Some(inner), types.find("#define BEFORE_INNER\nstruct inner {"));
+    assert!(inner < types.find("struct outer {").unwrap());
+    assert!(types.find("#define LAST").unwrap() > types.find("struct outer {").unwrap());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn doc_comments() {
+    let dir = generate_spec("doc_comments", "
+/** Answer */
+const ANSWER = 42;
+/**
+ * Pair
+ *
+ * Two ints
+ */
+struct pair {
+    int a;
+    int b;
+};
+/// Colors
+enum color { RED, GREEN };
+struct plain {
+    int x;
+};
+/// returns a/*b*/ value
+const ESCAPED = 1;
+");
+    let constants = read(&dir, "constants.h");
+    let types = read(&dir, "types.h");
+
+    assert_eq!(
+        vec!["/**", " * ANSWER - Answer", " */", "#define ANSWER 42"],
+        section(&constants, "/**", "#define ANSWER 42"),
+    );
+    assert_eq!(
+        vec![" * enum color - Colors", " */", "enum color {"],
+        section(&constants, " * enum color - Colors", "enum color {"),
+    );
+    assert_eq!(
+        vec!["/**", " * struct pair - Pair", " *", " * Two ints", " */", "struct pair {"],
+        section(&types, "/**", "struct pair {"),
+    );
+    assert!(types.contains("};\n\nstruct plain {"));
+    assert_eq!(
+        vec![" * ESCAPED - returns a/*b*\\/ value", " */", "#define ESCAPED 1"],
+        section(&constants, " * ESCAPED - returns a/*b*\\/ value", "#define ESCAPED 1"),
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn folded_numbers() {
+    let dir = generate_spec("folded_numbers", "
+enum vers { V1 = 1, V2 };
+program P { version PV1 { int F(int) = 1; } = V1; version PV2 { int G(int) = 1; } = V2; } = 0x20000002;
+");
+    let program = read(&dir, "servers/P/program.c");
+
+    assert!(program.contains("    .pg_lovers = 1,\n    .pg_hivers = 2,"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let dir = generate_spec("folded_values", "
+const BASE = 1 << 40;
+const HDR = 4;
+const LOWEST = -9223372036854775807 - 1;
+const HIGHEST = 0xFFFFFFFFFFFFFFF0 + 15;
+enum flags { F1 = HDR, F2 = 1 << 3 };
+struct packet {
+    opaque buf[HDR + 16];
+    opaque name<HDR * 2>;
+};
+program PKT_PROG { version PKT_VERS { int SEND(packet) = 1; } = 1; } = 0x20000002;
+");
+    let constants = read(&dir, "constants.h");
+    let types = read(&dir, "types.h");
+
+    assert!(constants.contains("#define BASE 1099511627776\n"));
+    assert!(constants.contains("#define HDR 4\n"));
+    assert!(constants.contains("#define LOWEST (-9223372036854775807LL - 1)\n"));
+    assert!(constants.contains("#define HIGHEST 18446744073709551615ULL\n"));
+    assert!(constants.contains("F1 = HDR,\n    F2 = 8,"), "{constants}");
+    assert!(types.contains("buf[20]"), "{types}");
+    assert!(read(&dir, "servers/PKT_PROG/PKT_VERS/version.c")
+        .contains("(20*(sizeof(char))+sizeof(u32)+8*(sizeof(char)))"));
+    assert!(read(&dir, "servers/PKT_PROG/PKT_VERS/procedure_xdr.c")
+        .contains("if (0 == rc && 8 < ((*arg).name).size) {"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn union_arms() {
+    let dir = generate_spec("union_arms", "
+const A = 1;
+const B = 2;
+const C = 3;
+union u switch (int d) {
+case A:
+case B:
+    int x;
+case C:
+    void;
+default:
+    void;
+};
+program U_PROG { version U_VERS { u GET(u) = 1; } = 1; } = 0x20000002;
+");
+    let xdr = read(&dir, "servers/U_PROG/U_VERS/procedure_xdr.c");
+    let labels = |access: &str| section(&xdr, &format!("        switch ({access}.d) {{"), "        }")
+        .into_iter()
+        .map(str::trim)
+        .filter(|line| line.starts_with("case") || line.starts_with("default") || line.starts_with("break")
+            || line.contains(".u_u.x"))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        vec![
+            "case (A):", "case (B):",
+            "&& 0 > (_rc = xdr_stream_decode_u32(xdr, &((*arg).u_u.x)))) {",
+            "break;",
+            "case (C):", "break;",
+            "default:", "break;",
+        ],
+        labels("(*arg)"),
+    );
+    assert_eq!(
+        vec![
+            "case (A):", "case (B):",
+            "&& 0 > (_rc = xdr_stream_encode_u32(xdr, (*res).u_u.x))) {",
+            "break;",
+            "case (C):", "break;",
+            "default:", "break;",
+        ],
+        labels("(*res)"),
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn forward_declarations() {
+    let dir = generate_spec("forward_declarations", "
+struct a {
+    b *next;
+    a *self;
+};
+struct b {
+    int v;
+};
+");
+    let types = read(&dir, "types.h");
+    let declaration = types.find("\nstruct b;\n").unwrap();
+
+    assert!(declaration < types.find("struct a {").unwrap());
+    assert!(!types.contains("\nstruct a;\n"));
+    assert_eq!(1, types.matches("\nstruct b;\n").count());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Node is recursive by itself, tree and branch contain each other
+const RECURSIVE: &str = "
+struct node {
+    int v;
+    node *next;
+};
+struct tree {
+    int v;
+    branch *left;
+};
+union branch switch (int d) {
+case 1:
+    tree t;
+default:
+    void;
+};
+struct plain {
+    node *head;
+};
+";
+
+#[test]
+fn find_recursion() {
+    let handle = definitions(RECURSIVE).into_iter().collect::<handle::Handle>();
+    let group = |named: rpc::NamedType| handle.recursion_group(&named);
+    let node = group(rpc::NamedType::Struct("node".to_string()));
+    let tree = group(rpc::NamedType::Struct("tree".to_string()));
+
+    assert_eq!(2, handle.recursion.groups.len());
+    assert!(node.is_some() && tree.is_some() && node != tree);
+    assert_eq!(tree, group(rpc::NamedType::Union("branch".to_string())));
+    assert_eq!(None, group(rpc::NamedType::Struct("plain".to_string())));
+    assert_eq!(
+        vec![rpc::NamedType::Struct("tree".to_string()), rpc::NamedType::Union("branch".to_string())],
+        handle.recursion.groups[tree.unwrap()],
+    );
+}
+
+#[test]
+fn recursive_types() {
+    let dir = generate_spec("recursive_types", &(RECURSIVE.to_string()
+        + "program R_PROG { version R_VERS { node GET(node) = 1; branch WALK(tree) = 2; } = 1; } = 0x20000002;"
+    ));
+    let types = read(&dir, "types.h");
+    let version = read(&dir, "servers/R_PROG/R_VERS/version.c");
+
+    assert!(types.contains("#include <linux/slab.h>"));
+    assert_eq!(
+        vec!["struct node {", "    s32 v;", "    struct node* next;", "};"],
+        section(&types, "struct node {", "};"),
+    );
+    assert!(types.find("struct branch;").unwrap() < types.find("struct tree {").unwrap());
+    assert!(types.contains(
+        "static inline int xdr_decode_struct_node(struct xdr_stream *xdr, struct node *value, u32 *limit);"
+    ));
+    assert!(types.contains("xdr_decode_struct_node(xdr, &(*((*value).next)), limit)"));
+    assert!(types.contains("xdr_decode_struct_tree(xdr, &((*value).branch_u.t), limit)"));
+    assert!(!types.contains("xdr_decode_struct_plain"));
+
+    assert!(version.contains("        .pc_xdrressize = RECURSION_LIMIT*(sizeof(s32)+sizeof(u32)),\n"));
+    assert!(version.contains(
+        "        .pc_xdrressize = RECURSION_LIMIT*(STATIC_MAX((sizeof(s32)+sizeof(u32)),(sizeof(s32)+0))),\n"
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -23,16 +23,15 @@ pub fn uses_dynamic_memory(handle: &handle::Handle, tp: &rpc::Type) -> bool {
         rpc::Type::Named(named) => match named {
             rpc::NamedType::Typedef(name) =>
                 uses_dynamic_memory(handle,
-                    handle.module.types.typedefs.get(name).expect("Was added")
+                    &handle.module.types.typedefs.get(name).expect("Was added").tp
                 ),
             rpc::NamedType::Enum(_) => false,
             rpc::NamedType::Struct(name) => handle.module.types.structs.get(name).expect("Was added")
-                .values()
+                .fields.values()
                 .any(|tp| uses_dynamic_memory(handle, tp)),
             rpc::NamedType::Union(name) => {
                 let un = handle.module.types.unions.get(name).expect("Was added");
-                un.arms.values().chain(un.default.iter())
-                    .any(|(_, tp)| uses_dynamic_memory(handle, tp))
+                un.declarations().any(|(_, tp)| uses_dynamic_memory(handle, tp))
             },
         }
     }
@@ -42,14 +41,19 @@ fn append_or_self(s: Option<String>, current: String) -> String {
     s.map(|out| out + "+" + &current).unwrap_or(current)
 }
 
-fn generate_xdr_size_inner(handle: &handle::Handle, tp: &rpc::Type, out: Option<String>) -> String {
+fn generate_xdr_size_inner(
+    handle: &handle::Handle,
+    tp: &rpc::Type,
+    out: Option<String>,
+    group: Option<usize>,
+) -> String {
     match tp {
         rpc::Type::Void => append_or_self(out, String::from("0")),
         rpc::Type::Pointer(tp) => generate_xdr_size_inner(handle, tp,
-            Some(append_or_self(out, format!("sizeof(u32)")))
+            Some(append_or_self(out, format!("sizeof(u32)"))), group,
         ),
         rpc::Type::Array(tp, sz) => append_or_self(out,
-            format!("{}*({})", asc::value(sz), generate_xdr_size(handle, tp))
+            format!("{}*({})", asc::value(sz), generate_xdr_size_inner(handle, tp, None, group))
         ),
         rpc::Type::VArray(tp, sz) => {
             append_or_self(out,
@@ -58,41 +62,61 @@ fn generate_xdr_size_inner(handle: &handle::Handle, tp: &rpc::Type, out: Option<
                         || String::from("VLA_LIMIT"),
                         asc::value,
                     ),
-                    generate_xdr_size(handle, tp)
+                    generate_xdr_size_inner(handle, tp, None, group)
                 )
             )
         }
         rpc::Type::Named(named) => match named {
             rpc::NamedType::Typedef(name) => generate_xdr_size_inner(
                 handle,
-                handle.module.types.typedefs.get(name).expect("Was added"),
-                out
+                &handle.module.types.typedefs.get(name).expect("Was added").tp,
+                out,
+                group,
             ),
             rpc::NamedType::Enum(name) =>
                 append_or_self(out, format!("sizeof(enum {name})")),
-            rpc::NamedType::Struct(name) => handle.module.types.structs.get(name)
-                .expect("Was added").values()
-                .fold(out, |out, tp| {
-                    Some(append_or_self(out, generate_xdr_size(handle, tp)))
-                }).expect("At least one field in struct"),
-            rpc::NamedType::Union(name) => {
-                let un = handle.module.types.unions.get(name).expect("Was added");
-
-                append_or_self(out, format!("{}+{}",
-                    generate_switch_xdr_size(handle, &un.switch_type),
-                    un.arms.values().chain(un.default.iter()).map(|(_, v)| v)
-                        .map(|current| generate_xdr_size(handle, current))
+            rpc::NamedType::Struct(_) | rpc::NamedType::Union(_) => match handle.recursion_group(named) {
+                // Element of the group is already counted by the limit
+                Some(current) if Some(current) == group => out.unwrap_or_else(|| String::from("0")),
+                Some(current) => append_or_self(out, format!("RECURSION_LIMIT*({})",
+                    handle.recursion.groups.get(current).expect("Was added").iter()
+                        .map(|named| generate_named_xdr_size(handle, named, Some(current)))
                         .reduce(|prev, next| format!("STATIC_MAX(({prev}),({next}))"))
-                        .expect("At least one field in union")
-                ))
+                        .expect("At least one type in group")
+                )),
+                None => append_or_self(out, generate_named_xdr_size(handle, named, group)),
             },
         },
         _ => append_or_self(out, format!("sizeof({})", asc::typename(&asc::fulltype(tp)))),
     }
 }
 
+/// Size of struct or union itself, recursive types of the `group` are not
+/// counted
+fn generate_named_xdr_size(handle: &handle::Handle, named: &rpc::NamedType, group: Option<usize>) -> String {
+    match named {
+        rpc::NamedType::Struct(name) => handle.module.types.structs.get(name)
+            .expect("Was added").fields.values()
+            .fold(None, |out, tp| {
+                Some(append_or_self(out, generate_xdr_size_inner(handle, tp, None, group)))
+            }).expect("At least one field in struct"),
+        rpc::NamedType::Union(name) => {
+            let un = handle.module.types.unions.get(name).expect("Was added");
+
+            format!("{}+{}",
+                generate_switch_xdr_size(handle, &un.switch_type),
+                un.declarations()
+                    .map(|(_, current)| generate_xdr_size_inner(handle, current, None, group))
+                    .reduce(|prev, next| format!("STATIC_MAX(({prev}),({next}))"))
+                    .unwrap_or_else(|| String::from("0"))
+            )
+        },
+        _ => panic!("Unexpected named type: {named:?}"),
+    }
+}
+
 pub fn generate_xdr_size(handle: &handle::Handle, tp: &rpc::Type) -> String {
-    generate_xdr_size_inner(handle, tp, None)
+    generate_xdr_size_inner(handle, tp, None, None)
 }
 
 fn generate_switch_xdr_size(handle: &handle::Handle, tp: &rpc::SwitchingType) -> String {
@@ -106,6 +130,131 @@ fn generate_switch_xdr_size(handle: &handle::Handle, tp: &rpc::SwitchingType) ->
     }
 }
 
+/// `case` labels of every union arm followed by its statement, arms sharing
+/// labels fall through to the statement
+fn generate_union_arms(
+    file: &mut dyn File,
+    un: &rpc::Union,
+    soffset: &str,
+    mut statement: impl FnMut(&mut dyn File, &String, &rpc::Type),
+) {
+    un.arms.iter()
+        .map(|arm| (arm, false))
+        .chain(un.default.iter().map(|arm| (arm, true)))
+        .for_each(|(arm, default)| {
+            arm.cases.iter().for_each(|v| format!("{soffset}    case ({}):", asc::value(v)).print(file));
+            if default {
+                format!("{soffset}    default:").print(file);
+            }
+            if let Some((field, tp)) = &arm.declaration {
+                statement(file, field, tp);
+            }
+            format!("{soffset}        break;").print(file);
+        });
+}
+
+fn recursive_function(action: &str, named: &rpc::NamedType) -> String {
+    match named {
+        rpc::NamedType::Struct(name) | rpc::NamedType::Union(name) =>
+            format!("xdr_{action}_struct_{name}"),
+        _ => panic!("Unexpected named type: {named:?}"),
+    }
+}
+
+/// Call of recursive type encode or decode function, `limit` is the pointer
+/// to amount of elements left, when it is not known the new one is started
+fn generate_recursive_call(
+    file: &mut dyn File,
+    function: &str,
+    argument: &str,
+    rc: &str,
+    soffset: &str,
+    limit: Option<&str>,
+) {
+    IteratorPrinter::from([
+        format!("{soffset}{{"),
+        format!("{soffset}    int _rc = 0;"),
+    ]).chain(limit.is_none().then(||
+        format!("{soffset}    u32 limit = RECURSION_LIMIT;")
+    )).chain(IteratorPrinter::from([
+        format!("{soffset}    if (0 == {rc}"),
+        format!("{soffset}        && 0 > (_rc = {function}(xdr, {argument}, {}))) {{",
+            limit.unwrap_or("&limit")
+        ),
+        format!("{soffset}        {rc} = _rc;"),
+        format!("{soffset}    }}"),
+        format!("{soffset}}}"),
+    ])).print(file);
+}
+
+fn recursive_prototypes(named: &rpc::NamedType) -> [String; 3] {
+    let ctype = asc::typename(&asc::fulltype(&rpc::Type::Named(named.clone())));
+
+    [
+        format!("static inline int {}(struct xdr_stream *xdr, {ctype} *value, u32 *limit)",
+            recursive_function("decode", named)
+        ),
+        format!("static inline int {}(struct xdr_stream *xdr, const {ctype} *value, u32 *limit)",
+            recursive_function("encode", named)
+        ),
+        format!("static inline void {}({ctype} *value)",
+            recursive_function("release", named)
+        ),
+    ]
+}
+
+/// Decode, encode and release functions of recursive types, statements call
+/// them instead of expanding the type in place. Every decoded or encoded
+/// element takes one from `limit`
+pub fn generate_recursive_functions(handle: &handle::Handle, file: &mut dyn File) {
+    let types = handle.recursion.groups.iter().flatten();
+
+    IteratorPrinter::from(types.clone().map(|named| IteratorPrinter::from(
+        recursive_prototypes(named).map(|prototype| prototype + ";")
+    ))).chain("").chain(IteratorPrinter::from(types.map(|named| {
+        let [decode, encode, release] = recursive_prototypes(named);
+
+        IteratorPrinter::from([
+            format!("{decode} {{"),
+            format!("    int rc = 0;"),
+            format!(""),
+            format!("    if (0 == *limit) {{"),
+            format!("        return -EMSGSIZE;"),
+            format!("    }}"),
+            format!("    (*limit)--;"),
+            format!(""),
+        ]).switch(|file|
+            generate_named_decode_statement(handle, file, named, "*value", Some("rc"), Some(4), Some("limit"))
+        ).chain(IteratorPrinter::from([
+            format!(""),
+            format!("    return rc;"),
+            format!("}}"),
+            format!(""),
+            format!("{encode} {{"),
+            format!("    int rc = 0;"),
+            format!(""),
+            format!("    if (0 == *limit) {{"),
+            format!("        return -EMSGSIZE;"),
+            format!("    }}"),
+            format!("    (*limit)--;"),
+            format!(""),
+        ])).switch(|file|
+            generate_named_encode_statement(handle, file, named, "*value", Some("rc"), Some(4), Some("limit"))
+        ).chain(IteratorPrinter::from([
+            format!(""),
+            format!("    return rc;"),
+            format!("}}"),
+            format!(""),
+            format!("{release} {{"),
+        ])).switch(|file|
+            generate_named_release_statement(handle, file, named, "*value", Some(4))
+        ).chain(IteratorPrinter::from([
+            format!("}}"),
+            format!(""),
+        ]))
+    }))).print(file);
+}
+
 pub fn generate_release_statement(
     handle: &handle::Handle,
     file: &mut dyn File,
@@ -154,42 +303,13 @@ pub fn generate_release_statement(
             format!("{soffset}}}").print(file);
         },
         rpc::Type::Named(named) => match named {
-            rpc::NamedType::Struct(name) => {
-                let st = handle.module.types.structs.get(name).expect("Was added");
-                format!("{soffset}{{ // struct {name}").print(file);
-                st.iter().for_each(|(field, tp)| generate_release_statement(
-                    handle, file, tp,
-                    &format!("({access}).{field}"),
-                    Some(offset + 4),
-                ));
-                format!("{soffset}}}").print(file);
-            },
-            rpc::NamedType::Union(name) => {
-                let un = handle.module.types.unions.get(name).expect("Was added");
-                format!("{soffset}{{ // union {name}").print(file);
-                format!("{soffset}    switch (({access}).{}) {{", un.value).print(file);
-                un.arms.iter().for_each(|(v, (field, tp))| {
-                    format!("{soffset}    case ({}):", asc::value(v)).print(file);
-                    generate_release_statement(handle, file, tp,
-                        &format!("({access}).{name}_u.{field}"),
-                        Some(offset + 8),
-                    );
-                    format!("{soffset}        break;").print(file);
-                });
-                if let Some((field, tp)) = &un.default {
-                    format!("{soffset}    default:").print(file);
-                    generate_release_statement(handle, file, tp,
-                        &format!("({access}).{name}_u.{field}"),
-                        Some(offset + 8),
-                    );
-                    format!("{soffset}        break;").print(file);
-                }
-                format!("{soffset}    }}").print(file);
-                format!("{soffset}}}").print(file);
+            rpc::NamedType::Struct(_) | rpc::NamedType::Union(_) => match handle.recursion_group(named) {
+                Some(_) => format!("{soffset}{}(&({access}));", recursive_function("release", named)).print(file),
+                None => generate_named_release_statement(handle, file, named, access, Some(offset)),
             },
             rpc::NamedType::Typedef(name) => {
                 generate_release_statement(handle, file,
-                    handle.module.types.typedefs.get(name).expect("Was added"),
+                    &handle.module.types.typedefs.get(name).expect("Was added").tp,
                     access, Some(offset)
                 );
             },
@@ -199,6 +319,42 @@ pub fn generate_release_statement(
     }
 }
 
+fn generate_named_release_statement(
+    handle: &handle::Handle,
+    file: &mut dyn File,
+    named: &rpc::NamedType,
+    access: &str,
+    offset: Option<usize>,
+) {
+    let offset = offset.unwrap_or(0);
+    let soffset = (0..offset).map(|_| ' ').collect::<String>();
+
+    match named {
+        rpc::NamedType::Struct(name) => {
+            let st = handle.module.types.structs.get(name).expect("Was added");
+            format!("{soffset}{{ // struct {name}").print(file);
+            st.fields.iter().for_each(|(field, tp)| generate_release_statement(
+                handle, file, tp,
+                &format!("({access}).{field}"),
+                Some(offset + 4),
+            ));
+            format!("{soffset}}}").print(file);
+        },
+        rpc::NamedType::Union(name) => {
+            let un = handle.module.types.unions.get(name).expect("Was added");
+            format!("{soffset}{{ // union {name}").print(file);
+            format!("{soffset}    switch (({access}).{}) {{", un.value).print(file);
+            generate_union_arms(file, un, &soffset, |file, field, tp| generate_release_statement(handle, file, tp,
+                &format!("({access}).{name}_u.{field}"),
+                Some(offset + 8),
+            ));
+            format!("{soffset}    }}").print(file);
+            format!("{soffset}}}").print(file);
+        },
+        _ => panic!("Unexpected named type: {named:?}"),
+    }
+}
+
 fn generate_switch_decode_statement(
     handle: &handle::Handle,
     file: &mut dyn File,
@@ -221,13 +377,14 @@ fn generate_switch_decode_statement(
     }
 }
 
-pub fn generate_decode_statement(
+fn generate_decode_statement_inner(
     handle: &handle::Handle,
     file: &mut dyn File,
     tp: &rpc::Type,
     access: &str,
     rc: Option<&str>,
     offset: Option<usize>,
+    limit: Option<&str>,
 ) {
     let rc = rc.unwrap_or("rc");
     let offset = offset.unwrap_or(0);
@@ -303,8 +460,8 @@ pub fn generate_decode_statement(
                 format!("{soffset}            {rc} = -ENOMEM;"),
                 format!("{soffset}        }} else {{"),
             ]).print(file);
-            generate_decode_statement(handle, file, tp,
-                &format!("*({access})"), Some(rc), Some(offset + 12)
+            generate_decode_statement_inner(handle, file, tp,
+                &format!("*({access})"), Some(rc), Some(offset + 12), limit,
             );
             IteratorPrinter::from([
                 format!("{soffset}        }}"),
@@ -326,8 +483,8 @@ pub fn generate_decode_statement(
             ]).print(file),
             _ => {
                 format!("{soffset}for (size_t i = 0; 0 == {rc} && {} > i; i++) {{", asc::value(sz)).print(file);
-                generate_decode_statement(handle, file, tp,
-                    &format!("({access})[i]"), Some(rc), Some(offset + 4)
+                generate_decode_statement_inner(handle, file, tp,
+                    &format!("({access})[i]"), Some(rc), Some(offset + 4), limit,
                 );
                 format!("{soffset}}}").print(file);
             },
@@ -379,8 +536,8 @@ pub fn generate_decode_statement(
                         ),
                         format!("{soffset}            for (size_t i = 0; 0 == {rc} && ({access}).size > i; i++) {{"),
                     ]).print(file);
-                    generate_decode_statement(handle, file, tp,
-                        &format!("base[i]"), Some(rc), Some(offset + 16)
+                    generate_decode_statement_inner(handle, file, tp,
+                        &format!("base[i]"), Some(rc), Some(offset + 16), limit,
                     );
                     format!("{soffset}            }}").print(file)
                 }
@@ -396,9 +553,9 @@ pub fn generate_decode_statement(
         },
         rpc::Type::Named(named) => match named {
             rpc::NamedType::Typedef(name) =>
-                generate_decode_statement(handle, file,
-                    handle.module.types.typedefs.get(name).expect("Was added"),
-                    access, Some(rc), Some(offset)
+                generate_decode_statement_inner(handle, file,
+                    &handle.module.types.typedefs.get(name).expect("Was added").tp,
+                    access, Some(rc), Some(offset), limit,
                 ),
             rpc::NamedType::Enum(_) => IteratorPrinter::from([
                 format!("{soffset}{{"),
@@ -413,49 +570,73 @@ pub fn generate_decode_statement(
                 format!("{soffset}    }}"),
                 format!("{soffset}}}"),
             ]).print(file),
-            rpc::NamedType::Struct(name) => {
-                let st = handle.module.types.structs.get(name).expect("Was added");
-                format!("{soffset}{{ // struct {name}").print(file);
-                st.iter().for_each(|(field, tp)| generate_decode_statement(
-                    handle, file, tp,
-                    &format!("({access}).{field}"),
-                    Some(rc),
-                    Some(offset + 4),
-                ));
-                format!("{soffset}}}").print(file);
-            }
-            rpc::NamedType::Union(name) => {
-                let un = handle.module.types.unions.get(name).expect("Was added");
-                format!("{soffset}{{ // union {name}").print(file);
-                generate_switch_decode_statement(handle, file, &un.switch_type,
-                    &format!("({access}).{}", un.value),
-                    Some(rc), Some(offset + 4),
-                );
-                format!("{soffset}    switch (({access}).{}) {{", un.value).print(file);
-                un.arms.iter().for_each(|(v, (field, tp))| {
-                    format!("{soffset}    case ({}):", asc::value(v)).print(file);
-                    generate_decode_statement(handle, file, tp,
-                        &format!("({access}).{name}_u.{field}"),
-                        Some(rc), Some(offset + 8),
-                    );
-                    format!("{soffset}        break;").print(file);
-                });
-                if let Some((field, tp)) = &un.default {
-                    format!("{soffset}    default:").print(file);
-                    generate_decode_statement(handle, file, tp,
-                        &format!("({access}).{name}_u.{field}"),
-                        Some(rc), Some(offset + 8),
-                    );
-                    format!("{soffset}        break;").print(file);
-                }
-                format!("{soffset}    }}").print(file);
-                format!("{soffset}}}").print(file);
+            rpc::NamedType::Struct(_) | rpc::NamedType::Union(_) => match handle.recursion_group(named) {
+                Some(_) => generate_recursive_call(file,
+                    &recursive_function("decode", named), &format!("&({access})"), rc, &soffset, limit,
+                ),
+                None => generate_named_decode_statement(handle, file, named, access, Some(rc), Some(offset), limit),
             },
         },
         rpc::Type::String | rpc::Type::Opaque => panic!("Unexpected unit type: {tp:?}"),
     }
 }
 
+fn generate_named_decode_statement(
+    handle: &handle::Handle,
+    file: &mut dyn File,
+    named: &rpc::NamedType,
+    access: &str,
+    rc: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<&str>,
+) {
+    let rc = rc.unwrap_or("rc");
+    let offset = offset.unwrap_or(0);
+    let soffset = (0..offset).map(|_| ' ').collect::<String>();
+
+    match named {
+        rpc::NamedType::Struct(name) => {
+            let st = handle.module.types.structs.get(name).expect("Was added");
+            format!("{soffset}{{ // struct {name}").print(file);
+            st.fields.iter().for_each(|(field, tp)| generate_decode_statement_inner(
+                handle, file, tp,
+                &format!("({access}).{field}"),
+                Some(rc),
+                Some(offset + 4),
+                limit,
+            ));
+            format!("{soffset}}}").print(file);
+        }
+        rpc::NamedType::Union(name) => {
+            let un = handle.module.types.unions.get(name).expect("Was added");
+            format!("{soffset}{{ // union {name}").print(file);
+            generate_switch_decode_statement(handle, file, &un.switch_type,
+                &format!("({access}).{}", un.value),
+                Some(rc), Some(offset + 4),
+            );
+            format!("{soffset}    switch (({access}).{}) {{", un.value).print(file);
+            generate_union_arms(file, un, &soffset, |file, field, tp| generate_decode_statement_inner(handle, file, tp,
+                &format!("({access}).{name}_u.{field}"),
+                Some(rc), Some(offset + 8), limit,
+            ));
+            format!("{soffset}    }}").print(file);
+            format!("{soffset}}}").print(file);
+        },
+        _ => panic!("Unexpected named type: {named:?}"),
+    }
+}
+
+pub fn generate_decode_statement(
+    handle: &handle::Handle,
+    file: &mut dyn File,
+    tp: &rpc::Type,
+    access: &str,
+    rc: Option<&str>,
+    offset: Option<usize>,
+) {
+    generate_decode_statement_inner(handle, file, tp, access, rc, offset, None)
+}
+
 fn generate_switch_encode_statement(
     handle: &handle::Handle,
     file: &mut dyn File,
@@ -478,13 +659,14 @@ fn generate_switch_encode_statement(
     }
 }
 
-pub fn generate_encode_statement(
+fn generate_encode_statement_inner(
     handle: &handle::Handle,
     file: &mut dyn File,
     tp: &rpc::Type,
     access: &str,
     rc: Option<&str>,
     offset: Option<usize>,
+    limit: Option<&str>,
 ) {
     let rc = rc.unwrap_or("rc");
     let offset = offset.unwrap_or(0);
@@ -553,8 +735,8 @@ pub fn generate_encode_statement(
                 format!("{soffset}    }}"),
                 format!("{soffset}    if (0 == {rc} && 1 == size) {{"),
             ]).print(file);
-            generate_encode_statement(handle, file, tp,
-                &format!("*({access})"), Some(rc), Some(offset + 8)
+            generate_encode_statement_inner(handle, file, tp,
+                &format!("*({access})"), Some(rc), Some(offset + 8), limit,
             );
             IteratorPrinter::from([
                 format!("{soffset}    }}"),
@@ -578,8 +760,8 @@ pub fn generate_encode_statement(
                 ]).print(file),
                 _ => {
                     format!("{soffset}    for (size_t i = 0; 0 == {rc} && {} > i; i++) {{", asc::value(sz)).print(file);
-                    generate_encode_statement(handle, file, tp,
-                        &format!("({access})[i]"), Some(rc), Some(offset + 8)
+                    generate_encode_statement_inner(handle, file, tp,
+                        &format!("({access})[i]"), Some(rc), Some(offset + 8), limit,
                     );
                     format!("{soffset}    }}").print(file);
                 },
@@ -627,8 +809,8 @@ pub fn generate_encode_statement(
                         ),
                         format!("{soffset}        for (size_t i = 0; 0 == {rc} && ({access}).size > i; i++) {{"),
                     ]).print(file);
-                    generate_encode_statement(handle, file, tp,
-                        &format!("base[i]"), Some(rc), Some(offset + 12)
+                    generate_encode_statement_inner(handle, file, tp,
+                        &format!("base[i]"), Some(rc), Some(offset + 12), limit,
                     );
                     format!("{soffset}        }}").print(file)
                 }
@@ -641,9 +823,9 @@ pub fn generate_encode_statement(
         },
         rpc::Type::Named(named) => match named {
             rpc::NamedType::Typedef(name) =>
-                generate_encode_statement(handle, file,
-                    handle.module.types.typedefs.get(name).expect("Was added"),
-                    access, Some(rc), Some(offset)
+                generate_encode_statement_inner(handle, file,
+                    &handle.module.types.typedefs.get(name).expect("Was added").tp,
+                    access, Some(rc), Some(offset), limit,
                 ),
             rpc::NamedType::Enum(_) => IteratorPrinter::from([
                 format!("{soffset}{{"),
@@ -655,50 +837,74 @@ pub fn generate_encode_statement(
                 format!("{soffset}    }}"),
                 format!("{soffset}}}"),
             ]).print(file),
-            rpc::NamedType::Struct(name) => {
-                let st = handle.module.types.structs.get(name).expect("Was added");
-                format!("{soffset}{{ // struct {name}").print(file);
-                st.iter().for_each(|(field, tp)| generate_encode_statement(
-                    handle, file, tp,
-                    &format!("({access}).{field}"),
-                    Some(rc),
-                    Some(offset + 4),
-                ));
-                format!("{soffset}}}").print(file);
-            }
-            rpc::NamedType::Union(name) => {
-                let un = handle.module.types.unions.get(name).expect("Was added");
-                format!("{soffset}{{ // union {name}").print(file);
-                generate_switch_encode_statement(handle, file, &un.switch_type,
-                    &format!("({access}).{}", un.value),
-                    Some(rc), Some(offset + 4),
-                );
-                format!("{soffset}    switch (({access}).{}) {{", un.value).print(file);
-                un.arms.iter().for_each(|(v, (field, tp))| {
-                    format!("{soffset}    case ({}):", asc::value(v)).print(file);
-                    generate_encode_statement(handle, file, tp,
-                        &format!("({access}).{name}_u.{field}"),
-                        Some(rc), Some(offset + 8),
-                    );
-                    format!("{soffset}        break;").print(file);
-                });
-                if let Some((field, tp)) = &un.default {
-                    format!("{soffset}    default:").print(file);
-                    generate_encode_statement(handle, file, tp,
-                        &format!("({access}).{name}_u.{field}"),
-                        Some(rc), Some(offset + 8),
-                    );
-                    format!("{soffset}        break;").print(file);
-                }
-                format!("{soffset}    }}").print(file);
-                format!("{soffset}}}").print(file);
+            rpc::NamedType::Struct(_) | rpc::NamedType::Union(_) => match handle.recursion_group(named) {
+                Some(_) => generate_recursive_call(file,
+                    &recursive_function("encode", named), &format!("&({access})"), rc, &soffset, limit,
+                ),
+                None => generate_named_encode_statement(handle, file, named, access, Some(rc), Some(offset), limit),
             },
         },
         rpc::Type::String | rpc::Type::Opaque => panic!("Unexpected unit type: {tp:?}"),
     }
 }
 
-pub fn generate_argument_wrap_struct(_: &handle::Handle, proc: &rpc::Procedure) -> (String, rpc::Struct) {
+fn generate_named_encode_statement(
+    handle: &handle::Handle,
+    file: &mut dyn File,
+    named: &rpc::NamedType,
+    access: &str,
+    rc: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<&str>,
+) {
+    let rc = rc.unwrap_or("rc");
+    let offset = offset.unwrap_or(0);
+    let soffset = (0..offset).map(|_| ' ').collect::<String>();
+
+    match named {
+        rpc::NamedType::Struct(name) => {
+            let st = handle.module.types.structs.get(name).expect("Was added");
+            format!("{soffset}{{ // struct {name}").print(file);
+            st.fields.iter().for_each(|(field, tp)| generate_encode_statement_inner(
+                handle, file, tp,
+                &format!("({access}).{field}"),
+                Some(rc),
+                Some(offset + 4),
+                limit,
+            ));
+            format!("{soffset}}}").print(file);
+        }
+        rpc::NamedType::Union(name) => {
+            let un = handle.module.types.unions.get(name).expect("Was added");
+            format!("{soffset}{{ // union {name}").print(file);
+            generate_switch_encode_statement(handle, file, &un.switch_type,
+                &format!("({access}).{}", un.value),
+                Some(rc), Some(offset + 4),
+            );
+            format!("{soffset}    switch (({access}).{}) {{", un.value).print(file);
+            generate_union_arms(file, un, &soffset, |file, field, tp| generate_encode_statement_inner(handle, file, tp,
+                &format!("({access}).{name}_u.{field}"),
+                Some(rc), Some(offset + 8), limit,
+            ));
+            format!("{soffset}    }}").print(file);
+            format!("{soffset}}}").print(file);
+        },
+        _ => panic!("Unexpected named type: {named:?}"),
+    }
+}
+
+pub fn generate_encode_statement(
+    handle: &handle::Handle,
+    file: &mut dyn File,
+    tp: &rpc::Type,
+    access: &str,
+    rc: Option<&str>,
+    offset: Option<usize>,
+) {
+    generate_encode_statement_inner(handle, file, tp, access, rc, offset, None)
+}
+
+pub fn generate_argument_wrap_struct(_: &handle::Handle, proc: &rpc::Procedure) -> (String, rpc::Fields) {
     (
         format!("{}_argument_wrap", proc.name),
         proc.arguments.iter()
@@ -734,13 +940,15 @@ pub fn misc_types(file: &mut dyn File) {
 }
 
 pub struct Constants {
-    vla_limit: usize
+    vla_limit: usize,
+    recursion_limit: usize,
 }
 
 impl Constants {
     pub fn new(cfg: &Option<config::Config<impl AsRef<std::path::Path>>>) -> Self {
         Self {
             vla_limit: config::vla_limit(cfg),
+            recursion_limit: config::recursion_limit(cfg),
         }
     }
 }
@@ -748,6 +956,7 @@ impl Constants {
 pub fn misc_constants(file: &mut dyn File, cfg: Constants) {
     IteratorPrinter::from([
         format!("#define VLA_LIMIT {}", cfg.vla_limit),
+        format!("#define RECURSION_LIMIT {}", cfg.recursion_limit),
     ]).print(file);
 }
 
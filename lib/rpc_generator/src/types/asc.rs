@@ -1,8 +1,13 @@
 
 pub fn value(v: &rpc::Value) -> String {
     match v {
-        rpc::Value::Number(num) => num.to_string(),
+        // C has no negative literals, so the smallest one doesn't fit
+        rpc::Value::Number(num) if i64::MIN as i128 == *num => format!("({}LL - 1)", num + 1),
+        rpc::Value::Number(num) if i64::MAX as i128 >= *num => num.to_string(),
+        rpc::Value::Number(num) => format!("{num}ULL"),
         rpc::Value::Identifier(id) => id.clone(),
+        rpc::Value::Unary(op, v) => format!("{op}({})", value(v)),
+        rpc::Value::Binary(op, left, right) => format!("({} {op} {})", value(left), value(right)),
     }
 }
 
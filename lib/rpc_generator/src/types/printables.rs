@@ -16,7 +16,7 @@ impl Printable for (&String, &rpc::Type) {
     }
 }
 
-impl Printable for (&String, &rpc::Struct) {
+impl Printable for (&String, &rpc::Fields) {
     fn print(self: Self, file: &mut dyn File) {
         IteratorPrinter::from(std::iter::once(
             format!("struct {} {{", self.0)
@@ -34,9 +34,7 @@ impl Printable for (&String, &rpc::Union) {
             format!("struct {} {{", self.0),
             format!("    {};", asc::switching_declaraion(&self.1.value, &self.1.switch_type)),
             format!("    union {{"),
-        ].into_iter().chain(self.1.arms.values().map(|(field, tp)|
-            format!("        {};", asc::declaration(field, &asc::fulltype(tp)))
-        )).chain(self.1.default.as_ref().map(|(field, tp)|
+        ].into_iter().chain(self.1.declarations().map(|(field, tp)|
             format!("        {};", asc::declaration(field, &asc::fulltype(tp)))
         )).chain([
             format!("    }} {}_u;", self.0),
@@ -45,7 +43,7 @@ impl Printable for (&String, &rpc::Union) {
     }
 }
 
-impl Printable for (&String, &rpc::Enum) {
+impl Printable for (&String, &rpc::Items) {
     fn print(self: Self, file: &mut dyn File) {
         IteratorPrinter::from(std::iter::once(
             format!("enum {} {{", self.0)
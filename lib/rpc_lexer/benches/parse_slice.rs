@@ -0,0 +1,73 @@
+
+//! Compares streaming `parse` against in-memory `parse_slice` on a generated
+//! multi-megabyte specification. Run with `cargo bench`.
+//!
+//! `parse_slice` only skips `Read` decoding and borrows lexeme text, so the
+//! difference is small: matchers run the same way and build the same owned
+//! token values, which is checked before measuring.
+
+use lexer::Lexer;
+
+const SIZE: usize = 8 * 1024 * 1024;
+
+fn specification() -> String {
+    let mut out = String::with_capacity(SIZE + 1024);
+    let mut i = 0;
+
+    while out.len() < SIZE {
+        out += &format!(
+"/*
+ * Generated structure number {i}
+ */
+const SIZE_{i} = {i};
+
+struct item_{i} {{
+    unsigned int id;
+    string name<SIZE_{i}>;
+    opaque data[16];
+}};
+
+"
+        );
+        i += 1;
+    }
+
+    out
+}
+
+fn measure(name: &str, size: usize, mut f: impl FnMut() -> usize) {
+    f(); // Warm up
+
+    let rounds = 5;
+    let start = std::time::Instant::now();
+    let tokens = (0..rounds).map(|_| f()).last().unwrap_or(0);
+    let elapsed = start.elapsed() / rounds;
+
+    println!(
+        "{name:>12}: {tokens} tokens in {elapsed:?} ({:.1} MiB/s)",
+        size as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let spec = specification();
+    let mut lexer = rpc_lexer::lexer();
+    let mut other = lexer.clone();
+
+    assert!(
+        lexer.parse_str(&spec)
+            .map(|t| t.expect("Valid specification"))
+            .eq(other.parse_slice(&spec).map(|t| t.expect("Valid specification").into())),
+        "parse and parse_slice produced different tokens",
+    );
+
+    measure("parse", spec.len(), || lexer.parse_str(&spec)
+        .try_fold(0, |count, t| t.map(|_| count + 1))
+        .expect("Valid specification")
+    );
+    measure("parse_slice", spec.len(), || lexer.parse_slice(&spec)
+        .try_fold(0, |count, t| t.map(|_| count + 1))
+        .expect("Valid specification")
+    );
+}
+
@@ -5,11 +5,28 @@ mod matcher;
 use rpc::token;
 use lexer::{
     Lexer,
-    generic::Lexer as GenericLexer,
+    generic::{Anchor, Transition, definition::Definition},
 };
 
-pub fn lexer() -> impl Lexer<token::Token> {
-    let mut out = GenericLexer::new();
+/// Lexer context of a line after `%`
+const PASSTHROUGH: &str = "passthrough";
+
+/// Lexer definition, clone it to lex several inputs at once
+pub fn lexer() -> impl Lexer<token::Token> + Clone + Send + Sync {
+    definition()
+}
+
+/// Lexer reporting unknown input as error tokens and continuing after them
+pub fn recovering_lexer() -> impl Lexer<token::Token> + Clone + Send + Sync {
+    let mut out = definition();
+
+    out.with_recovery(true);
+
+    out
+}
+
+fn definition() -> Definition<token::Token> {
+    let mut out = Definition::new();
 
     out.with_skip(|| char::is_whitespace)
         .push_back(matcher::separator_matcher)
@@ -19,8 +36,25 @@ pub fn lexer() -> impl Lexer<token::Token> {
         .push_back(matcher::type_matcher)
         .push_back(matcher::operator_matcher)
         .push_back(matcher::identifier_matcher)
-        .push_back(matcher::comment_matcher);
+        .push_back(matcher::comment_matcher)
+        .push_back_anchored(matcher::passthrough_matcher, Anchor::LineStart)
+        .push_back(matcher::remainder_matcher)
+        .in_context(PASSTHROUGH)
+        .with_skip(|| |_: char| false)
+        .push_back(matcher::passthrough_line_matcher)
+        .with_transitions(|token| match token {
+            token::Token::PassthroughStart => Some(Transition::Push(PASSTHROUGH)),
+            token::Token::Passthrough(_) => Some(Transition::Pop),
+            _ => None,
+        })
+        .build()
+        .expect("Rules don't shadow each other");
 
     out
 }
 
+/// Tokens which don't take part in grammar, see [`Lexer::parse_lossless`]
+pub fn is_trivia(token: &token::Token) -> bool {
+    matches!(token, token::Token::Comment(_))
+}
+
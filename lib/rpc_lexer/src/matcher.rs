@@ -1,14 +1,14 @@
 
-use lexer::{
-    group,
-    generic::{
-        Matcher,
-        State,
-        Char,
-        matcher::{
-            CharSequenceMatcher,
-            IntegerMatcher,
-        },
+
+use lexer::generic::{
+    Matcher,
+    State,
+    Char,
+    matcher::{
+        CharSequenceMatcher,
+        IntegerMatcher,
+        KeywordSetMatcher,
+        RestOfLineMatcher,
     },
 };
 
@@ -27,6 +27,16 @@ pub fn comment_matcher() -> impl Matcher<token::Token> {
     CommentMatcher::new()
 }
 
+/// `%` at the start of a line, see [`passthrough_line_matcher`]
+pub fn passthrough_matcher() -> impl Matcher<token::Token> {
+    CharSequenceMatcher::new("%", || token::Token::PassthroughStart, |_| false)
+}
+
+/// Everything up to the end of line after `%`
+pub fn passthrough_line_matcher() -> impl Matcher<token::Token> {
+    RestOfLineMatcher::new("", |line| token::Token::Passthrough(line.to_string()))
+}
+
 pub fn identifier_matcher() -> impl Matcher<token::Token> {
     IdentifierMatcher::new()
 }
@@ -36,103 +46,138 @@ pub fn literal_matcher() -> impl Matcher<token::Token> {
 }
 
 pub fn bracket_matcher() -> impl Matcher<token::Token> {
-    group! {
-        sbracket_matcher(token::Bracket::Left),
-        sbracket_matcher(token::Bracket::Right),
-        sbracket_matcher(token::Bracket::LeftCurly),
-        sbracket_matcher(token::Bracket::RightCurly),
-        sbracket_matcher(token::Bracket::LeftSquare),
-        sbracket_matcher(token::Bracket::RightSquare),
-        sbracket_matcher(token::Bracket::LeftTriangle),
-        sbracket_matcher(token::Bracket::RightTriangle),
-    }
-}
+    let mut out = KeywordSetMatcher::new();
+
+    [
+        token::Bracket::Left,
+        token::Bracket::Right,
+        token::Bracket::LeftCurly,
+        token::Bracket::RightCurly,
+        token::Bracket::LeftSquare,
+        token::Bracket::RightSquare,
+        token::Bracket::LeftTriangle,
+        token::Bracket::RightTriangle,
+    ].into_iter().for_each(|value| {
+        let allowed: fn(Char) -> bool = match value {
+            // Start of a shift operator
+            token::Bracket::LeftTriangle => |c| match c {
+                Char::Char(c) => '<' == c,
+                Char::EOF => false,
+            },
+            token::Bracket::RightTriangle => |c| match c {
+                Char::Char(c) => '>' == c,
+                Char::EOF => false,
+            },
+            _ => |_| false,
+        };
+        out.add(&value.to_string(), token::Token::Bracket(value), allowed);
+    });
 
-fn sbracket_matcher(value: token::Bracket) -> impl Matcher<token::Token> {
-    CharSequenceMatcher::new(
-        &value.to_string(),
-        move || token::Token::Bracket(value),
-        |_| false,
-    )
+    out
 }
 
 pub fn type_matcher() -> impl Matcher<token::Token> {
-    group! {
-        stype_matcher(token::Type::Void),
-        stype_matcher(token::Type::Unsigned),
-        stype_matcher(token::Type::Integer),
-        stype_matcher(token::Type::Hyper),
-        stype_matcher(token::Type::Float),
-        stype_matcher(token::Type::Double),
-        stype_matcher(token::Type::Boolean),
-        stype_matcher(token::Type::Quadruple),
-        stype_matcher(token::Type::String),
-        stype_matcher(token::Type::Opaque),
-        stype_matcher(token::Type::Pointer),
-        stype_matcher(token::Type::Enum),
-        stype_matcher(token::Type::Struct),
-        stype_matcher(token::Type::Union),
-    }
-}
-
-fn stype_matcher(value: token::Type) -> impl Matcher<token::Token> {
-    CharSequenceMatcher::new(
-        &value.to_string(),
-        move || token::Token::Type(value),
-        match value {
+    let mut out = KeywordSetMatcher::new();
+
+    [
+        token::Type::Void,
+        token::Type::Unsigned,
+        token::Type::Integer,
+        token::Type::Hyper,
+        token::Type::Float,
+        token::Type::Double,
+        token::Type::Boolean,
+        token::Type::Quadruple,
+        token::Type::String,
+        token::Type::Opaque,
+        token::Type::Pointer,
+        token::Type::Enum,
+        token::Type::Struct,
+        token::Type::Union,
+    ].into_iter().for_each(|value| {
+        let allowed: fn(Char) -> bool = match value {
             token::Type::Pointer => |_| false,
             _ => allowed_chars,
-        }
-    )
+        };
+        out.add(&value.to_string(), token::Token::Type(value), allowed);
+    });
+
+    out
 }
 
 pub fn operator_matcher() -> impl Matcher<token::Token> {
-    soperator_matcher(token::Operator::Assign)
+    let mut out = KeywordSetMatcher::new();
+
+    [
+        token::Operator::Assign,
+        token::Operator::Add,
+        token::Operator::Subtract,
+        token::Operator::Divide,
+        token::Operator::ShiftLeft,
+        token::Operator::ShiftRight,
+        token::Operator::Or,
+        token::Operator::And,
+        token::Operator::Not,
+    ].into_iter().for_each(|value| {
+        let allowed: fn(Char) -> bool = match value {
+            // Negative number is a literal
+            token::Operator::Subtract => |c| match c {
+                Char::Char(c) => char::is_digit(c, 10),
+                Char::EOF => false,
+            },
+            // Start of a comment
+            token::Operator::Divide => |c| match c {
+                Char::Char(c) => '/' == c || '*' == c,
+                Char::EOF => false,
+            },
+            _ => |_| false,
+        };
+        out.add(&value.to_string(), token::Token::Operator(value), allowed);
+    });
+
+    out
 }
 
-fn soperator_matcher(value: token::Operator) -> impl Matcher<token::Token> {
+/// `%` at the start of a line is a passthrough, which goes first
+pub fn remainder_matcher() -> impl Matcher<token::Token> {
     CharSequenceMatcher::new(
-        &value.to_string(),
-        move || token::Token::Operator(value),
+        &token::Operator::Remainder.to_string(),
+        || token::Token::Operator(token::Operator::Remainder),
         |_| false,
     )
 }
 
 pub fn separator_matcher() -> impl Matcher<token::Token> {
-    group! {
-        sseparator_matcher(token::Separator::Semicolon),
-        sseparator_matcher(token::Separator::Colon),
-        sseparator_matcher(token::Separator::Comma),
-    }
-}
+    let mut out = KeywordSetMatcher::new();
 
-fn sseparator_matcher(value: token::Separator) -> impl Matcher<token::Token> {
-    CharSequenceMatcher::new(
-        &value.to_string(),
-        move || token::Token::Separator(value),
-        |_| false,
-    )
-}
+    [
+        token::Separator::Semicolon,
+        token::Separator::Colon,
+        token::Separator::Comma,
+    ].into_iter().for_each(|value| {
+        out.add(&value.to_string(), token::Token::Separator(value), |_| false);
+    });
 
-pub fn keyword_matcher() -> impl Matcher<token::Token> {
-    group! {
-        skeyword_matcher(token::Keyword::Const),
-        skeyword_matcher(token::Keyword::Case),
-        skeyword_matcher(token::Keyword::Switch),
-        skeyword_matcher(token::Keyword::Default),
-        skeyword_matcher(token::Keyword::Typedef),
-        skeyword_matcher(token::Keyword::Program),
-        skeyword_matcher(token::Keyword::Version),
-        skeyword_matcher(token::Keyword::Procedure),
-    }
+    out
 }
 
-fn skeyword_matcher(value: token::Keyword) -> impl Matcher<token::Token> {
-    CharSequenceMatcher::new(
-        &value.to_string(),
-        move || token::Token::Keyword(value),
-        allowed_chars
-    )
+pub fn keyword_matcher() -> impl Matcher<token::Token> {
+    let mut out = KeywordSetMatcher::new();
+
+    [
+        token::Keyword::Const,
+        token::Keyword::Case,
+        token::Keyword::Switch,
+        token::Keyword::Default,
+        token::Keyword::Typedef,
+        token::Keyword::Program,
+        token::Keyword::Version,
+        token::Keyword::Procedure,
+    ].into_iter().for_each(|value| {
+        out.add(&value.to_string(), token::Token::Keyword(value), allowed_chars);
+    });
+
+    out
 }
 
 struct IdentifierMatcher {
@@ -161,7 +206,7 @@ impl Matcher<token::Token> for IdentifierMatcher {
                     State::Rejected
                 } else {
                     State::Matched(
-                        token::Token::Identifier(self.current.clone())
+                        token::Token::Identifier(std::mem::take(&mut self.current))
                     )
                 }
             },
@@ -180,7 +225,7 @@ impl Matcher<token::Token> for IdentifierMatcher {
                     State::Matching
                 } else if "" != self.current {
                     State::Matched(
-                        token::Token::Identifier(self.current.clone())
+                        token::Token::Identifier(std::mem::take(&mut self.current))
                     )
                 } else {
                     State::Rejected
@@ -234,7 +279,7 @@ impl Matcher<token::Token> for CommentMatcher {
 
         let res = match (c, &self.ctype) {
             (_, CommentType::Finished) => State::Matched(
-                token::Token::Comment(self.content.clone())
+                token::Token::Comment(std::mem::take(&mut self.content))
             ),
             (
                 Char::EOF,
@@ -242,7 +287,7 @@ impl Matcher<token::Token> for CommentMatcher {
                 | CommentType::Block | CommentType::PendingBlockEnd
             ) => State::Rejected,
             (Char::EOF, CommentType::Line) => State::Matched(
-                token::Token::Comment(self.content.clone())
+                token::Token::Comment(std::mem::take(&mut self.content))
             ),
             (Char::Char('\r' | '\n'), CommentType::Line) => {
                 self.ctype = CommentType::Finished;
@@ -35,63 +35,63 @@ fn ping() {
 
     assert_eq!(
         token::Token::Comment("\n * Simple ping program\n ".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Program),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PING_PROG".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Version),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PING_VERS_PINGBACK".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Void),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PINGPROC_NULL".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Left),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Void),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Right),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(0)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Comment(String::from(
@@ -101,173 +101,188 @@ fn ping() {
          * operation times-out
          "
         )),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Integer),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PINGPROC_PINGBACK".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Left),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Void),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Right),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Comment(String::from(
             " void - above is an argument to the call "
         )),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(2)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Comment(String::from(
             "\n * Original version\n "
         )),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Version),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PING_VERS_ORIG".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Void),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PINGPROC_NULL".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Left),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Void),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Right),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(0)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(200000)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("PING_VERS".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(2)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Comment(String::from(
             " latest version "
         )),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert!(tokens.next().is_none());
 }
 
+#[test]
+fn ping_spans() {
+    let mut l = lexer();
+    let mut tokens = l.parse_str(PING_PROGR).map(|t| t.unwrap().span);
+
+    let comment = tokens.next().unwrap();
+    assert_eq!((0, 1, 1, 29), (comment.offset, comment.line, comment.column, comment.length));
+
+    let program = tokens.next().unwrap();
+    assert_eq!((30, 4, 1, 7), (program.offset, program.line, program.column, program.length));
+
+    let name = tokens.next().unwrap();
+    assert_eq!((38, 4, 9, 9), (name.offset, name.line, name.column, name.length));
+}
+
 const BAKERY_PROGR: &str =
 "const REGISTER = 0;
 const ACCESS   = 1;
@@ -310,467 +325,467 @@ fn bakery() {
 
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("REGISTER".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(0)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("ACCESS".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("GET".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(2)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("STATUS".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(3)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("OP_MAX".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(4)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("STATUS_FREE".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(0)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("STATUS_REGISTERED".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("STATUS_ACCESSING".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(2)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("STATUS_READY_FOR_CR".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(3)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("ERROR_WRONG_ID_RPC".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(-1)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("ERROR_INCOMPATIBLE_HANLE_RPC".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(-2)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("ERROR_WRONG_STATUS_RPC".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(-3)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("ERROR_REJECT_ACCESS_RPC".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(-4)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Const),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("ERROR_WRONG_OP_RPC".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(-5)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Struct),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("BAKERY".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Integer),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("op".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Integer),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("id".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Integer),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("num".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Integer),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("result".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Struct),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("BAKERY".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Pointer),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("not_supposed_2_be_here".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Program),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("BAKERY_PROG".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Version),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("BAKERY_VER".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Struct),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("BAKERY".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("BAKERY_PROC".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Left),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Type(token::Type::Struct),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Identifier("BAKERY".to_string()),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Right),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(0x20000001)),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap()
+        tokens.next().unwrap().unwrap().value
     );
     assert!(tokens.next().is_none());
 }
@@ -799,182 +814,338 @@ fn union_enum() {
 
     assert_eq!(
         token::Token::Type(token::Type::Enum),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("cases".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("NUMBERS".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Operator(token::Operator::Assign),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(1)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Comma),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("NAME".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Comma),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
 
     assert_eq!(
         token::Token::Type(token::Type::Union),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("test".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Switch),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Left),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Enum),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("cases".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("value".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::Right),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftCurly),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Case),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("NUMBERS".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Colon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Unsigned),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Integer),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("values".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftSquare),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Literal(token::Literal::Integer(10)),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightSquare),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Case),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("NAME".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Colon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::String),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Identifier("name".to_string()),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::LeftTriangle),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightTriangle),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Keyword(token::Keyword::Default),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Colon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Type(token::Type::Void),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Bracket(token::Bracket::RightCurly),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
     assert_eq!(
         token::Token::Separator(token::Separator::Semicolon),
-        tokens.next().unwrap().unwrap(),
+        tokens.next().unwrap().unwrap().value,
     );
 
     assert!(tokens.next().is_none());
 }
 
+#[test]
+fn ping_slice() {
+    let mut l = lexer();
+    let expected: Vec<_> = l.parse_str(PING_PROGR).map(|t| t.unwrap()).collect();
+    let lexemes: Vec<_> = l.parse_slice(PING_PROGR).map(|t| t.unwrap()).collect();
+
+    assert_eq!(expected.len(), lexemes.len());
+    expected.iter().zip(lexemes.iter()).for_each(|(token, lexeme)| {
+        assert_eq!(token.value, lexeme.value);
+        assert_eq!(token.span, lexeme.span);
+    });
+
+    assert_eq!("program", lexemes[1].text);
+    assert_eq!("PING_PROG", lexemes[2].text);
+    assert_eq!("200000", lexemes[lexemes.len() - 8].text);
+}
+
+#[test]
+fn ping_lossless() {
+    let mut l = lexer();
+    let items: Vec<_> = l.parse_lossless(PING_PROGR, is_trivia).map(|t| t.unwrap()).collect();
+
+    let mut rebuilt = String::new();
+    items.iter().for_each(|item| item.write_text(&mut rebuilt));
+    assert_eq!(PING_PROGR, rebuilt);
+
+    assert_eq!(
+        items.iter().filter_map(|item| item.token.as_ref()).count(),
+        l.parse_str(PING_PROGR).filter(|t| !is_trivia(&t.as_ref().unwrap().value)).count()
+    );
+
+    let program = &items[0];
+    assert_eq!(2, program.leading.len());
+    assert!(match &program.leading[0] {
+        lexer::Trivia::Token(comment) => is_trivia(&comment.value),
+        _ => false,
+    });
+    assert!(match &program.leading[1] {
+        lexer::Trivia::Skipped(skipped) => "\n" == skipped.text && (3, 4) == (skipped.span.line, skipped.span.column),
+        _ => false,
+    });
+    assert_eq!(Some("program"), program.token.as_ref().map(|t| t.text));
+
+    let end = items.last().unwrap();
+    assert!(end.token.is_none());
+    assert_eq!(" /* latest version */", end.leading.iter().map(|t| t.text()).collect::<String>());
+}
+
+#[test]
+fn ping_threads() {
+    let d = &definition();
+    let expected: Vec<_> = { d }.parse_str(PING_PROGR).map(|t| t.unwrap().value).collect();
+
+    std::thread::scope(|scope| {
+        (0..4).map(|_| {
+            scope.spawn(move || { d }.parse_str(PING_PROGR).map(|t| t.unwrap().value).collect::<Vec<_>>())
+        }).collect::<Vec<_>>().into_iter().for_each(|handle| {
+            assert_eq!(expected, handle.join().unwrap());
+        });
+    });
+}
+
+
+#[test]
+fn no_shadowed_rules() {
+    let shadowed = definition().shadowed();
+
+    assert!(shadowed.is_empty(), "{}", shadowed.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"));
+}
+
+#[test]
+fn passthrough() {
+    let tokens: Vec<_> = lexer().parse_str("%#include <linux/types.h>\r\nconst A = 1;\n%\n")
+        .map(|t| t.unwrap().value)
+        .collect();
+
+    assert_eq!(vec![
+        token::Token::PassthroughStart,
+        token::Token::Passthrough("#include <linux/types.h>".to_string()),
+        token::Token::Keyword(token::Keyword::Const),
+        token::Token::Identifier("A".to_string()),
+        token::Token::Operator(token::Operator::Assign),
+        token::Token::Literal(token::Literal::Integer(1)),
+        token::Token::Separator(token::Separator::Semicolon),
+        token::Token::PassthroughStart,
+        token::Token::Passthrough("".to_string()),
+    ], tokens);
+
+    let tokens: Vec<_> = lexer().parse_str("%  x % y
+  %
+%")
+        .map(|t| t.unwrap().value)
+        .collect();
+
+    assert_eq!(vec![
+        token::Token::PassthroughStart,
+        token::Token::Passthrough("  x % y".to_string()),
+        token::Token::PassthroughStart,
+        token::Token::Passthrough("".to_string()),
+        token::Token::PassthroughStart,
+    ], tokens);
+}
+
+
+#[test]
+fn expressions() {
+    use token::{Token, Operator, Bracket, Literal};
+
+    let tokens: Vec<_> = lexer().parse_str("A = (B+1) << 2 >> C % 8 / D-1 - E | ~F & *G; // x\n%pass\n")
+        .map(|t| t.unwrap().value)
+        .collect();
+
+    assert_eq!(vec![
+        Token::Identifier("A".to_string()),
+        Token::Operator(Operator::Assign),
+        Token::Bracket(Bracket::Left),
+        Token::Identifier("B".to_string()),
+        Token::Operator(Operator::Add),
+        Token::Literal(Literal::Integer(1)),
+        Token::Bracket(Bracket::Right),
+        Token::Operator(Operator::ShiftLeft),
+        Token::Literal(Literal::Integer(2)),
+        Token::Operator(Operator::ShiftRight),
+        Token::Identifier("C".to_string()),
+        Token::Operator(Operator::Remainder),
+        Token::Literal(Literal::Integer(8)),
+        Token::Operator(Operator::Divide),
+        Token::Identifier("D".to_string()),
+        Token::Literal(Literal::Integer(-1)),
+        Token::Operator(Operator::Subtract),
+        Token::Identifier("E".to_string()),
+        Token::Operator(Operator::Or),
+        Token::Operator(Operator::Not),
+        Token::Identifier("F".to_string()),
+        Token::Operator(Operator::And),
+        Token::Type(token::Type::Pointer),
+        Token::Identifier("G".to_string()),
+        Token::Separator(token::Separator::Semicolon),
+        Token::Comment(" x".to_string()),
+        Token::PassthroughStart,
+        Token::Passthrough("pass".to_string()),
+    ], tokens);
+}
+
+#[test]
+fn big_literals() {
+    let tokens: Vec<_> = lexer().parse_str("18446744073709551615 -9223372036854775808 0xffffffffffffffffffffffffffffffffff")
+        .map(|t| t.unwrap().value)
+        .collect();
+
+    assert_eq!(vec![
+        token::Token::Literal(token::Literal::Integer(u64::MAX as i128)),
+        token::Token::Literal(token::Literal::Integer(i64::MIN as i128)),
+        token::Token::Literal(token::Literal::Integer(i128::MAX)),
+    ], tokens);
+}
@@ -1,18 +1,31 @@
 
 #[cfg(test)]
 mod test;
+mod resolve;
 
 use rpc::{self, token};
 
+/// Parser error, details are boxed to keep `Result` small
 #[derive(Debug)]
-pub enum Error {
+pub struct Error(Box<ErrorDetails>);
+
+#[derive(Debug)]
+pub struct ErrorDetails {
+    pub kind: ErrorKind,
+    pub span: Option<token::Span>,
+    /// Suggestion on how to fix the error
+    pub hint: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
     UnknownDefinition(token::Token),
     UnexpectedToken(String, token::Token),
     ExpressionNotClosed(String, token::Token),
     UnexpectedEOF(String),
     UndefinedType(String),
     UndefinedValue(String),
-    NonPositiveArraySize(i64),
+    NonPositiveArraySize(i128),
     TypeRedefined(String),
     IdentifierRedefined(String),
     StructureFieldRedefined(String),
@@ -22,6 +35,11 @@ pub enum Error {
     ProgramNumberReassigned(rpc::Value),
     VersionNumberReassigned(rpc::Value),
     ProcedureNumberReassigned(rpc::Value),
+    ArithmeticOverflow(String),
+    DivisionByZero(String),
+    ValueOutOfRange(i128, String),
+    AmbiguousType(String),
+    RecursiveValue(String),
 }
 
 #[derive(Debug)]
@@ -30,114 +48,301 @@ pub enum UnionArm {
     Default,
 }
 
+impl std::ops::Deref for Error {
+    type Target = ErrorDetails;
+
+    fn deref(self: &Self) -> &ErrorDetails {
+        &self.0
+    }
+}
+
 impl Error {
+    fn new(kind: ErrorKind) -> Self {
+        Self(Box::new(ErrorDetails {
+            kind,
+            span: None,
+            hint: None,
+        }))
+    }
+
+    pub fn into_details(self: Self) -> ErrorDetails {
+        *self.0
+    }
+
+    fn at(mut self: Self, span: Option<&token::Span>) -> Self {
+        if self.0.span.is_none() {
+            self.0.span = span.cloned();
+        }
+
+        self
+    }
+
+    fn with_hint(mut self: Self, hint: Option<String>) -> Self {
+        self.0.hint = hint;
+        self
+    }
+
     fn unknown_definition<T>(t: token::Token) -> Result<T> {
-        Err(Self::UnknownDefinition(t))
+        Err(Self::new(ErrorKind::UnknownDefinition(t)))
     }
 
     fn unexpected_token<T>(msg: String, t: token::Token) -> Result<T> {
-        Err(Self::UnexpectedToken(msg, t))
+        Err(Self::new(ErrorKind::UnexpectedToken(msg, t)))
     }
 
     fn expression_not_closed<T>(msg: String, t: token::Token) -> Result<T> {
-        Err(Self::ExpressionNotClosed(msg, t))
+        Err(Self::new(ErrorKind::ExpressionNotClosed(msg, t)))
     }
 
     fn unexpected_eof<T>(msg: String) -> Result<T> {
-        Err(Self::UnexpectedEOF(msg))
+        Err(Self::new(ErrorKind::UnexpectedEOF(msg)))
     }
 
     fn undefined_type<T>(msg: String) -> Result<T> {
-        Err(Self::UndefinedType(msg))
+        Err(Self::new(ErrorKind::UndefinedType(msg)))
+    }
+
+    fn undefined_type_hint<T>(msg: String, hint: Option<String>) -> Result<T> {
+        Err(Self::new(ErrorKind::UndefinedType(msg)).with_hint(hint))
     }
 
     fn undefined_value<T>(msg: String) -> Result<T> {
-        Err(Self::UndefinedValue(msg))
+        Err(Self::new(ErrorKind::UndefinedValue(msg)))
     }
 
-    fn non_positive_array_size<T>(size: i64) -> Result<T> {
-        Err(Self::NonPositiveArraySize(size))
+    fn non_positive_array_size<T>(size: i128) -> Result<T> {
+        Err(Self::new(ErrorKind::NonPositiveArraySize(size)))
     }
 
     fn type_redefined<T>(msg: String) -> Result<T> {
-        Err(Self::TypeRedefined(msg))
+        Err(Self::new(ErrorKind::TypeRedefined(msg)))
     }
 
     fn identifier_redefined<T>(msg: String) -> Result<T> {
-        Err(Self::IdentifierRedefined(msg))
+        Err(Self::new(ErrorKind::IdentifierRedefined(msg)))
     }
 
     fn structure_field_redefined<T>(msg: String) -> Result<T> {
-        Err(Self::StructureFieldRedefined(msg))
+        Err(Self::new(ErrorKind::StructureFieldRedefined(msg)))
     }
 
     fn not_switching_type<T>(tp: rpc::Type) -> Result<T> {
-        Err(Self::NotSwitchingType(tp))
+        Err(Self::new(ErrorKind::NotSwitchingType(tp)))
     }
 
     fn union_arm_redefined<T>(v: rpc::Value) -> Result<T> {
-        Err(Self::UnionArmRedefined(UnionArm::Regular(v)))
+        Err(Self::new(ErrorKind::UnionArmRedefined(UnionArm::Regular(v))))
     }
 
     fn union_default_redefined<T>() -> Result<T> {
-        Err(Self::UnionArmRedefined(UnionArm::Default))
+        Err(Self::new(ErrorKind::UnionArmRedefined(UnionArm::Default)))
     }
 
     fn use_of_pending_type<T>(msg: String) -> Result<T> {
-        Err(Self::UseOfPendingType(msg))
+        Err(Self::new(ErrorKind::UseOfPendingType(msg)))
     }
 
     fn program_number_reassigned<T>(v: rpc::Value) -> Result<T> {
-        Err(Self::ProgramNumberReassigned(v))
+        Err(Self::new(ErrorKind::ProgramNumberReassigned(v)))
     }
 
     fn version_number_reassigned<T>(v: rpc::Value) -> Result<T> {
-        Err(Self::VersionNumberReassigned(v))
+        Err(Self::new(ErrorKind::VersionNumberReassigned(v)))
     }
 
     fn procedure_number_reassigned<T>(v: rpc::Value) -> Result<T> {
-        Err(Self::ProcedureNumberReassigned(v))
+        Err(Self::new(ErrorKind::ProcedureNumberReassigned(v)))
+    }
+
+    fn arithmetic_overflow<T>(msg: String) -> Result<T> {
+        Err(Self::new(ErrorKind::ArithmeticOverflow(msg)))
+    }
+
+    fn division_by_zero<T>(msg: String) -> Result<T> {
+        Err(Self::new(ErrorKind::DivisionByZero(msg)))
+    }
+
+    fn value_out_of_range<T>(v: i128, msg: String) -> Result<T> {
+        Err(Self::new(ErrorKind::ValueOutOfRange(v, msg)))
+    }
+
+    fn ambiguous_type<T>(msg: String, hint: Option<String>) -> Result<T> {
+        Err(Self::new(ErrorKind::AmbiguousType(msg)).with_hint(hint))
+    }
+
+    fn recursive_value<T>(msg: String) -> Result<T> {
+        Err(Self::new(ErrorKind::RecursiveValue(msg)))
+    }
+}
+
+impl ErrorKind {
+    /// Stable identifier of the error used in diagnostics
+    pub fn code(self: &Self) -> &'static str {
+        match self {
+            ErrorKind::UnknownDefinition(_) => "E0001",
+            ErrorKind::UnexpectedToken(_, _) => "E0002",
+            ErrorKind::ExpressionNotClosed(_, _) => "E0003",
+            ErrorKind::UnexpectedEOF(_) => "E0004",
+            ErrorKind::UndefinedType(_) => "E0005",
+            ErrorKind::UndefinedValue(_) => "E0006",
+            ErrorKind::NonPositiveArraySize(_) => "E0007",
+            ErrorKind::TypeRedefined(_) => "E0008",
+            ErrorKind::IdentifierRedefined(_) => "E0009",
+            ErrorKind::StructureFieldRedefined(_) => "E0010",
+            ErrorKind::NotSwitchingType(_) => "E0011",
+            ErrorKind::UnionArmRedefined(_) => "E0012",
+            ErrorKind::UseOfPendingType(_) => "E0013",
+            ErrorKind::ProgramNumberReassigned(_) => "E0014",
+            ErrorKind::VersionNumberReassigned(_) => "E0015",
+            ErrorKind::ProcedureNumberReassigned(_) => "E0016",
+            ErrorKind::ArithmeticOverflow(_) => "E0017",
+            ErrorKind::DivisionByZero(_) => "E0018",
+            ErrorKind::ValueOutOfRange(_, _) => "E0019",
+            ErrorKind::AmbiguousType(_) => "E0020",
+            ErrorKind::RecursiveValue(_) => "E0021",
+        }
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-struct PickIterator<I: Iterator<Item=token::Token>> {
-    picked: Option<token::Token>,
+struct PickIterator<I: Iterator<Item=token::SpannedToken>> {
+    /// Token pushed back or peeked with the doc comment preceding it
+    picked: Option<(token::SpannedToken, Option<String>)>,
     iter: I,
+    span: Option<token::Span>,
+    previous: Option<token::Span>,
+    /// Doc comment preceding the last token taken from iterator
+    doc: Option<String>,
+    depth: isize,
 }
 
-impl<I: Iterator<Item=token::Token>> PickIterator<I> {
+impl<I: Iterator<Item=token::SpannedToken>> PickIterator<I> {
     fn new(iter: I) -> Self {
         Self {
             picked: None,
             iter,
+            span: None,
+            previous: None,
+            doc: None,
+            depth: 0,
         }
     }
 
     fn push_back(self: &mut Self, t: token::Token) {
         match self.picked {
             Some(_) => panic!("Supposed to pick only one token"),
-            None => self.picked = Some(t),
+            None => {
+                self.depth -= bracket_depth(&t);
+                let span = std::mem::replace(&mut self.span, self.previous.take());
+                self.picked = Some((token::Spanned::new(t, span.unwrap_or_default()), self.doc.take()));
+            },
+        }
+    }
+
+    /// Doc comment preceding the next token
+    fn doc(self: &mut Self) -> Option<String> {
+        if self.picked.is_none() {
+            self.picked = self.pull();
+        }
+
+        self.picked.as_ref().and_then(|(_, doc)| doc.clone())
+    }
+
+    /// Take the next token skipping comments, the last doc comment before
+    /// it is kept, consecutive `///` comments are joined
+    fn pull(self: &mut Self) -> Option<(token::SpannedToken, Option<String>)> {
+        let mut doc: Option<(String, bool)> = None;
+
+        for t in self.iter.by_ref() {
+            match &t.value {
+                token::Token::Comment(text) => if let Some((text, line)) = doc_comment(text) {
+                    doc = Some(match doc {
+                        Some((previous, true)) if line => (previous + "\n" + &text, line),
+                        _ => (text, line),
+                    });
+                },
+                _ => return Some((t, doc.map(|(text, _)| text))),
+            }
         }
+
+        None
+    }
+
+    /// Curly brackets nesting level of the last token taken from iterator
+    fn depth(self: &Self) -> isize {
+        self.depth
+    }
+
+    fn reset_depth(self: &mut Self) {
+        self.depth = 0;
+    }
+
+    /// Span of the last token taken from iterator
+    fn span(self: &Self) -> Option<&token::Span> {
+        self.span.as_ref()
     }
 }
 
-impl<I: Iterator<Item=token::Token>> Iterator for PickIterator<I> {
+impl<I: Iterator<Item=token::SpannedToken>> Iterator for PickIterator<I> {
     type Item = token::Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(t) = self.picked.take() {
-            Some(t)
-        } else {
-            self.iter.next()
-        }
+        self.picked.take().or_else(|| self.pull()).map(|(t, doc)| {
+            self.depth += bracket_depth(&t.value);
+            self.previous = self.span.replace(t.span);
+            self.doc = doc;
+            t.value
+        })
+    }
+}
+
+/// Text of `/** */` or `///` comment without decorations, `bool` is set for
+/// the line one
+fn doc_comment(text: &str) -> Option<(String, bool)> {
+    let (text, line) = match (text.strip_prefix('*'), text.strip_prefix('/')) {
+        (Some(text), _) if !text.starts_with('*') => (text, false),
+        (_, Some(text)) if !text.starts_with('/') => (text, true),
+        _ => return None,
+    };
+
+    let lines = text.lines()
+        .map(|l| match line {
+            true => l,
+            false => l.trim_start().strip_prefix('*').unwrap_or(l.trim_start()),
+        })
+        .map(|l| l.strip_prefix(' ').unwrap_or(l).trim_end())
+        .collect::<Vec<_>>();
+
+    let start = lines.iter().position(|line| !line.is_empty())?;
+    let end = lines.iter().rposition(|line| !line.is_empty()).unwrap_or(start);
+
+    Some((lines[start..=end].join("\n"), line))
+}
+
+fn bracket_depth(t: &token::Token) -> isize {
+    match t {
+        token::Token::Bracket(token::Bracket::LeftCurly) => 1,
+        token::Token::Bracket(token::Bracket::RightCurly) => -1,
+        _ => 0,
     }
 }
 
-struct PendingTypes {
-    structs: Option<String>,
-    unions: Option<String>,
+/// Value which can't be folded until constants are known, `name` is set for
+/// constants and enum items, so that they are folded once
+struct PendingValue {
+    name: Option<String>,
+    value: rpc::Value,
+    condition: Box<dyn FnOnce(i128) -> Result<i128>>,
+    span: Option<token::Span>,
+}
+
+/// Names used by definitions, they are checked by [`resolve::resolve`] after
+/// the whole input is parsed, so definitions may go in any order
+struct References {
+    /// Bare names are kept as typedefs until then
+    types: Vec<(rpc::NamedType, Option<token::Span>)>,
+    values: Vec<PendingValue>,
 }
 
 struct DefinedTypes {
@@ -147,96 +352,446 @@ struct DefinedTypes {
     unions: std::collections::HashSet<String>,
 }
 
-struct Handle<I: Iterator<Item=token::Token>> {
+impl DefinedTypes {
+    /// Find defined type with given name to suggest instead of undefined one
+    fn suggest(self: &Self, id: &str) -> Option<String> {
+        [
+            (&self.typedefs, ""),
+            (&self.structs, "struct "),
+            (&self.unions, "union "),
+            (&self.enums, "enum "),
+        ].into_iter()
+            .find(|(types, _)| types.contains(id))
+            .map(|(_, kind)| format!("did you mean `{kind}{id}`?"))
+    }
+}
+
+struct Handle<I: Iterator<Item=token::SpannedToken>> {
     tokens: PickIterator<I>,
     namespace: std::collections::HashSet<String>,
-    values: std::collections::HashSet<String>,
-    pending_types: PendingTypes,
     defined_types: DefinedTypes,
+    references: References,
     assigned_numbers: std::collections::HashSet<rpc::Value>,
+    /// Inline types of current definition, referenced by placeholder names
+    /// until they get the real ones in [`adopt_inline`]
+    inline: Vec<Option<rpc::Definition>>,
+    /// Named inline types in dependency order, they go before the definition
+    adopted: Vec<rpc::Definition>,
+}
+
+pub fn parse(tokens: impl Iterator<Item=impl Into<token::SpannedToken>>) -> Result<rpc::Module> {
+    let (module, errors) = parse_module(tokens, false);
+
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(module),
+    }
 }
 
-pub fn parse(tokens: impl Iterator<Item=token::Token>) -> Result<rpc::Module> {
+/// Parse whole input, broken definitions are skipped up to the next `;` or
+/// `}` on the definition level, so every error in the input is reported.
+/// Returned module contains only successfully parsed definitions.
+pub fn parse_recovering(
+    tokens: impl Iterator<Item=impl Into<token::SpannedToken>>,
+) -> (rpc::Module, Vec<Error>) {
+    parse_module(tokens, true)
+}
+
+fn parse_module(
+    tokens: impl Iterator<Item=impl Into<token::SpannedToken>>,
+    recover: bool,
+) -> (rpc::Module, Vec<Error>) {
     let mut module = rpc::new_module();
     let mut handle = Handle {
-        tokens: PickIterator::new(tokens.filter(|t| match t {
-            token::Token::Comment(_) => false,
-            _ => true,
-        })),
+        tokens: PickIterator::new(tokens.map(Into::into)),
         namespace: std::collections::HashSet::new(),
-        values: std::collections::HashSet::new(),
-        pending_types: PendingTypes {
-            structs: None,
-            unions: None,
-        },
         defined_types: DefinedTypes {
             typedefs: std::collections::HashSet::new(),
             enums: std::collections::HashSet::new(),
             structs: std::collections::HashSet::new(),
             unions: std::collections::HashSet::new(),
         },
+        references: References {
+            types: Vec::new(),
+            values: Vec::new(),
+        },
         assigned_numbers: std::collections::HashSet::new(),
+        inline: Vec::new(),
+        adopted: Vec::new(),
     };
-    let mut err = None;
+    let mut errors = Vec::new();
+    // Where definitions start, inline types share it with their parent
+    let mut spans = Vec::new();
+
+    while let (true, Some(t)) = (recover || errors.is_empty(), handle.tokens.next()) {
+        let span = handle.tokens.span().cloned();
+        let references = (handle.references.types.len(), handle.references.values.len());
 
-    while let (None, Some(t)) = (&err, handle.tokens.next()) {
         handle.tokens.push_back(t);
         match parse_definition(&mut handle) {
-            Ok(def) => module.definitions.push(def),
-            Err(error) => err = Some(error),
+            Ok(def) => {
+                spans.extend(handle.adopted.iter().map(|_| span.clone()));
+                spans.push(span);
+                module.definitions.append(&mut handle.adopted);
+                module.definitions.push(def);
+            },
+            Err(error) => {
+                let error = error.at(handle.tokens.span());
+
+                handle.inline.clear();
+                handle.adopted.clear();
+                handle.references.types.truncate(references.0);
+                handle.references.values.truncate(references.1);
+
+                if recover {
+                    synchronise(&mut handle, &error);
+                }
+
+                errors.push(error);
+            },
         }
     }
 
-    match err {
-        Some(err) => Err(err),
-        None => Ok(module),
+    if recover || errors.is_empty() {
+        errors.extend(resolve::resolve(&mut module, spans, &handle.defined_types, handle.references));
     }
+
+    (module, errors)
+}
+
+fn starts_definition(t: &token::Token) -> bool {
+    matches!(t,
+        token::Token::Keyword(
+            token::Keyword::Const | token::Keyword::Typedef | token::Keyword::Program
+        )
+        | token::Token::Type(
+            token::Type::Enum | token::Type::Struct | token::Type::Union
+        )
+        | token::Token::PassthroughStart
+    )
+}
+
+/// Skip the rest of broken definition. Stops after `;` or unmatched `}` on
+/// the definition level and before the token that starts new definition.
+fn synchronise(handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>, error: &Error) {
+    let done = match &error.kind {
+        ErrorKind::UnexpectedToken(_, t) | ErrorKind::ExpressionNotClosed(_, t)
+            if 0 >= handle.tokens.depth() => match t {
+                token::Token::Separator(token::Separator::Semicolon) => true,
+                t if starts_definition(t) && handle.tokens.picked.is_none() => {
+                    handle.tokens.push_back(t.clone());
+                    true
+                },
+                _ => false,
+            },
+        _ => false,
+    };
+
+    if !done {
+        while let Some(t) = handle.tokens.next() {
+            let depth = handle.tokens.depth();
+
+            match t {
+                token::Token::Separator(token::Separator::Semicolon) if 0 >= depth => break,
+                token::Token::Bracket(token::Bracket::RightCurly) if 0 > depth => break,
+                t if 0 >= depth && starts_definition(&t) => {
+                    handle.tokens.push_back(t);
+                    break;
+                },
+                _ => {},
+            }
+        }
+    }
+
+    handle.tokens.reset_depth();
 }
 
 fn parse_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Definition> {
+    let doc = handle.tokens.doc();
+
     match handle.tokens.next() {
         None => Error::unexpected_eof("Definition expected".to_string()),
         Some(token::Token::Type(token::Type::Enum)) =>
             parse_enum_definition(handle).map(|(id, en)| rpc::Definition::Enum(id, en)),
         Some(token::Token::Keyword(token::Keyword::Const)) =>
-            parse_const_definition(handle).map(|(id, v)| rpc::Definition::Const(id, v)),
+            parse_const_definition(handle)
+                .map(|(id, value)| rpc::Definition::Const(id, rpc::Const { doc: None, value })),
         Some(token::Token::Keyword(token::Keyword::Typedef)) =>
-            parse_typedef_definition(handle).map(|(id, tp)| rpc::Definition::Typedef(id, tp)),
+            parse_typedef_definition(handle)
+                .map(|(id, tp)| rpc::Definition::Typedef(id, rpc::Typedef { doc: None, tp })),
         Some(token::Token::Type(token::Type::Struct)) =>
             parse_struct_definition(handle).map(|(id, st)| rpc::Definition::Struct(id, st)),
         Some(token::Token::Type(token::Type::Union)) =>
             parse_union_definition(handle).map(|(id, un)| rpc::Definition::Union(id, un)),
         Some(token::Token::Keyword(token::Keyword::Program)) =>
             parse_program_definition(handle).map(|(v, pr)| rpc::Definition::Program(v, pr)),
+        Some(token::Token::PassthroughStart) =>
+            return Ok(rpc::Definition::Passthrough(parse_passthrough(handle))),
         Some(t) => Error::unknown_definition(t),
+    }.and_then(|mut def| adopt_inline(handle, &mut def).map(|_| def))
+    .map(|mut def| {
+        if let Some(slot) = def.doc_mut() {
+            *slot = doc;
+        }
+
+        def
+    })
+}
+
+/// Placeholder name of inline type, it can't clash with identifiers
+fn inline_type(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    def: rpc::Definition,
+) -> String {
+    handle.inline.push(Some(def));
+    format!("#{}", handle.inline.len() - 1)
+}
+
+/// Name inline types used by the definition after its fields, e.g.
+/// `struct a { struct { int c; } b; }` defines `struct a_b`. Inline type of
+/// typedef gets its name, procedure arguments are `{procedure}_arg{n}` and
+/// the result is `{procedure}_res`.
+fn adopt_inline(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    def: &mut rpc::Definition,
+) -> Result<()> {
+    match def {
+        rpc::Definition::Typedef(name, typedef) => adopt_type(handle, &mut typedef.tp, name.clone()),
+        rpc::Definition::Struct(name, st) => st.fields.iter_mut()
+            .try_for_each(|(field, tp)| adopt_type(handle, tp, format!("{name}_{field}"))),
+        rpc::Definition::Union(name, un) => {
+            if let rpc::SwitchingType::Enum(en) = &un.switch_type {
+                let mut tp = rpc::Type::Named(rpc::NamedType::Enum(en.clone()));
+
+                adopt_type(handle, &mut tp, format!("{name}_{}", un.value))?;
+                if let rpc::Type::Named(rpc::NamedType::Enum(en)) = tp {
+                    un.switch_type = rpc::SwitchingType::Enum(en);
+                }
+            }
+
+            un.arms.iter_mut()
+                .chain(un.default.as_mut())
+                .filter_map(|arm| arm.declaration.as_mut())
+                .try_for_each(|(arm, tp)| adopt_type(handle, tp, format!("{name}_{arm}")))
+        },
+        rpc::Definition::Program(_, progr) => progr.versions.values_mut()
+            .flat_map(|ver| ver.procedures.values_mut())
+            .try_for_each(|proc| {
+                let name = proc.name.clone();
+
+                adopt_type(handle, &mut proc.return_type, format!("{name}_res"))?;
+                proc.arguments.iter_mut()
+                    .enumerate()
+                    .try_for_each(|(i, tp)| adopt_type(handle, tp, format!("{name}_arg{}", i + 1)))
+            }),
+        rpc::Definition::Const(_, _) | rpc::Definition::Enum(_, _)
+            | rpc::Definition::Passthrough(_) => Ok(()),
+    }
+}
+
+fn adopt_type(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    tp: &mut rpc::Type,
+    name: String,
+) -> Result<()> {
+    let placeholder = match tp {
+        rpc::Type::Pointer(tp) | rpc::Type::Array(tp, _) | rpc::Type::VArray(tp, _) =>
+            return adopt_type(handle, tp, name),
+        rpc::Type::Named(
+            rpc::NamedType::Struct(id) | rpc::NamedType::Union(id) | rpc::NamedType::Enum(id)
+        ) => match id.strip_prefix('#').map(str::parse::<usize>) {
+            Some(Ok(index)) => {
+                *id = name.clone();
+                index
+            },
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    let mut def = handle.inline[placeholder].take().expect("Inline type is used once");
+    let (defined, kind) = match &mut def {
+        rpc::Definition::Struct(id, _) => {
+            *id = name.clone();
+            (&mut handle.defined_types.structs, "Struct")
+        },
+        rpc::Definition::Union(id, _) => {
+            *id = name.clone();
+            (&mut handle.defined_types.unions, "Union")
+        },
+        rpc::Definition::Enum(id, _) => {
+            *id = name.clone();
+            (&mut handle.defined_types.enums, "Enum")
+        },
+        _ => unreachable!("Only structs, unions and enums are inline"),
+    };
+
+    match defined.insert(name.clone()) {
+        false => Error::type_redefined(format!("{kind} with id \"{name}\" for inline type already exists")),
+        true => adopt_inline(handle, &mut def).map(|_| handle.adopted.push(def)),
     }
 }
 
-fn parse_value(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+/// Program, version or procedure number, which is `unsigned int`
+fn parse_number(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    what: &'static str,
 ) -> Result<rpc::Value> {
-    parse_value_condition(handle, |num| Ok(num))
+    parse_value_condition(handle, in_range(rpc::Integer::Integer.range(true), what))
 }
 
-fn parse_value_condition<F: FnOnce(i64) -> Result<i64>>(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+/// Condition for [`parse_value_condition`] checking that value fits its use
+fn in_range(range: std::ops::RangeInclusive<i128>, what: &'static str) -> impl FnOnce(i128) -> Result<i128> {
+    move |num| match range.contains(&num) {
+        true => Ok(num),
+        false => Error::value_out_of_range(num, format!("{what}, expected {}..={}", range.start(), range.end())),
+    }
+}
+
+fn parse_value_condition<F: FnOnce(i128) -> Result<i128> + 'static>(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
     cond: F,
 ) -> Result<rpc::Value> {
+    parse_named_value(handle, None, cond).map(|(v, _)| v)
+}
+
+/// Value of constant or enum item `name`, the condition is checked once
+/// identifiers it uses are resolved
+fn parse_named_value<F: FnOnce(i128) -> Result<i128> + 'static>(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    name: Option<&String>,
+    cond: F,
+) -> Result<(rpc::Value, Option<i128>)> {
+    let span = handle.tokens.next().and_then(|t| {
+        let span = handle.tokens.span().cloned();
+
+        handle.tokens.push_back(t);
+        span
+    });
+
+    parse_expression(handle, 1).and_then(|(value, num)| match num {
+        Some(num) => cond(num).map(|num| (value, Some(num))),
+        None => {
+            handle.references.values.push(PendingValue {
+                name: name.cloned(),
+                value: value.clone(),
+                condition: Box::new(cond),
+                span,
+            });
+            Ok((value, None))
+        },
+    })
+}
+
+fn binary_operator(t: &token::Token) -> Option<rpc::BinaryOperator> {
+    match t {
+        token::Token::Operator(op) => match op {
+            token::Operator::Add => Some(rpc::BinaryOperator::Add),
+            token::Operator::Subtract => Some(rpc::BinaryOperator::Subtract),
+            token::Operator::Divide => Some(rpc::BinaryOperator::Divide),
+            token::Operator::Remainder => Some(rpc::BinaryOperator::Remainder),
+            token::Operator::ShiftLeft => Some(rpc::BinaryOperator::ShiftLeft),
+            token::Operator::ShiftRight => Some(rpc::BinaryOperator::ShiftRight),
+            token::Operator::Or => Some(rpc::BinaryOperator::Or),
+            token::Operator::And => Some(rpc::BinaryOperator::And),
+            token::Operator::Assign | token::Operator::Not => None,
+        },
+        token::Token::Type(token::Type::Pointer) => Some(rpc::BinaryOperator::Multiply),
+        // `A -1` is lexed as identifier and negative number
+        token::Token::Literal(token::Literal::Integer(num)) if 0 > *num => Some(rpc::BinaryOperator::Subtract),
+        _ => None,
+    }
+}
+
+/// Expression with operators binding at least as strong as `min`, value
+/// is folded along the way to report overflows where they happen. It is
+/// `None` for expressions with identifiers, they are folded by [`resolve`]
+fn parse_expression(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    min: u8,
+) -> Result<(rpc::Value, Option<i128>)> {
+    let (mut value, mut num) = parse_operand(handle)?;
+
+    while let Some(t) = handle.tokens.next() {
+        let op = match binary_operator(&t) {
+            Some(op) if op.precedence() >= min => op,
+            _ => {
+                handle.tokens.push_back(t);
+                break;
+            },
+        };
+        let span = handle.tokens.span().cloned();
+
+        if let token::Token::Literal(token::Literal::Integer(negative)) = t {
+            match negative.checked_neg() {
+                Some(positive) => handle.tokens.push_back(token::Token::Literal(token::Literal::Integer(positive))),
+                None => return Error::arithmetic_overflow(format!("-({negative})")).map_err(|e| e.at(span.as_ref())),
+            }
+        }
+
+        let (right, rnum) = parse_expression(handle, op.precedence() + 1)?;
+
+        num = match (num, rnum) {
+            (Some(num), Some(rnum)) => Some(fold_binary(op, num, rnum).map_err(|e| e.at(span.as_ref()))?),
+            _ => None,
+        };
+        value = rpc::Value::Binary(op, Box::new(value), Box::new(right));
+    }
+
+    Ok((value, num))
+}
+
+fn fold_binary(op: rpc::BinaryOperator, num: i128, rnum: i128) -> Result<i128> {
+    match (op.apply(num, rnum), op) {
+        (Some(num), _) => Ok(num),
+        (None, rpc::BinaryOperator::Divide | rpc::BinaryOperator::Remainder) if 0 == rnum =>
+            Error::division_by_zero(format!("{num} {op} {rnum}")),
+        (None, _) => Error::arithmetic_overflow(format!("{num} {op} {rnum}")),
+    }
+}
+
+fn fold_unary(op: rpc::UnaryOperator, num: i128) -> Result<i128> {
+    match op.apply(num) {
+        Some(num) => Ok(num),
+        None => Error::arithmetic_overflow(format!("{op}({num})")),
+    }
+}
+
+fn parse_operand(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+) -> Result<(rpc::Value, Option<i128>)> {
+    let unary = |handle: &mut Handle<_>, op: rpc::UnaryOperator| {
+        let span = handle.tokens.span().cloned();
+
+        parse_operand(handle).and_then(|(v, num)| match num {
+            Some(num) => fold_unary(op, num).map(Some).map_err(|e| e.at(span.as_ref())),
+            None => Ok(None),
+        }.map(|num| (rpc::Value::Unary(op, Box::new(v)), num)))
+    };
+
     match handle.tokens.next() {
         None => Error::unexpected_eof("Expected value".to_string()),
-        Some(token::Token::Literal(token::Literal::Integer(num))) => cond(num).map(|num| rpc::Value::Number(num)),
-        Some(token::Token::Identifier(id)) => match handle.values.get(&id) {
-            Some(_) => Ok(rpc::Value::Identifier(id)),
-            None => Error::undefined_value(id),
+        Some(token::Token::Literal(token::Literal::Integer(num))) => match rpc::VALUE_RANGE.contains(&num) {
+            true => Ok((rpc::Value::Number(num), Some(num))),
+            false => Error::arithmetic_overflow(num.to_string()),
         },
+        Some(token::Token::Identifier(id)) => Ok((rpc::Value::Identifier(id), None)),
+        Some(token::Token::Operator(token::Operator::Not)) => unary(handle, rpc::UnaryOperator::Not),
+        Some(token::Token::Operator(token::Operator::Subtract)) => unary(handle, rpc::UnaryOperator::Negate),
+        Some(token::Token::Bracket(token::Bracket::Left)) => parse_expression(handle, 1)
+            .and_then(|pass| match handle.tokens.next() {
+                None => Error::unexpected_eof("Expected closing bracket".to_string()),
+                Some(token::Token::Bracket(token::Bracket::Right)) => Ok(pass),
+                Some(t) => Error::expression_not_closed("Expected closing bracket".to_string(), t),
+            }),
         Some(t) => Error::unexpected_token("Expected value".to_string(), t),
     }
 }
 
 fn parse_type_identifier(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Type> {
     match handle.tokens.next() {
         None => Error::unexpected_eof("Expected declaration type".to_string()),
@@ -258,40 +813,40 @@ fn parse_type_identifier(
             token::Type::Opaque => Ok(rpc::Type::Opaque),
             token::Type::Enum => match handle.tokens.next() {
                 None => Error::unexpected_eof("No identifier for enum".to_string()),
-                Some(token::Token::Identifier(id)) => match handle.defined_types.enums.get(&id) {
-                    None => Error::undefined_type(format!{"Unknown enum with identifier {id}"}),
-                    _ => Ok(rpc::Type::Named(rpc::NamedType::Enum(id)))
+                Some(token::Token::Identifier(id)) => Ok(reference(handle, rpc::NamedType::Enum(id))),
+                Some(token::Token::Bracket(token::Bracket::LeftCurly)) => {
+                    handle.tokens.push_back(token::Token::Bracket(token::Bracket::LeftCurly));
+                    parse_enum_block(handle).map(|en| rpc::Type::Named(rpc::NamedType::Enum(
+                        inline_type(handle, rpc::Definition::Enum(String::new(), en))
+                    )))
                 },
                 Some(t) => Error::unexpected_token("No identifier for enum".to_string(), t),
             },
             token::Type::Struct => match handle.tokens.next() {
                 None => Error::unexpected_eof("No identifier for struct".to_string()),
-                Some(token::Token::Identifier(id)) => match (handle.defined_types.structs.get(&id), &handle.pending_types.structs) {
-                    (None, None) =>
-                        Error::undefined_type(format!{"Unknown struct with identifier {id}"}),
-                    (None, Some(pid)) if *pid != id =>
-                        Error::undefined_type(format!{"Unknown struct with identifier {id}"}),
-                    _ => Ok(rpc::Type::Named(rpc::NamedType::Struct(id)))
+                Some(token::Token::Identifier(id)) => Ok(reference(handle, rpc::NamedType::Struct(id))),
+                Some(token::Token::Bracket(token::Bracket::LeftCurly)) => {
+                    handle.tokens.push_back(token::Token::Bracket(token::Bracket::LeftCurly));
+                    parse_struct_block(handle).map(|st| rpc::Type::Named(rpc::NamedType::Struct(
+                        inline_type(handle, rpc::Definition::Struct(String::new(), st))
+                    )))
                 },
                 Some(t) => Error::unexpected_token("No identifier for struct".to_string(), t),
             },
             token::Type::Union => match handle.tokens.next() {
                 None => Error::unexpected_eof("No identifier for union".to_string()),
-                Some(token::Token::Identifier(id)) => match (handle.defined_types.unions.get(&id), &handle.pending_types.unions) {
-                    (None, None) =>
-                        Error::undefined_type(format!{"Unknown union with identifier {id}"}),
-                    (None, Some(pid)) if *pid != id =>
-                        Error::undefined_type(format!{"Unknown union with identifier {id}"}),
-                    _ => Ok(rpc::Type::Named(rpc::NamedType::Union(id)))
+                Some(token::Token::Identifier(id)) => Ok(reference(handle, rpc::NamedType::Union(id))),
+                Some(token::Token::Keyword(token::Keyword::Switch)) => {
+                    handle.tokens.push_back(token::Token::Keyword(token::Keyword::Switch));
+                    parse_union_block(handle).map(|un| rpc::Type::Named(rpc::NamedType::Union(
+                        inline_type(handle, rpc::Definition::Union(String::new(), un))
+                    )))
                 },
                 Some(t) => Error::unexpected_token("No identifier for union".to_string(), t),
             },
             token::Type::Pointer => Error::undefined_type("No type for pointer".to_string()),
         },
-        Some(token::Token::Identifier(id)) => match handle.defined_types.typedefs.get(&id) {
-            None => Error::undefined_type(format!{"Unknown type identifier {id}"}),
-            _ => Ok(rpc::Type::Named(rpc::NamedType::Typedef(id)))
-        },
+        Some(token::Token::Identifier(id)) => Ok(reference(handle, rpc::NamedType::Typedef(id))),
         Some(t) => Error::unexpected_token("Expected declaration type".to_string(), t),
     }.and_then(|tp| Ok(match handle.tokens.next() {
         Some(token::Token::Type(token::Type::Pointer)) =>
@@ -301,27 +856,19 @@ fn parse_type_identifier(
             handle.tokens.push_back(t);
             tp
         }
-    })).and_then(|tp| match tp { // Check not to use pending types directly
-        rpc::Type::Named(nm) => match nm {
-            rpc::NamedType::Struct(st) => match &handle.pending_types.structs {
-                Some(rst) if st == *rst => Error::use_of_pending_type(format!(
-                    "Can't use struct \"{st}\" that is being defined directly"
-                )),
-                _ => Ok(rpc::Type::Named(rpc::NamedType::Struct(st)))
-            },
-            rpc::NamedType::Union(un) => match &handle.pending_types.unions {
-                Some(run) if un == *run => Error::use_of_pending_type(format!(
-                    "Can't use union \"{un}\" that is being defined directly"
-                )),
-                _ => Ok(rpc::Type::Named(rpc::NamedType::Union(un)))
-            },
-            _ => Ok(rpc::Type::Named(nm)),
-        },
-        _ => Ok(tp),
-    })
+    }))
+}
+
+/// Type used by name, it is checked once all definitions are parsed
+fn reference(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    named: rpc::NamedType,
+) -> rpc::Type {
+    handle.references.types.push((named.clone(), handle.tokens.span().cloned()));
+    rpc::Type::Named(named)
 }
 
-fn parse_array_type(handle: &mut Handle<impl Iterator<Item=token::Token>>, tp: rpc::Type) -> Result<rpc::Type> {
+fn parse_array_type(handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>, tp: rpc::Type) -> Result<rpc::Type> {
     match handle.tokens.next() {
         Some(token::Token::Bracket(br)) => match br {
             token::Bracket::LeftTriangle => match handle.tokens.next() {
@@ -333,7 +880,7 @@ fn parse_array_type(handle: &mut Handle<impl Iterator<Item=token::Token>>, tp: r
                     parse_value_condition(handle, |num| if 0 >= num {
                         Error::non_positive_array_size(num)
                     } else {
-                        Ok(num)
+                        in_range(rpc::Integer::Integer.range(true), "array size")(num)
                     }).and_then(|v| match handle.tokens.next() {
                         None => Error::unexpected_eof("Expected variadic array closing bracket".to_string()),
                         Some(token::Token::Bracket(token::Bracket::RightTriangle)) =>
@@ -345,7 +892,7 @@ fn parse_array_type(handle: &mut Handle<impl Iterator<Item=token::Token>>, tp: r
             token::Bracket::LeftSquare => parse_value_condition(handle, |num| if 0 >= num {
                 Error::non_positive_array_size(num)
             } else {
-                Ok(num)
+                in_range(rpc::Integer::Integer.range(true), "array size")(num)
             }).and_then(|v| match handle.tokens.next() {
                 None => Error::unexpected_eof("Expected array closing bracket".to_string()),
                 Some(token::Token::Bracket(token::Bracket::RightSquare)) =>
@@ -365,7 +912,7 @@ fn parse_array_type(handle: &mut Handle<impl Iterator<Item=token::Token>>, tp: r
     }
 }
 
-fn parse_declaration(handle: &mut Handle<impl Iterator<Item=token::Token>>) -> Result<(String, rpc::Type)> {
+fn parse_declaration(handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>) -> Result<(String, rpc::Type)> {
     parse_type_identifier(handle)
         .and_then(|tp| match handle.tokens.next() {
             None => Error::unexpected_eof("Expected declaration identifier".to_string()),
@@ -376,12 +923,12 @@ fn parse_declaration(handle: &mut Handle<impl Iterator<Item=token::Token>>) -> R
         })
 }
 
-fn parse_type(handle: &mut Handle<impl Iterator<Item=token::Token>>) -> Result<rpc::Type> {
+fn parse_type(handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>) -> Result<rpc::Type> {
     parse_type_identifier(handle).and_then(|tp| parse_array_type(handle, tp))
 }
 
 fn parse_enum_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(String, rpc::Enum)> {
     match handle.tokens.next() {                                 // Identifier
         None => Error::unexpected_eof("No enum identifier was provided".to_owned()),
@@ -390,16 +937,8 @@ fn parse_enum_definition(
             None => Ok(id),
         },
         Some(t) => Error::unexpected_token("Expected enum identifier".to_owned(), t),
-    }.and_then(|id| match handle.tokens.next() {                 // {
-        None => Error::unexpected_eof("No enum body".to_owned()),
-        Some(token::Token::Bracket(token::Bracket::LeftCurly)) => Ok(id),
-        Some(t) => Error::unexpected_token("Expected enum body \"{\"".to_owned(), t),
-    }).and_then(|id| parse_enum_body(handle).map(|en| (id, en))) // Body
-    .and_then(|pass| match handle.tokens.next() {                // }
-        None => Error::unexpected_eof("Enum definition wasn't finished".to_owned()),
-        Some(token::Token::Bracket(token::Bracket::RightCurly)) => Ok(pass),
-        Some(t) => Error::unexpected_token("Enum definition wasn't finished".to_owned(), t),
-    }).and_then(|pass| match handle.tokens.next() {              // ;
+    }.and_then(|id| parse_enum_block(handle).map(|en| (id, en))) // Body
+    .and_then(|pass| match handle.tokens.next() {                // ;
         None => Error::unexpected_eof("Enum definition wasn't finished".to_owned()),
         Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
         Some(t) => Error::expression_not_closed("Enum definition wasn't finished".to_owned(), t),
@@ -409,40 +948,82 @@ fn parse_enum_definition(
     })
 }
 
+/// Enum body in curly brackets
+fn parse_enum_block(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+) -> Result<rpc::Enum> {
+    match handle.tokens.next() {                   // {
+        None => Error::unexpected_eof("No enum body".to_owned()),
+        Some(token::Token::Bracket(token::Bracket::LeftCurly)) => Ok(()),
+        Some(t) => Error::unexpected_token("Expected enum body \"{\"".to_owned(), t),
+    }.and_then(|_| parse_enum_body(handle))        // Body
+    .and_then(|pass| match handle.tokens.next() {  // }
+        None => Error::unexpected_eof("Enum definition wasn't finished".to_owned()),
+        Some(token::Token::Bracket(token::Bracket::RightCurly)) => Ok(pass),
+        Some(t) => Error::unexpected_token("Enum definition wasn't finished".to_owned(), t),
+    })
+}
+
+/// Item without value gets `next` one, which is the previous item plus one
+/// with its folded value, values have to fit `int`
 fn parse_enum_item(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
-) -> Result<(String, Option<rpc::Value>)> {
-    match handle.tokens.next() {                        // Identifier
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    next: (rpc::Value, Option<i128>),
+) -> Result<(String, Option<rpc::Value>, Option<i128>)> {
+    let range = || in_range(rpc::Integer::Integer.range(false), "enum value");
+
+    match handle.tokens.next() {                              // Identifier
         None => Error::unexpected_eof("Expected enum item identifier".to_owned()),
         Some(token::Token::Identifier(id)) => match handle.namespace.get(&id) {
             Some(_) => Error::identifier_redefined(format!("Enum identifier \"{id}\" already exists")),
-            None => Ok(id),
+            None => Ok((id, handle.tokens.span().cloned())),
         },
         Some(t) => Error::expression_not_closed("Expected enum item identifier".to_owned(), t),
-    }.and_then(|id| match handle.tokens.next() {        // [=]
-        None => Ok((id, None)),
+    }.and_then(|(id, span)| match handle.tokens.next() {      // [=]
         Some(token::Token::Operator(token::Operator::Assign)) =>
-            parse_value(handle).map(|v| (id, Some(v))), // [Value]
-        Some(t) => {
-            handle.tokens.push_back(t);
-            Ok((id, None))
-        }
-    }).and_then(|(id, v)| {
+            parse_named_value(handle, Some(&id), range())     // [Value]
+                .map(|(v, num)| (id, Some(v), num)),
+        t => {
+            if let Some(t) = t {
+                handle.tokens.push_back(t);
+            }
+            match next {
+                (_, Some(num)) => range()(num).map(|num| (id, None, Some(num))),
+                (value, None) => {
+                    handle.references.values.push(PendingValue {
+                        name: Some(id.clone()),
+                        value,
+                        condition: Box::new(range()),
+                        span,
+                    });
+                    Ok((id, None, None))
+                },
+            }
+        },
+    }).map(|(id, v, num)| {
         handle.namespace.insert(id.clone());
-        handle.values.insert(id.clone());
-        Ok((id, v))
+        (id, v, num)
     })
 }
 
 fn parse_enum_body(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Enum> {
     let mut en = rpc::new_enum();
     let mut error: Option<Error> = None;
-
-    while match parse_enum_item(handle) { // Item
-        Ok(item) => {
-            en.push(item);
+    let mut next = (rpc::Value::Number(0), Some(0));
+
+    while match parse_enum_item(handle, next.clone()) { // Item
+        Ok((id, v, num)) => {
+            next = (
+                rpc::Value::Binary(
+                    rpc::BinaryOperator::Add,
+                    Box::new(rpc::Value::Identifier(id.clone())),
+                    Box::new(rpc::Value::Number(1)),
+                ),
+                num.map(|num| num + 1),
+            );
+            en.items.push((id, v));
 
             match handle.tokens.next() {  // [,]
                 None => false,
@@ -466,8 +1047,20 @@ fn parse_enum_body(
 }
 
 
+/// Text of `%` line, it's missing only when `%` is the last character
+fn parse_passthrough(handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>) -> String {
+    match handle.tokens.next() {
+        Some(token::Token::Passthrough(line)) => line,
+        Some(t) => {
+            handle.tokens.push_back(t);
+            String::new()
+        },
+        None => String::new(),
+    }
+}
+
 fn parse_const_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(String, rpc::Value)> {
     match handle.tokens.next() {                           // Identifier
         None => Error::unexpected_eof("Expected const identifier".to_owned()),
@@ -480,20 +1073,21 @@ fn parse_const_definition(
         None => Error::unexpected_eof("Expected assign sign".to_string()),
         Some(token::Token::Operator(token::Operator::Assign)) => Ok(pass),
         Some(t) => Error::unexpected_token("Expected assign sign".to_string(), t),
-    }).and_then(|id| parse_value(handle).map(|v| (id, v))) // Value
+    }).and_then(|id| parse_named_value(handle, Some(&id), Ok) // Value
+        .map(|(v, _)| (id, v))
+    )
     .and_then(|pass| match handle.tokens.next() {          // ;
         None => Error::unexpected_eof("Const definition wasn't finished".to_owned()),
         Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
         Some(t) => Error::expression_not_closed("Const definition wasn't finished".to_owned(), t),
     }).and_then(|(id, v)| {
         handle.namespace.insert(id.clone());
-        handle.values.insert(id.clone());
         Ok((id, v))
     })
 }
 
 fn parse_typedef_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(String, rpc::Type)> {
     parse_declaration(handle)                           // Declaraion
         .and_then(|(id, tp)| match handle.namespace.get(&id) {
@@ -511,54 +1105,55 @@ fn parse_typedef_definition(
 }
 
 fn parse_struct_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(String, rpc::Struct)> {
-    let out = match handle.tokens.next() {                       // Identifier
+    // Struct is declared before its body, so that it is still declared if
+    // the body is broken and further references to it don't produce extra
+    // errors during recovery
+    match handle.tokens.next() {                                 // Identifier
         None => Error::unexpected_eof("No struct identifier was provided".to_owned()),
-        Some(token::Token::Identifier(id)) => match handle.defined_types.structs.get(&id) {
-            Some(_) => Error::type_redefined(format!{"Struct with id \"{id}\" already exists"}),
-            None => {
-                handle.pending_types.structs = Some(id.clone());
-                Ok(id)
-            },
+        Some(token::Token::Identifier(id)) => match handle.defined_types.structs.insert(id.clone()) {
+            false => Error::type_redefined(format!{"Struct with id \"{id}\" already exists"}),
+            true => Ok(id),
         },
         Some(t) => Error::unexpected_token("Expected struct identifier".to_owned(), t),
-    }.and_then(|id| match handle.tokens.next() {                 // {
+    }.and_then(|id| parse_struct_block(handle).map(|st| (id, st))) // Body
+    .and_then(|pass| match handle.tokens.next() {                // ;
+        None => Error::unexpected_eof("Struct definition wasn't finished".to_owned()),
+        Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
+        Some(t) => Error::expression_not_closed("Struct definition wasn't finished".to_owned(), t),
+    })
+}
+
+/// Struct body in curly brackets
+fn parse_struct_block(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+) -> Result<rpc::Struct> {
+    match handle.tokens.next() {                   // {
         None => Error::unexpected_eof("No struct body".to_owned()),
-        Some(token::Token::Bracket(token::Bracket::LeftCurly)) => Ok(id),
+        Some(token::Token::Bracket(token::Bracket::LeftCurly)) => Ok(()),
         Some(t) => Error::unexpected_token("Expected struct body \"{\"".to_owned(), t),
-    }).and_then(|id| parse_struct_body(handle).map(|en| (id, en))) // Body
-    .and_then(|pass| match handle.tokens.next() {                // }
+    }.and_then(|_| parse_struct_body(handle))      // Body
+    .and_then(|pass| match handle.tokens.next() {  // }
         None => Error::unexpected_eof("Struct definition wasn't finished".to_owned()),
         Some(token::Token::Bracket(token::Bracket::RightCurly)) => Ok(pass),
         Some(t) => Error::unexpected_token("Struct definition wasn't finished".to_owned(), t),
-    }).and_then(|pass| match handle.tokens.next() {              // ;
-        None => Error::unexpected_eof("Struct definition wasn't finished".to_owned()),
-        Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
-        Some(t) => Error::expression_not_closed("Struct definition wasn't finished".to_owned(), t),
-    }).and_then(|(id, en)| {
-        handle.defined_types.structs.insert(id.clone());
-        Ok((id, en))
-    });
-
-    handle.pending_types.structs = None;
-
-    out
+    })
 }
 
 fn parse_struct_body(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Struct> {
     let mut st = rpc::new_struct();
     let mut error: Option<Error> = None;
 
     while match parse_declaration(handle)    // Item
-        .and_then(|(id, tp)| match st.get(&id) {
+        .and_then(|(id, tp)| match st.fields.get(&id) {
             Some(_) => Error::structure_field_redefined(
                 format!("Field with identifier \"{id}\" already exists")
             ),
             None => {
-                st.insert(id, tp);
+                st.fields.insert(id, tp);
 
                 match handle.tokens.next() { // ;
                     None => Error::unexpected_eof(
@@ -596,116 +1191,138 @@ fn parse_struct_body(
 }
 
 fn parse_union_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(String, rpc::Union)> {
-    let out = match handle.tokens.next() {                                 // Identifier
+    // Same as for structs
+    match handle.tokens.next() {                                  // Identifier
         None => Error::unexpected_eof("No union identifier was provided".to_owned()),
-        Some(token::Token::Identifier(id)) => match handle.defined_types.unions.get(&id) {
-            Some(_) => Error::type_redefined(format!{"Union with id \"{id}\" already exists"}),
-            None => {
-                handle.pending_types.unions = Some(id.clone());
-                Ok(id)
-            },
+        Some(token::Token::Identifier(id)) => match handle.defined_types.unions.insert(id.clone()) {
+            false => Error::type_redefined(format!{"Union with id \"{id}\" already exists"}),
+            true => Ok(id),
         },
         Some(t) => Error::unexpected_token("Expected union identifier".to_owned(), t),
-    }.and_then(|pass| match handle.tokens.next() {                         // switch
-        None => Error::unexpected_eof("Keyword \"case\" expected".to_owned()),
-        Some(token::Token::Keyword(token::Keyword::Switch)) => Ok(pass),
-        Some(t) => Error::unexpected_token("Keyword \"case\" expected".to_owned(), t),
-    }).and_then(|pass| match handle.tokens.next() {                        // (
+    }.and_then(|id| parse_union_block(handle).map(|un| (id, un))) // Body
+    .and_then(|pass| match handle.tokens.next() {                 // ;
+        None => Error::unexpected_eof("Union definition wasn't finished".to_owned()),
+        Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
+        Some(t) => Error::expression_not_closed("Union definition wasn't finished".to_owned(), t),
+    })
+}
+
+/// Union discriminant and body in curly brackets
+fn parse_union_block(
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+) -> Result<rpc::Union> {
+    match handle.tokens.next() {                                    // switch
+        None => Error::unexpected_eof("Keyword \"switch\" expected".to_owned()),
+        Some(token::Token::Keyword(token::Keyword::Switch)) => Ok(()),
+        Some(t) => Error::unexpected_token("Keyword \"switch\" expected".to_owned(), t),
+    }.and_then(|_| match handle.tokens.next() {                      // (
         None => Error::unexpected_eof("Expected \"(\"".to_owned()),
-        Some(token::Token::Bracket(token::Bracket::Left)) => Ok(pass),
+        Some(token::Token::Bracket(token::Bracket::Left)) => Ok(()),
         Some(t) => Error::unexpected_token("Expected \"(\"".to_owned(), t),
-    }).and_then(|id| parse_declaration(handle).and_then(|(sid, tp)|        // Declaration
+    }).and_then(|_| parse_declaration(handle).and_then(|(sid, tp)| { // Declaration
+        // Bare name is kept as enum until resolve finds what it names, cases
+        // are checked against that type there
+        let bare = matches!(tp, rpc::Type::Named(rpc::NamedType::Typedef(_)));
+
         match tp {
             rpc::Type::Integer(i) => Ok(rpc::SwitchingType::Integer(i)),
             rpc::Type::Unsigned(u) => Ok(rpc::SwitchingType::Unsigned(u)),
-            rpc::Type::Named(rpc::NamedType::Enum(en)) => Ok(rpc::SwitchingType::Enum(en)),
+            rpc::Type::Named(rpc::NamedType::Enum(en) | rpc::NamedType::Typedef(en)) =>
+                Ok(rpc::SwitchingType::Enum(en)),
             tp => Error::not_switching_type(tp),
-        }.map(|stp| (id, (sid, stp)))
-    )).and_then(|pass| match handle.tokens.next() {                        // )
-        None => Error::unexpected_eof("Expected \"(\"".to_owned()),
+        }.map(|stp| {
+            let range = match bare {
+                true => rpc::VALUE_RANGE,
+                false => stp.range(),
+            };
+
+            (sid, stp, range)
+        })
+    })).and_then(|pass| match handle.tokens.next() {                  // )
+        None => Error::unexpected_eof("Expected \")\"".to_owned()),
         Some(token::Token::Bracket(token::Bracket::Right)) => Ok(pass),
-        Some(t) => Error::unexpected_token("Expected \"(\"".to_owned(), t),
-    }).and_then(|pass| match handle.tokens.next() {                        // {
+        Some(t) => Error::unexpected_token("Expected \")\"".to_owned(), t),
+    }).and_then(|pass| match handle.tokens.next() {                  // {
         None => Error::unexpected_eof("No union body".to_owned()),
         Some(token::Token::Bracket(token::Bracket::LeftCurly)) => Ok(pass),
         Some(t) => Error::unexpected_token("Expected union body \"{\"".to_owned(), t),
-    }).and_then(|(id, (sid, stp))| parse_union_body(handle).map(|mut un| { // Body
+    }).and_then(|(sid, stp, range)| parse_union_body(handle, range).map(|mut un| { // Body
         un.value = sid;
         un.switch_type = stp;
-        (id, un)
-    })).and_then(|pass| match handle.tokens.next() {                       // }
+        un
+    })).and_then(|pass| match handle.tokens.next() {                 // }
         None => Error::unexpected_eof("Union definition wasn't finished".to_owned()),
         Some(token::Token::Bracket(token::Bracket::RightCurly)) => Ok(pass),
         Some(t) => Error::unexpected_token("Union definition wasn't finished".to_owned(), t),
-    }).and_then(|pass| match handle.tokens.next() {                        // ;
-        None => Error::unexpected_eof("Union definition wasn't finished".to_owned()),
-        Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
-        Some(t) => Error::expression_not_closed("Union definition wasn't finished".to_owned(), t),
-    }).and_then(|(id, en)| {
-        handle.defined_types.unions.insert(id.clone());
-        Ok((id, en))
-    });
-
-    handle.pending_types.unions = None;
-
-    out
-}
-
-enum UnionItem {
-    Regular(rpc::Value, (String, rpc::Type)),
-    Default(String, rpc::Type),
+    })
 }
 
+/// Union arm with its labels, `bool` is set if one of them is `default`.
+/// Cases are checked to be in `range` of the discriminant type.
 fn parse_union_item(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
-) -> Result<UnionItem> {
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    range: &std::ops::RangeInclusive<i128>,
+) -> Result<(rpc::Arm, bool)> {
+    let mut arm = rpc::Arm { cases: Vec::new(), declaration: None };
+    let mut default = false;
+
+    loop {
+        match handle.tokens.next() {
+            None => return Error::unexpected_eof("Matching value expected".to_owned()),
+            Some(token::Token::Keyword(token::Keyword::Case)) => arm.cases.push( // Case
+                parse_value_condition(                                          // Value
+                    handle,
+                    in_range(range.clone(), "union discriminant"),
+                )?
+            ),
+            Some(token::Token::Keyword(token::Keyword::Default)) if !default =>  // Default
+                default = true,
+            Some(t) if arm.cases.is_empty() && !default =>
+                return Error::unexpected_token("Matching value expected".to_owned(), t),
+            Some(t) => {
+                handle.tokens.push_back(t);
+                break;
+            },
+        }
+
+        match handle.tokens.next() {                                            // :
+            None => return Error::unexpected_eof("Colon expected".to_owned()),
+            Some(token::Token::Separator(token::Separator::Colon)) => {},
+            Some(t) => return Error::unexpected_token("Colon expected".to_owned(), t),
+        }
+    }
+
     match handle.tokens.next() {
-        None => Error::unexpected_eof("Matching value expected".to_owned()),
-        Some(token::Token::Keyword(token::Keyword::Case)) =>      // Case
-            parse_value(handle)                                   // Value
-                .and_then(|pass| match handle.tokens.next() {     // :
-                    None => Error::unexpected_eof("Colon expected".to_owned()),
-                    Some(token::Token::Separator(token::Separator::Colon)) => Ok(pass),
-                    Some(t) => Error::unexpected_token("Colon expected".to_owned(), t),
-                }).and_then(|v| {
-                    parse_declaration(handle)                     // Declaration
-                        .map(|decl| UnionItem::Regular(v, decl))
-                }),
-        Some(token::Token::Keyword(token::Keyword::Default)) => { // Default
-            match handle.tokens.next() {                          // :
-                None => Error::unexpected_eof("Colon expected".to_owned()),
-                Some(token::Token::Separator(token::Separator::Colon)) => Ok(()),
-                Some(t) => Error::unexpected_token("Colon expected".to_owned(), t),
-            }.and_then(|_| {
-                parse_declaration(handle)                         // Declaration
-                    .map(|(id, tp)| UnionItem::Default(id, tp))
-            })
+        Some(token::Token::Type(token::Type::Void)) => Ok(None),                // void
+        Some(t) => {
+            handle.tokens.push_back(t);
+            parse_declaration(handle).map(Some)                                 // Declaration
         },
-        Some(t) => Error::unexpected_token("Matching value expected".to_owned(), t),
-    }
+        None => Error::unexpected_eof("Expected declaration type".to_string()),
+    }.map(|declaration| (rpc::Arm { declaration, ..arm }, default))
 }
 
 fn parse_union_body(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
+    range: std::ops::RangeInclusive<i128>,
 ) -> Result<rpc::Union> {
     let mut un = rpc::new_union();
+    let mut cases = std::collections::HashSet::new();
     let mut error: Option<Error> = None;
 
-    while match parse_union_item(handle).and_then(|item| // Item
-        match item {
-            UnionItem::Regular(v, decl) => match un.arms.get(&v) {
-                Some(_) => Error::union_arm_redefined(v),
-                None => {
-                    un.arms.insert(v, decl);
-                    Ok(true)
-                },
+    while match parse_union_item(handle, &range).and_then(|(arm, default)| // Item
+        match (arm.cases.iter().find(|v| !cases.insert((*v).clone())), default) {
+            (Some(v), _) => Error::union_arm_redefined(v.clone()),
+            (None, false) => {
+                un.arms.push(arm);
+                Ok(true)
             },
-            UnionItem::Default(id, tp) => match &un.default {
+            (None, true) => match &un.default {
                 Some(_) => Error::union_default_redefined(),
                 None => {
-                    un.default = Some((id, tp));
+                    un.default = Some(arm);
                     Ok(false)
                 },
             },
@@ -718,17 +1335,17 @@ fn parse_union_body(
                 "Union arm declaraion wasn't finished".to_string(),
                 t,
             ),
-        }).and_then(|next| match next {
-            false => Ok(false),
+        }).map(|next| match next {
+            false => false,
             true => match handle.tokens.next() {
-                None => Ok(false),
+                None => false,
                 Some(token::Token::Bracket(token::Bracket::RightCurly)) => {
                     handle.tokens.push_back(token::Token::Bracket(token::Bracket::RightCurly));
-                    Ok(false)
+                    false
                 },
                 Some(t) => {
                     handle.tokens.push_back(t);
-                    Ok(true)
+                    true
                 },
             }
         })
@@ -747,7 +1364,7 @@ fn parse_union_body(
 }
 
 fn parse_program_definition(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(rpc::Value, rpc::Program)> {
     match handle.tokens.next() {                                   // Identifier
         None => Error::unexpected_eof("Program identifier expected".to_owned()),
@@ -773,7 +1390,9 @@ fn parse_program_definition(
         None => Error::unexpected_eof("Number not assigned to program".to_owned()),
         Some(token::Token::Operator(token::Operator::Assign)) => Ok(pass),
         Some(t) => Error::unexpected_token("Number not assigned to program".to_owned(), t),
-    }).and_then(|pr| parse_value(handle).map(|v| (v, pr)))         // Value
+    }).and_then(|pr| parse_number(handle, "program number")        // Value
+        .map(|v| (v, pr))
+    )
     .and_then(|(v, pr)| match handle.assigned_numbers.get(&v) {
         Some(_) => Error::program_number_reassigned(v),
         None => Ok((v, pr)),
@@ -789,7 +1408,7 @@ fn parse_program_definition(
 }
 
 fn parse_program_versions(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Program> {
     let mut out = rpc::new_program();
     let mut error: Option<Error> = None;
@@ -837,8 +1456,10 @@ fn parse_program_versions(
 }
 
 fn parse_version(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(rpc::Value, rpc::Version)> {
+    let doc = handle.tokens.doc();
+
     match handle.tokens.next() {                                      // Version
         None => Error::unexpected_eof("Version identifier expected".to_owned()),
         Some(token::Token::Keyword(token::Keyword::Version)) => Ok(()),
@@ -853,6 +1474,7 @@ fn parse_version(
         Some(t) => Error::unexpected_token("Expected version body \"{\"".to_owned(), t),
     }).and_then(|id| parse_version_procedures(handle).map(|mut ver| { // Body
         ver.name = id;
+        ver.doc = doc;
         ver
     })).and_then(|pass| match handle.tokens.next() {                  // }
         None => Error::unexpected_eof("Version body not closed".to_owned()),
@@ -862,7 +1484,9 @@ fn parse_version(
         None => Error::unexpected_eof("Number not assigned to version".to_owned()),
         Some(token::Token::Operator(token::Operator::Assign)) => Ok(pass),
         Some(t) => Error::unexpected_token("Number not assigned to version".to_owned(), t),
-    }).and_then(|ver| parse_value(handle).map(|v| (v, ver)))          // Value
+    }).and_then(|ver| parse_number(handle, "version number")          // Value
+        .map(|v| (v, ver))
+    )
     .and_then(|pass| match handle.tokens.next() {                     // ;
         None => Error::unexpected_eof("Version definition not closed".to_owned()),
         Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
@@ -871,7 +1495,7 @@ fn parse_version(
 }
 
 fn parse_version_procedures(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Version> {
     let mut out = rpc::new_version();
     let mut error: Option<Error> = None;
@@ -919,8 +1543,10 @@ fn parse_version_procedures(
 }
 
 fn parse_procedure(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<(rpc::Value, rpc::Procedure)> {
+    let doc = handle.tokens.doc();
+
     parse_type(handle).and_then(|tp| match handle.tokens.next() {        // Type + Identifier
         None => Error::unexpected_eof("Procedure identifier expected".to_owned()),
         Some(token::Token::Identifier(id)) => Ok((tp, id)),
@@ -931,6 +1557,7 @@ fn parse_procedure(
         Some(t) => Error::unexpected_token("Expected procedure body \"{\"".to_owned(), t),
     }).and_then(|(tp, id)| parse_procedure_args(handle).map(|mut proc| { // Args
         proc.name = id;
+        proc.doc = doc;
         proc.return_type = tp;
         proc
     })).and_then(|pass| match handle.tokens.next() {                     // )
@@ -941,7 +1568,9 @@ fn parse_procedure(
         None => Error::unexpected_eof("Number not assigned to procedure".to_owned()),
         Some(token::Token::Operator(token::Operator::Assign)) => Ok(pass),
         Some(t) => Error::unexpected_token("Number not assigned to procedure".to_owned(), t),
-    }).and_then(|proc| parse_value(handle).map(|v| (v, proc)))           // Value
+    }).and_then(|proc| parse_number(handle, "procedure number")          // Value
+        .map(|v| (v, proc))
+    )
     .and_then(|pass| match handle.tokens.next() {                        // ;
         None => Error::unexpected_eof("Procedure definition not closed".to_owned()),
         Some(token::Token::Separator(token::Separator::Semicolon)) => Ok(pass),
@@ -950,7 +1579,7 @@ fn parse_procedure(
 }
 
 fn parse_procedure_args(
-    handle: &mut Handle<impl Iterator<Item=token::Token>>,
+    handle: &mut Handle<impl Iterator<Item=token::SpannedToken>>,
 ) -> Result<rpc::Procedure> {
     let mut out = rpc::new_procedure();
     let mut error: Option<Error> = None;
@@ -986,45 +1615,65 @@ fn parse_procedure_args(
 impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.span {
+            Some(span) if span.is_known() => write!(f, "{span}: {}", self.kind),
+            _ => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::UnknownDefinition(token) => write!(f,
+            ErrorKind::UnknownDefinition(token) => write!(f,
                 "Expected definition, got: {token:?}"
             ),
-            Error::UnexpectedToken(msg, token) => write!(f,
+            ErrorKind::UnexpectedToken(msg, token) => write!(f,
                 "Unexpected token: {token:?}\n{msg}"
             ),
-            Error::ExpressionNotClosed(msg, token) => write!(f,
+            ErrorKind::ExpressionNotClosed(msg, token) => write!(f,
                 "Expression not closed, got token: {token:?}\n{msg}"
             ),
-            Error::UnexpectedEOF(msg) => write!(f, "Unexpected EOF\n{msg}"),
-            Error::UndefinedType(msg) => write!(f, "Undefined type\n{msg}"),
-            Error::UndefinedValue(msg) => write!(f, "Undefined value: {msg}"),
-            Error::NonPositiveArraySize(size) => write!(f,
+            ErrorKind::UnexpectedEOF(msg) => write!(f, "Unexpected EOF\n{msg}"),
+            ErrorKind::UndefinedType(msg) => write!(f, "Undefined type\n{msg}"),
+            ErrorKind::UndefinedValue(msg) => write!(f, "Undefined value: {msg}"),
+            ErrorKind::NonPositiveArraySize(size) => write!(f,
                 "Array size must be greater than 0, got {size}"
             ),
-            Error::TypeRedefined(msg) => write!(f, "Type redefined\n{msg}"),
-            Error::IdentifierRedefined(msg) => write!(f, "Identifier redefined\n{msg}"),
-            Error::StructureFieldRedefined(msg) => write!(f, "Structure field redefined\n{msg}"),
-            Error::NotSwitchingType(tp) => write!(f,
+            ErrorKind::TypeRedefined(msg) => write!(f, "Type redefined\n{msg}"),
+            ErrorKind::IdentifierRedefined(msg) => write!(f, "Identifier redefined\n{msg}"),
+            ErrorKind::StructureFieldRedefined(msg) => write!(f, "Structure field redefined\n{msg}"),
+            ErrorKind::NotSwitchingType(tp) => write!(f,
                 "Only integer type can be used for union identifier, got {tp:?}"
             ),
-            Error::UnionArmRedefined(arm) => match arm {
+            ErrorKind::UnionArmRedefined(arm) => match arm {
                 UnionArm::Regular(v) => write!(f, "Union arm for value {v:?} redefined"),
                 UnionArm::Default => write!(f, "Default union arm redefined"),
             },
-            Error::UseOfPendingType(msg) => write!(f,
+            ErrorKind::UseOfPendingType(msg) => write!(f,
                 "Use of type being defined\n{msg}"
             ),
-            Error::ProgramNumberReassigned(value) => write!(f,
+            ErrorKind::ProgramNumberReassigned(value) => write!(f,
                 "Program with number {value:?} redefined"
             ),
-            Error::VersionNumberReassigned(value) => write!(f,
+            ErrorKind::VersionNumberReassigned(value) => write!(f,
                 "Version with number {value:?} redefined"
             ),
-            Error::ProcedureNumberReassigned(value) => write!(f,
+            ErrorKind::ProcedureNumberReassigned(value) => write!(f,
                 "Procedure with number {value:?} redefined"
             ),
+            ErrorKind::ArithmeticOverflow(msg) => write!(f,
+                "Constant fits neither hyper nor unsigned hyper: {msg}"
+            ),
+            ErrorKind::DivisionByZero(msg) => write!(f,
+                "Division by zero in constant expression: {msg}"
+            ),
+            ErrorKind::ValueOutOfRange(value, msg) => write!(f,
+                "Value {value} is out of range for {msg}"
+            ),
+            ErrorKind::AmbiguousType(msg) => write!(f, "Ambiguous type\n{msg}"),
+            ErrorKind::RecursiveValue(msg) => write!(f, "Value depends on itself: {msg}"),
         }
     }
 }
@@ -0,0 +1,546 @@
+use std::collections::{HashMap, HashSet};
+
+use rpc::{self, token};
+
+use crate::{Error, Result, DefinedTypes, References, PendingValue};
+
+/// Check names used by definitions, fold values with constants and sort
+/// definitions, so that types go after the ones they contain. Program,
+/// version and procedure numbers and all expressions are replaced with their
+/// values.
+pub(crate) fn resolve(
+    module: &mut rpc::Module,
+    spans: Vec<Option<token::Span>>,
+    types: &DefinedTypes,
+    references: References,
+) -> Vec<Error> {
+    let mut errors = Vec::new();
+    let mut bare = HashMap::new();
+
+    references.types.into_iter().for_each(|(named, span)| match resolve_type(types, &named) {
+        Ok(resolved) => if let rpc::NamedType::Typedef(id) = named {
+            bare.insert(id, resolved);
+        },
+        Err(error) => errors.push(error.at(span.as_ref())),
+    });
+
+    module.definitions.iter_mut().for_each(|def| definition_types_mut(def).into_iter()
+        .for_each(|tp| rename(tp, &bare))
+    );
+
+    let mut values = Values::new(&module.definitions, &references.values);
+
+    references.values.into_iter().for_each(|PendingValue { name, value, condition, span }| {
+        match name {
+            Some(name) => values.value(&name),
+            None => values.fold(&value),
+        }.and_then(|num| match num {
+            Some(num) => condition(num).map(|_| ()),
+            None => Ok(()),
+        }).unwrap_or_else(|error| errors.push(error.at(span.as_ref())));
+    });
+
+    let typedefs = module.definitions.iter().filter_map(|def| match def {
+        rpc::Definition::Typedef(id, typedef) => Some((id.clone(), typedef.tp.clone())),
+        _ => None,
+    }).collect::<HashMap<_, _>>();
+
+    module.definitions.iter_mut().zip(&spans).for_each(|(def, span)| match def {
+        rpc::Definition::Union(_, un) => resolve_switch(types, &typedefs, &mut values, un).err().into_iter().collect(),
+        rpc::Definition::Program(number, progr) => fold_numbers(&mut values, number, progr),
+        _ => Vec::new(),
+    }.into_iter().for_each(|error| errors.push(error.at(span.as_ref()))));
+
+    module.definitions.iter_mut().for_each(|def| {
+        definition_values_mut(def).into_iter().for_each(|value| fold_compound(&mut values, value));
+        definition_types_mut(def).into_iter().for_each(|tp| fold_sizes(&mut values, tp));
+    });
+
+    let items = values.items;
+
+    errors.extend(values.errors);
+    errors.extend(sort(module, spans, &items));
+    errors
+}
+
+fn resolve_type(types: &DefinedTypes, named: &rpc::NamedType) -> Result<rpc::NamedType> {
+    let (defined, kind, id) = match named {
+        rpc::NamedType::Typedef(id) => return resolve_bare(types, id),
+        rpc::NamedType::Struct(id) => (&types.structs, "struct", id),
+        rpc::NamedType::Union(id) => (&types.unions, "union", id),
+        rpc::NamedType::Enum(id) => (&types.enums, "enum", id),
+    };
+
+    match defined.contains(id) {
+        true => Ok(named.clone()),
+        false => Error::undefined_type_hint(
+            format!{"Unknown {kind} with identifier {id}"},
+            types.suggest(id),
+        ),
+    }
+}
+
+/// Resolve type referenced without `struct`, `union` or `enum` keyword
+fn resolve_bare(types: &DefinedTypes, id: &String) -> Result<rpc::NamedType> {
+    let mut found = [
+        (&types.typedefs, "", rpc::NamedType::Typedef as fn(String) -> rpc::NamedType),
+        (&types.structs, "struct ", rpc::NamedType::Struct),
+        (&types.unions, "union ", rpc::NamedType::Union),
+        (&types.enums, "enum ", rpc::NamedType::Enum),
+    ].into_iter().filter(|(defined, _, _)| defined.contains(id)).collect::<Vec<_>>();
+
+    match found.len() {
+        0 => Error::undefined_type_hint(
+            format!{"Unknown type identifier {id}"},
+            types.suggest(id),
+        ),
+        1 => Ok((found.remove(0).2)(id.clone())),
+        _ => Error::ambiguous_type(
+            format!("Type identifier {id} refers to several types"),
+            Some(format!("use one of {}", found.iter()
+                .map(|(_, kind, _)| format!("`{kind}{id}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+            )),
+        ),
+    }
+}
+
+fn rename(tp: &mut rpc::Type, bare: &HashMap<String, rpc::NamedType>) {
+    match tp {
+        rpc::Type::Pointer(tp) | rpc::Type::Array(tp, _) | rpc::Type::VArray(tp, _) => rename(tp, bare),
+        rpc::Type::Named(named @ rpc::NamedType::Typedef(_)) => if let rpc::NamedType::Typedef(id) = named {
+            if let Some(resolved) = bare.get(id) {
+                *named = resolved.clone();
+            }
+        },
+        _ => {},
+    }
+}
+
+fn definition_types_mut(def: &mut rpc::Definition) -> Vec<&mut rpc::Type> {
+    match def {
+        rpc::Definition::Typedef(_, typedef) => vec![&mut typedef.tp],
+        rpc::Definition::Struct(_, st) => st.fields.values_mut().collect(),
+        rpc::Definition::Union(_, un) => un.arms.iter_mut()
+            .chain(un.default.as_mut())
+            .filter_map(|arm| arm.declaration.as_mut().map(|(_, tp)| tp))
+            .collect(),
+        rpc::Definition::Program(_, progr) => progr.versions.values_mut()
+            .flat_map(|ver| ver.procedures.values_mut())
+            .flat_map(|proc| std::iter::once(&mut proc.return_type).chain(proc.arguments.iter_mut()))
+            .collect(),
+        rpc::Definition::Const(_, _) | rpc::Definition::Enum(_, _)
+            | rpc::Definition::Passthrough(_) => Vec::new(),
+    }
+}
+
+/// Values of constants, enum items and union cases
+fn definition_values_mut(def: &mut rpc::Definition) -> Vec<&mut rpc::Value> {
+    match def {
+        rpc::Definition::Const(_, c) => vec![&mut c.value],
+        rpc::Definition::Enum(_, en) => en.items.iter_mut().filter_map(|(_, value)| value.as_mut()).collect(),
+        rpc::Definition::Union(_, un) => un.arms.iter_mut().flat_map(|arm| arm.cases.iter_mut()).collect(),
+        rpc::Definition::Typedef(_, _) | rpc::Definition::Struct(_, _) | rpc::Definition::Program(_, _)
+            | rpc::Definition::Passthrough(_) => Vec::new(),
+    }
+}
+
+/// Replace expression with its folded number, so that generated C doesn't
+/// evaluate it in `int`. Plain names of constants and enum items are kept.
+fn fold_compound(values: &mut Values, value: &mut rpc::Value) {
+    if let rpc::Value::Unary(_, _) | rpc::Value::Binary(_, _, _) = value {
+        *value = values.number(value.clone());
+    }
+}
+
+fn fold_sizes(values: &mut Values, tp: &mut rpc::Type) {
+    match tp {
+        rpc::Type::Pointer(tp) | rpc::Type::VArray(tp, None) => fold_sizes(values, tp),
+        rpc::Type::Array(tp, size) | rpc::Type::VArray(tp, Some(size)) => {
+            fold_compound(values, size);
+            fold_sizes(values, tp);
+        },
+        _ => {},
+    }
+}
+
+enum State {
+    Folding,
+    Folded(i128),
+    /// Error is already reported
+    Broken,
+}
+
+/// Constants and enum items folded on demand
+struct Values {
+    sources: HashMap<String, rpc::Value>,
+    /// Enums by their items
+    items: HashMap<String, String>,
+    /// Where values which folding may fail are defined
+    spans: HashMap<String, Option<token::Span>>,
+    states: HashMap<String, State>,
+    errors: Vec<Error>,
+}
+
+impl Values {
+    fn new(definitions: &[rpc::Definition], pending: &[PendingValue]) -> Self {
+        let mut out = Self {
+            sources: HashMap::new(),
+            items: HashMap::new(),
+            spans: pending.iter()
+                .filter_map(|value| value.name.clone().map(|name| (name, value.span.clone())))
+                .collect(),
+            states: HashMap::new(),
+            errors: Vec::new(),
+        };
+
+        definitions.iter().for_each(|def| out.read_definition(def));
+        out
+    }
+
+    fn read_definition(self: &mut Self, def: &rpc::Definition) {
+        match def {
+            rpc::Definition::Const(id, c) => {
+                self.sources.insert(id.clone(), c.value.clone());
+            },
+            rpc::Definition::Enum(name, en) => {
+                let mut next = rpc::Value::Number(0);
+
+                en.items.iter().for_each(|(id, value)| {
+                    self.sources.insert(id.clone(), value.clone().unwrap_or(next.clone()));
+                    self.items.insert(id.clone(), name.clone());
+                    next = rpc::Value::Binary(
+                        rpc::BinaryOperator::Add,
+                        Box::new(rpc::Value::Identifier(id.clone())),
+                        Box::new(rpc::Value::Number(1)),
+                    );
+                });
+            },
+            _ => {},
+        }
+    }
+
+    /// Value of constant or enum item, `None` if it is broken. Errors in its
+    /// definition are reported once where it is defined.
+    fn value(self: &mut Self, id: &String) -> Result<Option<i128>> {
+        match self.states.get(id) {
+            Some(State::Folded(num)) => return Ok(Some(*num)),
+            Some(State::Broken) => return Ok(None),
+            Some(State::Folding) => return Error::recursive_value(id.clone()),
+            None => {},
+        }
+
+        let value = match self.sources.get(id) {
+            Some(value) => value.clone(),
+            None => return Error::undefined_value(id.clone()),
+        };
+
+        self.states.insert(id.clone(), State::Folding);
+
+        let (state, num) = match self.fold(&value) {
+            Ok(Some(num)) => (State::Folded(num), Some(num)),
+            Ok(None) => (State::Broken, None),
+            Err(error) => {
+                self.errors.push(error.at(self.spans.get(id).and_then(Option::as_ref)));
+                (State::Broken, None)
+            },
+        };
+
+        self.states.insert(id.clone(), state);
+        Ok(num)
+    }
+
+    /// Folded value, broken one is kept as is, its error is already reported
+    fn number(self: &mut Self, value: rpc::Value) -> rpc::Value {
+        match self.fold(&value) {
+            Ok(Some(num)) => rpc::Value::Number(num),
+            _ => value,
+        }
+    }
+
+    fn fold(self: &mut Self, value: &rpc::Value) -> Result<Option<i128>> {
+        Ok(match value {
+            rpc::Value::Number(num) => Some(*num),
+            rpc::Value::Identifier(id) => self.value(id)?,
+            rpc::Value::Unary(op, value) => match self.fold(value)? {
+                Some(num) => Some(crate::fold_unary(*op, num)?),
+                None => None,
+            },
+            rpc::Value::Binary(op, left, right) => match (self.fold(left)?, self.fold(right)?) {
+                (Some(num), Some(rnum)) => Some(crate::fold_binary(*op, num, rnum)?),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Replace bare name of union discriminant, which parser keeps as enum, with
+/// the type it names through typedefs and check cases against that type
+fn resolve_switch(
+    types: &DefinedTypes,
+    typedefs: &HashMap<String, rpc::Type>,
+    values: &mut Values,
+    un: &mut rpc::Union,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    let mut tp = match &un.switch_type {
+        rpc::SwitchingType::Enum(id) if !types.enums.contains(id) => match typedefs.get(id) {
+            Some(tp) => tp,
+            // Undefined type is reported with the other references
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    un.switch_type = loop {
+        match tp {
+            rpc::Type::Integer(integer) => break rpc::SwitchingType::Integer(*integer),
+            rpc::Type::Unsigned(integer) => break rpc::SwitchingType::Unsigned(*integer),
+            rpc::Type::Named(rpc::NamedType::Enum(en)) => break rpc::SwitchingType::Enum(en.clone()),
+            // Typedefs naming each other are reported by sort
+            rpc::Type::Named(rpc::NamedType::Typedef(id)) => match (seen.insert(id), typedefs.get(id)) {
+                (true, Some(next)) => tp = next,
+                _ => return Ok(()),
+            },
+            tp => return Error::not_switching_type(tp.clone()),
+        }
+    };
+
+    let range = un.switch_type.range();
+
+    un.arms.iter().flat_map(|arm| arm.cases.iter()).try_for_each(|case| match values.fold(case) {
+        Ok(Some(num)) => crate::in_range(range.clone(), "union discriminant")(num).map(|_| ()),
+        // Broken value is already reported
+        _ => Ok(()),
+    })
+}
+
+/// Replace program, version and procedure numbers with their values, numbers
+/// which became equal once folded are reported and only the first one is kept
+fn fold_numbers(values: &mut Values, number: &mut rpc::Value, progr: &mut rpc::Program) -> Vec<Error> {
+    let mut errors = Vec::new();
+
+    *number = values.number(number.clone());
+
+    std::mem::take(&mut progr.versions).into_iter().for_each(|(v, mut ver)| {
+        std::mem::take(&mut ver.procedures).into_iter().for_each(|(p, proc)| match values.number(p) {
+            p if ver.procedures.contains_key(&p) => errors.extend(Error::procedure_number_reassigned::<()>(p).err()),
+            p => {
+                ver.procedures.insert(p, proc);
+            },
+        });
+
+        match values.number(v) {
+            v if progr.versions.contains_key(&v) => errors.extend(Error::version_number_reassigned::<()>(v).err()),
+            v => {
+                progr.versions.insert(v, ver);
+            },
+        }
+    });
+
+    errors
+}
+
+/// Types which have to be defined before the ones using `tp`. Behind
+/// pointers and in variable arrays forward declaration of struct or union
+/// is enough, but typedef has to go before.
+fn dependencies(
+    tp: &rpc::Type,
+    weak: bool,
+    typedefs: &HashMap<&String, &rpc::Type>,
+    out: &mut Vec<rpc::NamedType>,
+) {
+    match tp {
+        rpc::Type::Pointer(tp) | rpc::Type::VArray(tp, _) => dependencies(tp, true, typedefs, out),
+        rpc::Type::Array(tp, _) => dependencies(tp, weak, typedefs, out),
+        rpc::Type::Named(named) => {
+            match named {
+                rpc::NamedType::Struct(_) | rpc::NamedType::Union(_) if weak => {},
+                _ => out.push(named.clone()),
+            }
+
+            // Alias is declared with forward declaration, but its use needs
+            // the whole type
+            if let (rpc::NamedType::Typedef(id), false) = (named, weak) {
+                if let Some(tp @ rpc::Type::Named(rpc::NamedType::Struct(_) | rpc::NamedType::Union(_))) =
+                    typedefs.get(id) {
+                    dependencies(tp, false, typedefs, out);
+                }
+            }
+        },
+        _ => {},
+    }
+}
+
+fn describe(named: &rpc::NamedType) -> String {
+    match named {
+        rpc::NamedType::Typedef(id) => id.clone(),
+        rpc::NamedType::Struct(id) => format!("struct {id}"),
+        rpc::NamedType::Union(id) => format!("union {id}"),
+        rpc::NamedType::Enum(id) => format!("enum {id}"),
+    }
+}
+
+fn identifiers<'a>(value: &'a rpc::Value, out: &mut Vec<&'a String>) {
+    match value {
+        rpc::Value::Number(_) => {},
+        rpc::Value::Identifier(id) => out.push(id),
+        rpc::Value::Unary(_, value) => identifiers(value, out),
+        rpc::Value::Binary(_, left, right) => {
+            identifiers(left, out);
+            identifiers(right, out);
+        },
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    New,
+    Visiting,
+    Done,
+}
+
+struct Sorter {
+    dependencies: Vec<Vec<usize>>,
+    marks: Vec<Mark>,
+    path: Vec<usize>,
+    keys: Vec<Option<rpc::NamedType>>,
+    spans: Vec<Option<token::Span>>,
+    order: Vec<usize>,
+    errors: Vec<Error>,
+}
+
+impl Sorter {
+    fn visit(self: &mut Self, i: usize) {
+        match self.marks[i] {
+            Mark::Done => return,
+            Mark::Visiting => {
+                let start = self.path.iter().position(|j| *j == i).expect("Is being visited");
+                let last = *self.path.last().expect("Is being visited");
+                let cycle = self.path[start..].iter()
+                    .chain([&i])
+                    .filter_map(|j| self.keys[*j].as_ref().map(describe))
+                    .collect::<Vec<_>>();
+
+                return Error::use_of_pending_type(format!(
+                    "{} contains itself: {}", cycle[0], cycle.join(" -> "),
+                )).unwrap_or_else(|error| self.errors.push(error.at(self.spans[last].as_ref())));
+            },
+            Mark::New => {},
+        }
+
+        self.marks[i] = Mark::Visiting;
+        self.path.push(i);
+        (0..self.dependencies[i].len()).for_each(|d| self.visit(self.dependencies[i][d]));
+        self.path.pop();
+        self.marks[i] = Mark::Done;
+        self.order.push(i);
+    }
+}
+
+/// Stable topological sort, definitions are moved only to go after types
+/// they contain by value and enums which items they use. `%` lines aren't
+/// sorted, they stay right before the definition following them.
+fn sort(
+    module: &mut rpc::Module,
+    spans: Vec<Option<token::Span>>,
+    items: &HashMap<String, String>,
+) -> Vec<Error> {
+    let mut lines = Vec::new();
+    let mut leading = Vec::new();
+    let (definitions, spans): (Vec<_>, Vec<_>) = std::mem::take(&mut module.definitions).into_iter()
+        .zip(spans)
+        .filter_map(|(def, span)| match def {
+            rpc::Definition::Passthrough(_) => {
+                lines.push(def);
+                None
+            },
+            def => {
+                leading.push(std::mem::take(&mut lines));
+                Some((def, span))
+            },
+        })
+        .unzip();
+
+    module.definitions = definitions;
+
+    let keys = module.definitions.iter().map(|def| match def {
+        rpc::Definition::Typedef(id, _) => Some(rpc::NamedType::Typedef(id.clone())),
+        rpc::Definition::Struct(id, _) => Some(rpc::NamedType::Struct(id.clone())),
+        rpc::Definition::Union(id, _) => Some(rpc::NamedType::Union(id.clone())),
+        rpc::Definition::Enum(id, _) => Some(rpc::NamedType::Enum(id.clone())),
+        _ => None,
+    }).collect::<Vec<_>>();
+    let indices = keys.iter()
+        .enumerate()
+        .filter_map(|(i, key)| key.as_ref().map(|key| (key, i)))
+        .collect::<HashMap<_, _>>();
+    let typedefs = module.definitions.iter().filter_map(|def| match def {
+        rpc::Definition::Typedef(id, typedef) => Some((id, &typedef.tp)),
+        _ => None,
+    }).collect::<HashMap<_, _>>();
+
+    let dependencies = module.definitions.iter().map(|def| {
+        let mut named = Vec::new();
+
+        match def {
+            rpc::Definition::Typedef(_, typedef) => match &typedef.tp {
+                tp @ rpc::Type::Named(rpc::NamedType::Struct(_) | rpc::NamedType::Union(_)) =>
+                    dependencies(tp, true, &typedefs, &mut named),
+                tp => dependencies(tp, false, &typedefs, &mut named),
+            },
+            rpc::Definition::Struct(_, st) => st.fields.values()
+                .for_each(|tp| dependencies(tp, false, &typedefs, &mut named)),
+            rpc::Definition::Union(_, un) => {
+                if let rpc::SwitchingType::Enum(en) = &un.switch_type {
+                    named.push(rpc::NamedType::Enum(en.clone()));
+                }
+                un.declarations().for_each(|(_, tp)| dependencies(tp, false, &typedefs, &mut named));
+            },
+            rpc::Definition::Enum(name, en) => {
+                let mut ids = Vec::new();
+
+                en.items.iter().filter_map(|(_, value)| value.as_ref()).for_each(|value| identifiers(value, &mut ids));
+                named.extend(ids.into_iter()
+                    .filter_map(|id| items.get(id))
+                    .filter(|en| *en != name)
+                    .map(|en| rpc::NamedType::Enum(en.clone()))
+                );
+            },
+            _ => {},
+        }
+
+        let mut seen = HashSet::new();
+
+        named.iter()
+            .filter_map(|named| indices.get(named).copied())
+            .filter(|i| seen.insert(*i))
+            .collect()
+    }).collect();
+
+    let mut sorter = Sorter {
+        dependencies,
+        marks: vec![Mark::New; keys.len()],
+        path: Vec::new(),
+        keys,
+        spans,
+        order: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    (0..sorter.marks.len()).for_each(|i| sorter.visit(i));
+
+    let mut definitions = std::mem::take(&mut module.definitions).into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+
+    module.definitions = sorter.order.iter()
+        .flat_map(|i| std::mem::take(&mut leading[*i]).into_iter()
+            .chain([definitions[*i].take().expect("Sorted once")])
+        )
+        .chain(lines)
+        .collect();
+
+    sorter.errors
+}
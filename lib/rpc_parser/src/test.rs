@@ -90,7 +90,7 @@ macro_rules! check_struct {
         {
             let (name, st) = $struct;
             assert_eq!(stringify!{$name}, name.as_str());
-            assert_eq!($size, st.len());
+            assert_eq!($size, st.fields.len());
             check_struct_field!(st, $($defs)*);
         }
     };
@@ -100,7 +100,7 @@ macro_rules! check_struct_field {
     ( $struct: expr, ) => {};
     ( $struct: expr, $field: ident: $type: expr; $($rest: tt)* ) => {
         {
-            match $struct.get(stringify!{$field}) {
+            match $struct.fields.get(stringify!{$field}) {
                 None => panic!("No field \"{}\" found", stringify!{$field}),
                 Some(tp) => if *tp != $type {
                     panic!("type {:?} for field \"{}\" expected, got {tp:?}", $type, stringify!{$field});
@@ -166,7 +166,7 @@ fn ping() {
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("PING_VERS", id.as_str());
-            assert_eq!(&rpc::Value::Number(2), v);
+            assert_eq!(&rpc::Value::Number(2), &v.value);
         },
         _ => panic!("Const expected"),
     }
@@ -216,35 +216,35 @@ fn bakery() {
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("REGISTER", id.as_str());
-            assert_eq!(&rpc::Value::Number(0), v);
+            assert_eq!(&rpc::Value::Number(0), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("ACCESS", id.as_str());
-            assert_eq!(&rpc::Value::Number(1), v);
+            assert_eq!(&rpc::Value::Number(1), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("GET", id.as_str());
-            assert_eq!(&rpc::Value::Number(2), v);
+            assert_eq!(&rpc::Value::Number(2), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("STATUS", id.as_str());
-            assert_eq!(&rpc::Value::Number(3), v);
+            assert_eq!(&rpc::Value::Number(3), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("OP_MAX", id.as_str());
-            assert_eq!(&rpc::Value::Number(4), v);
+            assert_eq!(&rpc::Value::Number(4), &v.value);
         },
         _ => panic!("Const expected"),
     }
@@ -252,28 +252,28 @@ fn bakery() {
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("STATUS_FREE", id.as_str());
-            assert_eq!(&rpc::Value::Number(0), v);
+            assert_eq!(&rpc::Value::Number(0), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("STATUS_REGISTERED", id.as_str());
-            assert_eq!(&rpc::Value::Number(1), v);
+            assert_eq!(&rpc::Value::Number(1), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("STATUS_ACCESSING", id.as_str());
-            assert_eq!(&rpc::Value::Number(2), v);
+            assert_eq!(&rpc::Value::Number(2), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("STATUS_READY_FOR_CR", id.as_str());
-            assert_eq!(&rpc::Value::Number(3), v);
+            assert_eq!(&rpc::Value::Number(3), &v.value);
         },
         _ => panic!("Const expected"),
     }
@@ -281,35 +281,35 @@ fn bakery() {
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("ERROR_WRONG_ID_RPC", id.as_str());
-            assert_eq!(&rpc::Value::Number(-1), v);
+            assert_eq!(&rpc::Value::Number(-1), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("ERROR_INCOMPATIBLE_HANLE_RPC", id.as_str());
-            assert_eq!(&rpc::Value::Number(-2), v);
+            assert_eq!(&rpc::Value::Number(-2), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("ERROR_WRONG_STATUS_RPC", id.as_str());
-            assert_eq!(&rpc::Value::Number(-3), v);
+            assert_eq!(&rpc::Value::Number(-3), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("ERROR_REJECT_ACCESS_RPC", id.as_str());
-            assert_eq!(&rpc::Value::Number(-4), v);
+            assert_eq!(&rpc::Value::Number(-4), &v.value);
         },
         _ => panic!("Const expected"),
     }
     match defs.next() {
         Some(rpc::Definition::Const(id, v)) => {
             assert_eq!("ERROR_WRONG_OP_RPC", id.as_str());
-            assert_eq!(&rpc::Value::Number(-5), v);
+            assert_eq!(&rpc::Value::Number(-5), &v.value);
         },
         _ => panic!("Const expected"),
     }
@@ -332,7 +332,7 @@ fn bakery() {
     match defs.next() {
         Some(rpc::Definition::Typedef(id, tp)) => {
             assert_eq!("BAKERY", id.as_str());
-            assert_eq!(&rpc::Type::Named(rpc::NamedType::Struct(String::from("BAKERY"))), tp);
+            assert_eq!(&rpc::Type::Named(rpc::NamedType::Struct(String::from("BAKERY"))), &tp.tp);
         }
         _ => panic!("Typedef expected"),
     }
@@ -378,7 +378,7 @@ fn enum_test() {
                 ("B".to_string(), Some(rpc::Value::Number(3))),
                 ("C".to_string(), Some(rpc::Value::Identifier("A".to_string()))),
                 ("D".to_string(), None),
-            ], en);
+            ], &en.items);
         },
         _ => panic!("Enum expected"),
     }
@@ -389,3 +389,735 @@ fn enum_test() {
 }
 
 
+fn spanned(value: token::Token, line: usize, column: usize) -> token::SpannedToken {
+    token::Spanned::new(value, token::Span {
+        file: None,
+        offset: 0,
+        line,
+        column,
+        length: 1,
+    })
+}
+
+#[test]
+fn error_span() {
+    let err = parse([
+        spanned(token::Token::Type(token::Type::Struct), 1, 1),
+        spanned(token::Token::Identifier("A".to_string()), 1, 8),
+        spanned(token::Token::Bracket(token::Bracket::LeftCurly), 1, 10),
+        spanned(token::Token::Type(token::Type::Integer), 2, 5),
+        spanned(token::Token::Identifier("a".to_string()), 2, 9),
+        spanned(token::Token::Separator(token::Separator::Semicolon), 2, 10),
+        spanned(token::Token::Identifier("foo".to_string()), 3, 5),
+        spanned(token::Token::Identifier("b".to_string()), 3, 9),
+        spanned(token::Token::Separator(token::Separator::Semicolon), 3, 10),
+        spanned(token::Token::Bracket(token::Bracket::RightCurly), 4, 1),
+        spanned(token::Token::Separator(token::Separator::Semicolon), 4, 2),
+    ].into_iter()).unwrap_err();
+
+    assert!(matches!(err.kind, ErrorKind::UndefinedType(_)));
+    assert_eq!(Some((3, 5)), err.span.as_ref().map(|span| (span.line, span.column)));
+
+    let err = parse([
+        spanned(token::Token::Keyword(token::Keyword::Const), 1, 1),
+        spanned(token::Token::Identifier("A".to_string()), 1, 7),
+        spanned(token::Token::Literal(token::Literal::Integer(1)), 1, 9),
+    ].into_iter()).unwrap_err();
+
+    assert!(matches!(err.kind, ErrorKind::UnexpectedToken(_, token::Token::Literal(_))));
+    assert_eq!(Some((1, 9)), err.span.as_ref().map(|span| (span.line, span.column)));
+}
+
+#[test]
+fn undefined_type_hint() {
+    let err = parse([
+        token::Token::Type(token::Type::Struct),
+        token::Token::Identifier("foo".to_string()),
+        token::Token::Bracket(token::Bracket::LeftCurly),
+        token::Token::Type(token::Type::Integer),
+        token::Token::Identifier("a".to_string()),
+        token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Bracket(token::Bracket::RightCurly),
+        token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Keyword(token::Keyword::Typedef),
+        token::Token::Type(token::Type::Enum),
+        token::Token::Identifier("foo".to_string()),
+        token::Token::Identifier("bar".to_string()),
+        token::Token::Separator(token::Separator::Semicolon),
+    ].into_iter()).unwrap_err();
+
+    assert_eq!("E0005", err.kind.code());
+    assert_eq!(Some("did you mean `struct foo`?"), err.hint.as_deref());
+}
+
+fn broken_progr() -> [token::Token; 52] { [
+    token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("A".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Type(token::Type::Struct), token::Token::Identifier("S".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+        token::Token::Identifier("foo".to_string()), token::Token::Identifier("x".to_string()), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Bracket(token::Bracket::RightCurly), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("B".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(2)), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Keyword(token::Keyword::Typedef), token::Token::Type(token::Type::Struct), token::Token::Identifier("S".to_string()), token::Token::Identifier("s_t".to_string()), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Keyword(token::Keyword::Program), token::Token::Identifier("P".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+        token::Token::Keyword(token::Keyword::Version), token::Token::Identifier("V".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+            token::Token::Type(token::Type::Void), token::Token::Identifier("F".to_string()), token::Token::Bracket(token::Bracket::Left), token::Token::Type(token::Type::Void), token::Token::Bracket(token::Bracket::Right),
+            token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(1)),
+        token::Token::Bracket(token::Bracket::RightCurly),
+        token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(1)), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Bracket(token::Bracket::RightCurly),
+    token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(1)), token::Token::Separator(token::Separator::Semicolon),
+    token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("C".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(3)),
+    token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("D".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(4)), token::Token::Separator(token::Separator::Semicolon),
+] }
+
+#[test]
+fn recovery() {
+    assert!(matches!(
+        parse(broken_progr().into_iter()).unwrap_err().kind,
+        ErrorKind::UnexpectedToken(_, token::Token::Separator(token::Separator::Semicolon)),
+    ));
+
+    let (module, errors) = parse_recovering(broken_progr().into_iter());
+    let mut errors = errors.into_iter().map(|err| err.into_details().kind);
+
+    assert!(matches!(
+        errors.next(),
+        Some(ErrorKind::UnexpectedToken(_, token::Token::Separator(token::Separator::Semicolon))),
+    ));
+    assert!(matches!(
+        errors.next(),
+        Some(ErrorKind::UnexpectedToken(_, token::Token::Bracket(token::Bracket::RightCurly))),
+    ));
+    assert!(matches!(
+        errors.next(),
+        Some(ErrorKind::ExpressionNotClosed(_, token::Token::Keyword(token::Keyword::Const))),
+    ));
+    // Names are resolved after the whole input is parsed
+    assert!(matches!(errors.next(), Some(ErrorKind::UndefinedType(_))));
+    assert!(errors.next().is_none());
+
+    let mut defs = module.definitions.iter();
+
+    match defs.next() {
+        Some(rpc::Definition::Struct(id, _)) => assert_eq!("S", id.as_str()),
+        _ => panic!("Struct expected"),
+    }
+    match defs.next() {
+        Some(rpc::Definition::Const(id, v)) => {
+            assert_eq!("B", id.as_str());
+            assert_eq!(&rpc::Value::Number(2), &v.value);
+        },
+        _ => panic!("Const expected"),
+    }
+    match defs.next() {
+        Some(rpc::Definition::Typedef(id, _)) => assert_eq!("s_t", id.as_str()),
+        _ => panic!("Typedef expected"),
+    }
+    match defs.next() {
+        Some(rpc::Definition::Const(id, _)) => assert_eq!("D", id.as_str()),
+        _ => panic!("Const expected"),
+    }
+
+    assert!(defs.next().is_none(), "End expected");
+}
+
+#[test]
+fn passthrough() {
+    let tokens = vec![
+        token::Token::PassthroughStart, token::Token::Passthrough("#include <linux/types.h>".to_string()),
+        token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("A".to_string()), token::Token::Operator(token::Operator::Assign),
+        token::Token::PassthroughStart, token::Token::Passthrough("#define B 2".to_string()),
+        token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("C".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(3)), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::PassthroughStart,
+    ];
+
+    let (module, errors) = parse_recovering(tokens.into_iter());
+
+    assert_eq!(1, errors.len());
+
+    let lines: Vec<_> = module.definitions.iter().map(|def| match def {
+        rpc::Definition::Passthrough(line) => line.as_str(),
+        rpc::Definition::Const(id, _) => id.as_str(),
+        _ => panic!("Passthrough or const expected"),
+    }).collect();
+
+    assert_eq!(vec!["#include <linux/types.h>", "#define B 2", "C", ""], lines);
+
+    // struct a { b x; };
+    // %line
+    // struct b { int y; };
+    // %end
+    let tokens = vec![
+        token::Token::Type(token::Type::Struct), token::Token::Identifier("a".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+            token::Token::Identifier("b".to_string()), token::Token::Identifier("x".to_string()), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Bracket(token::Bracket::RightCurly), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::PassthroughStart, token::Token::Passthrough("line".to_string()),
+        token::Token::Type(token::Type::Struct), token::Token::Identifier("b".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+            token::Token::Type(token::Type::Integer), token::Token::Identifier("y".to_string()), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Bracket(token::Bracket::RightCurly), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::PassthroughStart, token::Token::Passthrough("end".to_string()),
+    ];
+
+    // Line goes along with the definition following it
+    let lines: Vec<_> = parse(tokens.into_iter()).unwrap().definitions.into_iter().map(|def| match def {
+        rpc::Definition::Passthrough(line) | rpc::Definition::Struct(line, _) => line,
+        _ => panic!("Passthrough or struct expected"),
+    }).collect();
+
+    assert_eq!(vec!["line", "b", "a", "end"], lines);
+}
+
+
+#[test]
+fn doc_comments() {
+    let tokens = vec![
+        token::Token::Comment("* Answer\n * to everything\n ".to_string()),
+        token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("ANSWER".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(42)), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Comment("/ Pair".to_string()),
+        token::Token::Comment("/ of ints".to_string()),
+        token::Token::Type(token::Type::Struct), token::Token::Identifier("pair".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+            token::Token::Comment("* Not attached ".to_string()),
+            token::Token::Type(token::Type::Integer), token::Token::Identifier("a".to_string()), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Bracket(token::Bracket::RightCurly), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Comment("// Banner".to_string()),
+        token::Token::Comment(" Plain".to_string()),
+        token::Token::Keyword(token::Keyword::Const), token::Token::Identifier("PLAIN".to_string()), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(1)), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Comment("* Service ".to_string()),
+        token::Token::Keyword(token::Keyword::Program), token::Token::Identifier("P".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+            token::Token::Comment("/ First version".to_string()),
+            token::Token::Keyword(token::Keyword::Version), token::Token::Identifier("V".to_string()), token::Token::Bracket(token::Bracket::LeftCurly),
+                token::Token::Comment("*\n * Ping\n ".to_string()),
+                token::Token::Type(token::Type::Void), token::Token::Identifier("PING".to_string()), token::Token::Bracket(token::Bracket::Left), token::Token::Type(token::Type::Void), token::Token::Bracket(token::Bracket::Right), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(0)), token::Token::Separator(token::Separator::Semicolon),
+                token::Token::Type(token::Type::Void), token::Token::Identifier("NOP".to_string()), token::Token::Bracket(token::Bracket::Left), token::Token::Type(token::Type::Void), token::Token::Bracket(token::Bracket::Right), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(1)), token::Token::Separator(token::Separator::Semicolon),
+            token::Token::Bracket(token::Bracket::RightCurly), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(1)), token::Token::Separator(token::Separator::Semicolon),
+        token::Token::Bracket(token::Bracket::RightCurly), token::Token::Operator(token::Operator::Assign), token::Token::Literal(token::Literal::Integer(100)), token::Token::Separator(token::Separator::Semicolon),
+    ];
+
+    let module = parse(tokens.into_iter()).unwrap();
+    let mut defs = module.definitions.iter();
+
+    match defs.next() {
+        Some(rpc::Definition::Const(id, c)) => {
+            assert_eq!("ANSWER", id.as_str());
+            assert_eq!(Some("Answer\nto everything"), c.doc.as_deref());
+        },
+        _ => panic!("Documented const expected"),
+    }
+    match defs.next() {
+        Some(rpc::Definition::Struct(id, st)) => {
+            assert_eq!("pair", id.as_str());
+            assert_eq!(Some("Pair\nof ints"), st.doc.as_deref());
+        },
+        _ => panic!("Documented struct expected"),
+    }
+    match defs.next() {
+        Some(rpc::Definition::Const(id, c)) => {
+            assert_eq!("PLAIN", id.as_str());
+            assert!(c.doc.is_none());
+        },
+        _ => panic!("Undocumented const expected"),
+    }
+    match defs.next() {
+        Some(rpc::Definition::Program(_, progr)) => {
+            assert_eq!(Some("Service"), progr.doc.as_deref());
+
+            let version = progr.versions.get(&rpc::Value::Number(1)).unwrap();
+            assert_eq!(Some("First version"), version.doc.as_deref());
+            assert_eq!(
+                vec![Some("Ping"), None],
+                version.procedures.values().map(|proc| proc.doc.as_deref()).collect::<Vec<_>>()
+            );
+        },
+        _ => panic!("Documented program expected"),
+    }
+    assert!(defs.next().is_none());
+}
+
+#[test]
+fn expressions() {
+    use token::{Token, Operator, Literal, Bracket, Separator, Keyword};
+
+    let num = |n| Token::Literal(Literal::Integer(n));
+    let id = |s: &str| Token::Identifier(s.to_string());
+
+    // const BASE = 4; enum e { X = BASE, Y }; const MAX = (BASE + Y) * ~-3 -1 << 1 | 1;
+    let tokens = [
+        Token::Keyword(Keyword::Const), id("BASE"), Token::Operator(Operator::Assign), num(4), Token::Separator(Separator::Semicolon),
+        Token::Type(token::Type::Enum), id("e"), Token::Bracket(Bracket::LeftCurly),
+            id("X"), Token::Operator(Operator::Assign), id("BASE"), Token::Separator(Separator::Comma), id("Y"),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+        Token::Keyword(Keyword::Const), id("MAX"), Token::Operator(Operator::Assign),
+            Token::Bracket(Bracket::Left), id("BASE"), Token::Operator(Operator::Add), id("Y"), Token::Bracket(Bracket::Right),
+            Token::Type(token::Type::Pointer), Token::Operator(Operator::Not), Token::Operator(Operator::Subtract), num(3),
+            num(-1), Token::Operator(Operator::ShiftLeft), num(1), Token::Operator(Operator::Or), num(1),
+        Token::Separator(Separator::Semicolon),
+        Token::Keyword(Keyword::Typedef), Token::Type(token::Type::Opaque), id("buf"),
+            Token::Bracket(Bracket::LeftSquare), id("MAX"), Token::Operator(Operator::Divide), num(2), Token::Bracket(Bracket::RightSquare),
+        Token::Separator(Separator::Semicolon),
+    ];
+
+    let module = parse(tokens.into_iter()).unwrap();
+
+    // Expressions are folded, other precedences give other numbers:
+    // ((4 + 5) * ~-3 - 1) << 1 | 1 == 35
+    match &module.definitions[2] {
+        rpc::Definition::Const(id, v) => {
+            assert_eq!("MAX", id);
+            assert_eq!(rpc::Value::Number(35), v.value);
+        },
+        _ => panic!("Const expected"),
+    }
+
+    match &module.definitions[3] {
+        rpc::Definition::Typedef(_, rpc::Typedef { tp: rpc::Type::Array(_, v), .. }) => {
+            assert_eq!(&rpc::Value::Number(17), v)
+        },
+        _ => panic!("Array typedef expected"),
+    }
+
+    match &module.definitions[1] {
+        rpc::Definition::Enum(_, en) => assert_eq!(
+            vec![Some(rpc::Value::Identifier("BASE".to_string())), None],
+            en.items.iter().map(|(_, v)| v.clone()).collect::<Vec<_>>()
+        ),
+        _ => panic!("Enum expected"),
+    }
+}
+
+#[test]
+fn expression_errors() {
+    use token::{Token, Operator, Literal, Separator, Keyword};
+
+    let code = |tokens: Vec<token::SpannedToken>| {
+        let err = parse(tokens.into_iter()).unwrap_err();
+        (err.kind.code(), err.span.as_ref().map(|span| (span.line, span.column)))
+    };
+
+    assert_eq!(("E0018", Some((1, 13))), code(vec![
+        spanned(Token::Keyword(Keyword::Const), 1, 1),
+        spanned(Token::Identifier("A".to_string()), 1, 7),
+        spanned(Token::Operator(Operator::Assign), 1, 9),
+        spanned(Token::Literal(Literal::Integer(1)), 1, 11),
+        spanned(Token::Operator(Operator::Remainder), 1, 13),
+        spanned(Token::Literal(Literal::Integer(0)), 1, 15),
+        spanned(Token::Separator(Separator::Semicolon), 1, 16),
+    ]));
+    assert_eq!(("E0017", Some((1, 21))), code(vec![
+        spanned(Token::Keyword(Keyword::Const), 1, 1),
+        spanned(Token::Identifier("A".to_string()), 1, 7),
+        spanned(Token::Operator(Operator::Assign), 1, 9),
+        spanned(Token::Literal(Literal::Integer(u64::MAX as i128)), 1, 11),
+        spanned(Token::Operator(Operator::ShiftLeft), 1, 21),
+        spanned(Token::Literal(Literal::Integer(1)), 1, 24),
+        spanned(Token::Separator(Separator::Semicolon), 1, 25),
+    ]));
+    assert_eq!(("E0007", Some((1, 18))), code(vec![
+        spanned(Token::Keyword(Keyword::Typedef), 1, 1),
+        spanned(Token::Type(token::Type::Opaque), 1, 9),
+        spanned(Token::Identifier("a".to_string()), 1, 15),
+        spanned(Token::Bracket(token::Bracket::LeftSquare), 1, 16),
+        spanned(Token::Literal(Literal::Integer(2)), 1, 17),
+        spanned(Token::Literal(Literal::Integer(-2)), 1, 18),
+        spanned(Token::Bracket(token::Bracket::RightSquare), 1, 20),
+        spanned(Token::Separator(Separator::Semicolon), 1, 21),
+    ]));
+}
+
+#[test]
+fn value_ranges() {
+    use token::{Token, Operator, Literal, Bracket, Separator, Keyword};
+
+    let num = |n| Token::Literal(Literal::Integer(n));
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let code = |tokens: Vec<Token>| parse(tokens.into_iter()).err().map(|err| err.kind.code());
+
+    // const BIG = 18446744073709551615;
+    let big = vec![
+        Token::Keyword(Keyword::Const), id("BIG"), Token::Operator(Operator::Assign), num(u64::MAX as i128), Token::Separator(Separator::Semicolon),
+    ];
+    assert_eq!(None, code(big.clone()));
+    assert_eq!(Some("E0017"), code(vec![
+        Token::Keyword(Keyword::Const), id("BIG"), Token::Operator(Operator::Assign), num(u64::MAX as i128 + 1), Token::Separator(Separator::Semicolon),
+    ]));
+
+    // enum e { A = 2147483647, B };
+    assert_eq!(Some("E0019"), code(vec![
+        Token::Type(token::Type::Enum), id("e"), Token::Bracket(Bracket::LeftCurly),
+            id("A"), Token::Operator(Operator::Assign), num(i32::MAX as i128), Token::Separator(Separator::Comma), id("B"),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ]));
+
+    // typedef opaque a[BIG];
+    assert_eq!(Some("E0019"), code(big.iter().cloned().chain([
+        Token::Keyword(Keyword::Typedef), Token::Type(token::Type::Opaque), id("a"),
+            Token::Bracket(Bracket::LeftSquare), id("BIG"), Token::Bracket(Bracket::RightSquare),
+        Token::Separator(Separator::Semicolon),
+    ]).collect()));
+
+    // union u switch (unsigned int d) { case -1: void; };
+    let union = |tp: Vec<Token>, case| code([
+        Token::Type(token::Type::Union), id("u"), Token::Keyword(Keyword::Switch), Token::Bracket(Bracket::Left),
+    ].into_iter().chain(tp).chain([
+        id("d"), Token::Bracket(Bracket::Right), Token::Bracket(Bracket::LeftCurly),
+            Token::Keyword(Keyword::Case), num(case), Token::Separator(Separator::Colon),
+            Token::Type(token::Type::Integer), id("x"), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ]).collect());
+    assert_eq!(Some("E0019"), union(vec![Token::Type(token::Type::Unsigned), Token::Type(token::Type::Integer)], -1));
+    assert_eq!(None, union(vec![Token::Type(token::Type::Integer)], -1));
+    assert_eq!(Some("E0019"), union(vec![Token::Type(token::Type::Integer)], 1 << 31));
+    assert_eq!(None, union(vec![Token::Type(token::Type::Hyper)], 1 << 31));
+
+    // program P { version V { void F(void) = 1; } = 1; } = BIG;
+    assert_eq!(Some("E0019"), code(big.iter().cloned().chain([
+        Token::Keyword(Keyword::Program), id("P"), Token::Bracket(Bracket::LeftCurly),
+            Token::Keyword(Keyword::Version), id("V"), Token::Bracket(Bracket::LeftCurly),
+                Token::Type(token::Type::Void), id("F"), Token::Bracket(Bracket::Left), Token::Type(token::Type::Void), Token::Bracket(Bracket::Right),
+                Token::Operator(Operator::Assign), num(1), Token::Separator(Separator::Semicolon),
+            Token::Bracket(Bracket::RightCurly), Token::Operator(Operator::Assign), num(1), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Operator(Operator::Assign), id("BIG"), Token::Separator(Separator::Semicolon),
+    ]).collect()));
+}
+
+#[test]
+fn inline_types() {
+    use token::{Token, Operator, Literal, Bracket, Separator, Keyword};
+
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let tp = |t| Token::Type(t);
+
+    // struct a { struct { enum { X, Y } c; int d; } b; };
+    // typedef union switch (enum { P = 1 } k) { case P: struct { int e; } *f; } u;
+    // program G { version V { struct { int h; } F(enum { Z } ) = 1; } = 1; } = 1;
+    let tokens = [
+        tp(token::Type::Struct), id("a"), Token::Bracket(Bracket::LeftCurly),
+            tp(token::Type::Struct), Token::Bracket(Bracket::LeftCurly),
+                tp(token::Type::Enum), Token::Bracket(Bracket::LeftCurly), id("X"), Token::Separator(Separator::Comma), id("Y"), Token::Bracket(Bracket::RightCurly), id("c"), Token::Separator(Separator::Semicolon),
+                tp(token::Type::Integer), id("d"), Token::Separator(Separator::Semicolon),
+            Token::Bracket(Bracket::RightCurly), id("b"), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+        Token::Keyword(Keyword::Typedef), tp(token::Type::Union), Token::Keyword(Keyword::Switch), Token::Bracket(Bracket::Left),
+            tp(token::Type::Enum), Token::Bracket(Bracket::LeftCurly), id("P"), Token::Operator(Operator::Assign), Token::Literal(Literal::Integer(1)), Token::Bracket(Bracket::RightCurly), id("k"),
+        Token::Bracket(Bracket::Right), Token::Bracket(Bracket::LeftCurly),
+            Token::Keyword(Keyword::Case), id("P"), Token::Separator(Separator::Colon),
+                tp(token::Type::Struct), Token::Bracket(Bracket::LeftCurly), tp(token::Type::Integer), id("e"), Token::Separator(Separator::Semicolon), Token::Bracket(Bracket::RightCurly),
+                tp(token::Type::Pointer), id("f"), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), id("u"), Token::Separator(Separator::Semicolon),
+        Token::Keyword(Keyword::Program), id("G"), Token::Bracket(Bracket::LeftCurly),
+            Token::Keyword(Keyword::Version), id("V"), Token::Bracket(Bracket::LeftCurly),
+                tp(token::Type::Struct), Token::Bracket(Bracket::LeftCurly), tp(token::Type::Integer), id("h"), Token::Separator(Separator::Semicolon), Token::Bracket(Bracket::RightCurly),
+                id("F"), Token::Bracket(Bracket::Left),
+                    tp(token::Type::Enum), Token::Bracket(Bracket::LeftCurly), id("Z"), Token::Bracket(Bracket::RightCurly),
+                Token::Bracket(Bracket::Right), Token::Operator(Operator::Assign), Token::Literal(Literal::Integer(1)), Token::Separator(Separator::Semicolon),
+            Token::Bracket(Bracket::RightCurly), Token::Operator(Operator::Assign), Token::Literal(Literal::Integer(1)), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Operator(Operator::Assign), Token::Literal(Literal::Integer(1)), Token::Separator(Separator::Semicolon),
+    ];
+
+    let module = parse(tokens.into_iter()).unwrap();
+
+    assert_eq!(
+        vec![
+            "enum a_b_c", "struct a_b", "struct a",
+            "enum u_k", "struct u_f", "union u", "typedef u",
+            "struct F_res", "enum F_arg1", "program G",
+        ],
+        module.definitions.iter().map(|def| match def {
+            rpc::Definition::Enum(name, _) => format!("enum {name}"),
+            rpc::Definition::Struct(name, _) => format!("struct {name}"),
+            rpc::Definition::Union(name, _) => format!("union {name}"),
+            rpc::Definition::Typedef(name, _) => format!("typedef {name}"),
+            rpc::Definition::Program(_, progr) => format!("program {}", progr.name),
+            _ => String::from("other"),
+        }).collect::<Vec<_>>()
+    );
+
+    match &module.definitions[2] {
+        rpc::Definition::Struct(_, st) => assert_eq!(
+            Some(&rpc::Type::Named(rpc::NamedType::Struct("a_b".to_string()))),
+            st.fields.get("b")
+        ),
+        _ => panic!("Struct expected"),
+    }
+    match &module.definitions[5] {
+        rpc::Definition::Union(_, un) => {
+            assert_eq!(rpc::SwitchingType::Enum("u_k".to_string()), un.switch_type);
+            assert_eq!(
+                rpc::Type::Pointer(Box::new(rpc::Type::Named(rpc::NamedType::Struct("u_f".to_string())))),
+                un.arms[0].declaration.as_ref().unwrap().1
+            );
+        },
+        _ => panic!("Union expected"),
+    }
+
+    // struct a_b { int x; }; struct a { struct { int y; } b; };
+    let err = parse([
+        tp(token::Type::Struct), id("a_b"), Token::Bracket(Bracket::LeftCurly), tp(token::Type::Integer), id("x"), Token::Separator(Separator::Semicolon), Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+        tp(token::Type::Struct), id("a"), Token::Bracket(Bracket::LeftCurly),
+            tp(token::Type::Struct), Token::Bracket(Bracket::LeftCurly), tp(token::Type::Integer), id("y"), Token::Separator(Separator::Semicolon), Token::Bracket(Bracket::RightCurly), id("b"), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ].into_iter()).unwrap_err();
+
+    assert_eq!("E0008", err.kind.code());
+}
+
+#[test]
+fn union_arms() {
+    use token::{Token, Literal, Bracket, Separator, Keyword};
+
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let num = |n| Token::Literal(Literal::Integer(n));
+    let case = |n| [Token::Keyword(Keyword::Case), num(n), Token::Separator(Separator::Colon)];
+    let union = |arms: Vec<Token>| parse([
+        Token::Type(token::Type::Union), id("u"), Token::Keyword(Keyword::Switch), Token::Bracket(Bracket::Left),
+            Token::Type(token::Type::Integer), id("d"),
+        Token::Bracket(Bracket::Right), Token::Bracket(Bracket::LeftCurly),
+    ].into_iter().chain(arms).chain([
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ]));
+
+    // case 1: case 2: int a; case 3: void; case 4: default: void;
+    let module = union(case(1).into_iter().chain(case(2)).chain([
+        Token::Type(token::Type::Integer), id("a"), Token::Separator(Separator::Semicolon),
+    ]).chain(case(3)).chain([
+        Token::Type(token::Type::Void), Token::Separator(Separator::Semicolon),
+    ]).chain(case(4)).chain([
+        Token::Keyword(Keyword::Default), Token::Separator(Separator::Colon),
+        Token::Type(token::Type::Void), Token::Separator(Separator::Semicolon),
+    ]).collect()).unwrap();
+
+    match &module.definitions[0] {
+        rpc::Definition::Union(_, un) => {
+            assert_eq!(
+                vec![
+                    (vec![rpc::Value::Number(1), rpc::Value::Number(2)], Some("a")),
+                    (vec![rpc::Value::Number(3)], None),
+                ],
+                un.arms.iter()
+                    .map(|arm| (arm.cases.clone(), arm.declaration.as_ref().map(|(name, _)| name.as_str())))
+                    .collect::<Vec<_>>()
+            );
+            let default = un.default.as_ref().unwrap();
+            assert_eq!(vec![rpc::Value::Number(4)], default.cases);
+            assert!(default.declaration.is_none());
+            assert_eq!(1, un.declarations().count());
+        },
+        _ => panic!("Union expected"),
+    }
+
+    // case 1: case 1: void;
+    let err = union(case(1).into_iter().chain(case(1)).chain([
+        Token::Type(token::Type::Void), Token::Separator(Separator::Semicolon),
+    ]).collect()).unwrap_err();
+    assert_eq!("E0012", err.kind.code());
+}
+
+#[test]
+fn switch_typedefs() {
+    use token::{Token, Literal, Bracket, Separator, Keyword, Operator};
+
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let num = |n| Token::Literal(Literal::Integer(n));
+    let typedef = |tp: Vec<Token>, name: &str| [Token::Keyword(Keyword::Typedef)].into_iter()
+        .chain(tp)
+        .chain([id(name), Token::Separator(Separator::Semicolon)]);
+    let union = |disc: &str, case| [
+        Token::Type(token::Type::Union), id("u"), Token::Keyword(Keyword::Switch), Token::Bracket(Bracket::Left),
+            id(disc), id("d"),
+        Token::Bracket(Bracket::Right), Token::Bracket(Bracket::LeftCurly),
+            Token::Keyword(Keyword::Case), num(case), Token::Separator(Separator::Colon),
+            Token::Type(token::Type::Integer), id("x"), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ];
+    let switch_type = |module: rpc::Module| module.definitions.into_iter().find_map(|def| match def {
+        rpc::Definition::Union(_, un) => Some(un.switch_type),
+        _ => None,
+    }).unwrap();
+
+    // typedef int disc; union u switch (disc d) { case -1: int x; };
+    let module = parse(typedef(vec![Token::Type(token::Type::Integer)], "disc").chain(union("disc", -1))).unwrap();
+    assert_eq!(rpc::SwitchingType::Integer(rpc::Integer::Integer), switch_type(module));
+
+    // union u switch (udisc d) { case 4294967295: int x; };
+    // typedef udisc_base udisc; typedef unsigned int udisc_base;
+    let module = parse(union("udisc", u32::MAX as i128)
+        .into_iter()
+        .chain(typedef(vec![id("udisc_base")], "udisc"))
+        .chain(typedef(vec![Token::Type(token::Type::Unsigned), Token::Type(token::Type::Integer)], "udisc_base"))
+    ).unwrap();
+    assert_eq!(rpc::SwitchingType::Unsigned(rpc::Integer::Integer), switch_type(module));
+
+    // typedef unsigned int udisc; union u switch (udisc d) { case -1: int x; };
+    let err = parse(typedef(vec![Token::Type(token::Type::Unsigned), Token::Type(token::Type::Integer)], "udisc")
+        .chain(union("udisc", -1))
+    ).unwrap_err();
+    assert_eq!("E0019", err.kind.code());
+
+    // enum color { RED = 1 }; typedef enum color paint; union u switch (paint d) { case 1: int x; };
+    let module = parse([
+        Token::Type(token::Type::Enum), id("color"), Token::Bracket(Bracket::LeftCurly),
+            id("RED"), Token::Operator(Operator::Assign), num(1),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ].into_iter().chain(typedef(vec![Token::Type(token::Type::Enum), id("color")], "paint")).chain(union("paint", 1))).unwrap();
+    assert_eq!(rpc::SwitchingType::Enum("color".to_string()), switch_type(module));
+
+    // typedef float real; union u switch (real d) { case 1: int x; };
+    let err = parse(typedef(vec![Token::Type(token::Type::Float)], "real").chain(union("real", 1))).unwrap_err();
+    assert_eq!("E0011", err.kind.code());
+}
+
+#[test]
+fn bare_types() {
+    use token::{Token, Bracket, Separator};
+
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let structure = |name: &str, field: Vec<Token>| [
+        Token::Type(token::Type::Struct), id(name), Token::Bracket(Bracket::LeftCurly),
+    ].into_iter().chain(field).chain([
+        Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ]);
+    let typedef = |tp: &str, name: &str| [
+        Token::Keyword(token::Keyword::Typedef), id(tp), id(name), Token::Separator(Separator::Semicolon),
+    ];
+
+    // struct node { node *next; }; typedef node alias;
+    let module = parse(structure("node", vec![id("node"), Token::Type(token::Type::Pointer), id("next")])
+        .chain(typedef("node", "alias"))
+    ).unwrap();
+    match &module.definitions[1] {
+        rpc::Definition::Typedef(_, tp) =>
+            assert_eq!(rpc::Type::Named(rpc::NamedType::Struct("node".to_string())), tp.tp),
+        _ => panic!("Typedef expected"),
+    }
+
+    // struct node { node inner; };
+    let err = parse(structure("node", vec![id("node"), id("inner")])).unwrap_err();
+    assert_eq!("E0013", err.kind.code());
+
+    // struct foo { int a; }; enum foo { A }; typedef foo bar;
+    let err = parse(structure("foo", vec![Token::Type(token::Type::Integer), id("a")]).chain([
+        Token::Type(token::Type::Enum), id("foo"), Token::Bracket(Bracket::LeftCurly),
+            id("A"),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ]).chain(typedef("foo", "bar"))).unwrap_err();
+    assert_eq!("E0020", err.kind.code());
+    assert_eq!(Some("use one of `struct foo`, `enum foo`"), err.hint.as_deref());
+}
+
+#[test]
+fn folded_numbers() {
+    use token::{Token, Operator, Literal, Bracket, Separator, Keyword};
+
+    let num = |n| Token::Literal(Literal::Integer(n));
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let program = |versions: Vec<(&str, Token)>| [
+        Token::Type(token::Type::Enum), id("vers"), Token::Bracket(Bracket::LeftCurly),
+            id("V1"), Token::Operator(Operator::Assign), num(1),
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+        Token::Keyword(Keyword::Program), id("P"), Token::Bracket(Bracket::LeftCurly),
+    ].into_iter().chain(versions.into_iter().flat_map(|(name, number)| [
+        Token::Keyword(Keyword::Version), id(name), Token::Bracket(Bracket::LeftCurly),
+            Token::Type(token::Type::Integer), id(&format!("F{name}")), Token::Bracket(Bracket::Left), Token::Type(token::Type::Integer), Token::Bracket(Bracket::Right),
+            Token::Operator(Operator::Assign), id("V1"), Token::Separator(Separator::Semicolon),
+        Token::Bracket(Bracket::RightCurly), Token::Operator(Operator::Assign), number, Token::Separator(Separator::Semicolon),
+    ])).chain([
+        Token::Bracket(Bracket::RightCurly), Token::Operator(Operator::Assign), num(0x20000001), Token::Separator(Separator::Semicolon),
+    ]).collect::<Vec<_>>();
+
+    // enum vers { V1 = 1 };
+    // program P { version PV { int FPV(int) = V1; } = V1; } = 0x20000001;
+    let module = parse(program(vec![("PV", id("V1"))]).into_iter()).unwrap();
+    match &module.definitions[1] {
+        rpc::Definition::Program(v, progr) => {
+            assert_eq!(&rpc::Value::Number(0x20000001), v);
+
+            let ver = progr.versions.get(&rpc::Value::Number(1)).unwrap();
+            assert_eq!("PV", ver.name);
+            assert!(ver.procedures.contains_key(&rpc::Value::Number(1)));
+        },
+        _ => panic!("Program expected"),
+    }
+
+    // program P { version A { ... } = V1; version B { ... } = 1; } = 0x20000001;
+    let err = parse(program(vec![("A", id("V1")), ("B", num(1))]).into_iter()).unwrap_err();
+    assert!(matches!(err.kind, ErrorKind::VersionNumberReassigned(rpc::Value::Number(1))));
+
+    // Versions after the reassigned one are kept
+    let (module, errors) = parse_recovering(program(vec![("A", id("V1")), ("B", num(1)), ("C", num(2))]).into_iter());
+    assert_eq!(1, errors.len());
+    match &module.definitions[1] {
+        rpc::Definition::Program(_, progr) => assert_eq!(
+            vec!["A", "C"],
+            progr.versions.values().map(|ver| ver.name.as_str()).collect::<Vec<_>>()
+        ),
+        _ => panic!("Program expected"),
+    }
+}
+
+#[test]
+fn forward_references() {
+    use token::{Token, Operator, Literal, Bracket, Separator, Keyword};
+
+    let num = |n| Token::Literal(Literal::Integer(n));
+    let id = |s: &str| Token::Identifier(s.to_string());
+    let constant = |name: &str, value: Vec<Token>| [
+        Token::Keyword(Keyword::Const), id(name), Token::Operator(Operator::Assign),
+    ].into_iter().chain(value).chain([Token::Separator(Separator::Semicolon)]).collect::<Vec<_>>();
+    let structure = |name: &str, fields: Vec<Vec<Token>>| [
+        Token::Type(token::Type::Struct), id(name), Token::Bracket(Bracket::LeftCurly),
+    ].into_iter().chain(fields.into_iter().flat_map(|field| field.into_iter()
+        .chain([Token::Separator(Separator::Semicolon)])
+    )).chain([
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ]).collect::<Vec<_>>();
+    let names = |module: rpc::Module| module.definitions.into_iter().map(|def| match def {
+        rpc::Definition::Const(name, _) | rpc::Definition::Struct(name, _) | rpc::Definition::Enum(name, _) => name,
+        _ => panic!("Const, struct or enum expected"),
+    }).collect::<Vec<_>>();
+    let code = |tokens: Vec<Vec<Token>>| parse(tokens.into_iter().flatten()).err().map(|err| err.kind.code());
+
+    // const N = M * 2;
+    // struct a { b *peer; opaque buf[N]; c inner; };
+    // struct b { a *peer; };
+    // struct c { int v; };
+    // const M = 4;
+    let module = parse([
+        constant("N", vec![id("M"), Token::Type(token::Type::Pointer), num(2)]),
+        structure("a", vec![
+            vec![id("b"), Token::Type(token::Type::Pointer), id("peer")],
+            vec![Token::Type(token::Type::Opaque), id("buf"), Token::Bracket(Bracket::LeftSquare), id("N"), Token::Bracket(Bracket::RightSquare)],
+            vec![id("c"), id("inner")],
+        ]),
+        structure("b", vec![vec![id("a"), Token::Type(token::Type::Pointer), id("peer")]]),
+        structure("c", vec![vec![Token::Type(token::Type::Integer), id("v")]]),
+        constant("M", vec![num(4)]),
+    ].into_iter().flatten()).unwrap();
+    assert_eq!(vec!["N", "c", "a", "b", "M"], names(module));
+
+    // enum e { X = Y }; enum f { Y = 1 };
+    let enumeration = |name: &str, item: &str, value: Token| vec![
+        Token::Type(token::Type::Enum), id(name), Token::Bracket(Bracket::LeftCurly),
+            id(item), Token::Operator(Operator::Assign), value,
+        Token::Bracket(Bracket::RightCurly), Token::Separator(Separator::Semicolon),
+    ];
+    let module = parse([enumeration("e", "X", id("Y")), enumeration("f", "Y", num(1))].into_iter().flatten()).unwrap();
+    assert_eq!(vec!["f", "e"], names(module));
+
+    // const A = B; const B = A;
+    assert_eq!(Some("E0021"), code(vec![constant("A", vec![id("B")]), constant("B", vec![id("A")])]));
+    // struct a { b x; }; struct b { a y; };
+    assert_eq!(Some("E0013"), code(vec![
+        structure("a", vec![vec![id("b"), id("x")]]),
+        structure("b", vec![vec![id("a"), id("y")]]),
+    ]));
+    // typedef opaque t[M - 5]; const M = 4;
+    assert_eq!(Some("E0007"), code(vec![
+        vec![
+            Token::Keyword(Keyword::Typedef), Token::Type(token::Type::Opaque), id("t"),
+                Token::Bracket(Bracket::LeftSquare), id("M"), num(-5), Token::Bracket(Bracket::RightSquare),
+            Token::Separator(Separator::Semicolon),
+        ],
+        constant("M", vec![num(4)]),
+    ]));
+    // const A = B + 1;
+    assert_eq!(Some("E0006"), code(vec![constant("A", vec![id("B"), Token::Operator(Operator::Add), num(1)])]));
+}
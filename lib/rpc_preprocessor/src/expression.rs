@@ -0,0 +1,243 @@
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+/// Longer operators go first, so they aren't split
+const OPERATORS: [&str; 24] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "(", ")", "!", "~", "+", "-", "*", "/", "%", "<", ">", "&", "^", "|", "?", ":",
+];
+
+/// Evaluate `#if` condition, `defined` is resolved first, then macros are
+/// expanded and the rest of identifiers are 0
+pub(crate) fn evaluate(text: &str, defines: &HashMap<String, String>) -> Result<i64, String> {
+    let tokens = resolve(tokenize(text)?, defines, &mut Vec::new())?;
+    let mut parser = Parser { tokens, position: 0, evaluated: true };
+
+    let value = parser.expression()?;
+
+    match parser.tokens.get(parser.position) {
+        None => Ok(value),
+        Some(token) => Err(format!("Unexpected {} in expression", describe(Some(token)))),
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut out = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_digit() {
+            let length = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            out.push(Token::Number(number(&rest[..length])?));
+            length
+        } else if c.is_alphabetic() || '_' == c {
+            let length = rest.find(|c: char| !(c.is_alphanumeric() || '_' == c)).unwrap_or(rest.len());
+            out.push(Token::Identifier(rest[..length].to_string()));
+            length
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            out.push(Token::Operator(op));
+            op.len()
+        } else {
+            return Err(format!("Unexpected character `{c}` in expression"));
+        };
+
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(out)
+}
+
+fn number(text: &str) -> Result<i64, String> {
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => (hex, 16),
+        None if 1 < digits.len() && digits.starts_with('0') => (&digits[1..], 8),
+        None => (digits, 10),
+    };
+
+    i64::from_str_radix(digits, radix).map_err(|_| format!("Invalid number `{text}`"))
+}
+
+fn resolve(
+    tokens: Vec<Token>,
+    defines: &HashMap<String, String>,
+    hidden: &mut Vec<String>,
+) -> Result<Vec<Token>, String> {
+    let mut out = Vec::new();
+    let mut tokens = tokens.into_iter();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Identifier(id) if "defined" == id => {
+                let name = match tokens.next() {
+                    Some(Token::Identifier(name)) => Some(name),
+                    Some(Token::Operator("(")) => match (tokens.next(), tokens.next()) {
+                        (Some(Token::Identifier(name)), Some(Token::Operator(")"))) => Some(name),
+                        _ => None,
+                    },
+                    _ => None,
+                }.ok_or_else(|| String::from("Expected macro name after `defined`"))?;
+
+                out.push(Token::Number(defines.contains_key(&name) as i64));
+            },
+            Token::Identifier(id) => match defines.get(&id) {
+                Some(value) if !hidden.contains(&id) => {
+                    hidden.push(id);
+                    out.extend(resolve(tokenize(value)?, defines, hidden)?);
+                    hidden.pop();
+                },
+                _ => out.push(Token::Number(0)),
+            },
+            token => out.push(token),
+        }
+    }
+
+    Ok(out)
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => String::from("end of expression"),
+        Some(Token::Number(n)) => format!("`{n}`"),
+        Some(Token::Identifier(id)) => format!("`{id}`"),
+        Some(Token::Operator(op)) => format!("`{op}`"),
+    }
+}
+
+fn precedence(op: &str) -> Option<u8> {
+    match op {
+        "||" => Some(1),
+        "&&" => Some(2),
+        "|" => Some(3),
+        "^" => Some(4),
+        "&" => Some(5),
+        "==" | "!=" => Some(6),
+        "<" | ">" | "<=" | ">=" => Some(7),
+        "<<" | ">>" => Some(8),
+        "+" | "-" => Some(9),
+        "*" | "/" | "%" => Some(10),
+        _ => None,
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// False in operands short-circuited by `&&`, `||` and `?:`, which are
+    /// parsed, but can't fail on their values
+    evaluated: bool,
+}
+
+impl Parser {
+    fn next(self: &mut Self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn operator(self: &Self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(self: &mut Self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Operator(found)) if op == *found => Ok(()),
+            token => Err(format!("Expected `{op}`, got {}", describe(token))),
+        }
+    }
+
+    /// Parse with `evaluated` cleared when `skip` is set
+    fn skipping<R>(self: &mut Self, skip: bool, parse: impl FnOnce(&mut Self) -> R) -> R {
+        let evaluated = self.evaluated;
+
+        self.evaluated &= !skip;
+        let out = parse(self);
+        self.evaluated = evaluated;
+
+        out
+    }
+
+    fn expression(self: &mut Self) -> Result<i64, String> {
+        let condition = self.binary(1)?;
+
+        if let Some("?") = self.operator() {
+            self.position += 1;
+            let then = self.skipping(0 == condition, Self::expression)?;
+            self.expect(":")?;
+            let otherwise = self.skipping(0 != condition, Self::expression)?;
+
+            Ok(if 0 != condition { then } else { otherwise })
+        } else {
+            Ok(condition)
+        }
+    }
+
+    fn binary(self: &mut Self, min: u8) -> Result<i64, String> {
+        let mut left = self.unary()?;
+
+        while let Some((op, precedence)) = self.operator()
+            .and_then(|op| precedence(op).map(|precedence| (op, precedence)))
+            .filter(|(_, precedence)| *precedence >= min)
+        {
+            self.position += 1;
+            let skip = match op {
+                "||" => 0 != left,
+                "&&" => 0 == left,
+                _ => false,
+            };
+            let right = self.skipping(skip, |parser| parser.binary(precedence + 1))?;
+
+            left = match op {
+                "||" => (0 != left || 0 != right) as i64,
+                "&&" => (0 != left && 0 != right) as i64,
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "==" => (left == right) as i64,
+                "!=" => (left != right) as i64,
+                "<" => (left < right) as i64,
+                ">" => (left > right) as i64,
+                "<=" => (left <= right) as i64,
+                ">=" => (left >= right) as i64,
+                "<<" => left.wrapping_shl(right as u32),
+                ">>" => left.wrapping_shr(right as u32),
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                "/" | "%" if 0 == right && self.evaluated => {
+                    return Err(String::from("Division by zero in expression"))
+                },
+                "/" | "%" if 0 == right => 0,
+                "/" => left.wrapping_div(right),
+                _ => left.wrapping_rem(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn unary(self: &mut Self) -> Result<i64, String> {
+        match self.next().cloned() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::Operator("!")) => self.unary().map(|v| (0 == v) as i64),
+            Some(Token::Operator("~")) => self.unary().map(|v| !v),
+            Some(Token::Operator("-")) => self.unary().map(i64::wrapping_neg),
+            Some(Token::Operator("+")) => self.unary(),
+            Some(Token::Operator("(")) => {
+                let value = self.expression()?;
+                self.expect(")")?;
+                Ok(value)
+            },
+            token => Err(format!("Expected value, got {}", describe(token.as_ref()))),
+        }
+    }
+}
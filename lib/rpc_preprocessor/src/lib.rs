@@ -0,0 +1,485 @@
+
+#[cfg(test)]
+mod test;
+mod expression;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use lexer::Span;
+
+/// Maximal nesting of `#include`, deeper one is most likely a cycle
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Include(String, std::io::Error),
+    IncludeDepth(String),
+    UnknownDirective(String),
+    MalformedDirective(String),
+    UnbalancedConditional(String),
+    InvalidExpression(String),
+    ErrorDirective(String),
+}
+
+impl ErrorKind {
+    /// Stable identifier of the error used in diagnostics
+    pub fn code(self: &Self) -> &'static str {
+        match self {
+            ErrorKind::Include(_, _) => "P0001",
+            ErrorKind::IncludeDepth(_) => "P0002",
+            ErrorKind::UnknownDirective(_) => "P0003",
+            ErrorKind::MalformedDirective(_) => "P0004",
+            ErrorKind::UnbalancedConditional(_) => "P0005",
+            ErrorKind::InvalidExpression(_) => "P0006",
+            ErrorKind::ErrorDirective(_) => "P0007",
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.span {
+            Some(span) if span.is_known() => write!(f, "{span}: {}", self.kind),
+            _ => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Include(file, error) => write!(f, "Can't include `{file}`: {error}"),
+            ErrorKind::IncludeDepth(file) => write!(f,
+                "Includes are nested too deep at `{file}`\nProbably files include each other"
+            ),
+            ErrorKind::UnknownDirective(name) => write!(f, "Unknown directive `#{name}`"),
+            ErrorKind::MalformedDirective(msg) => write!(f, "Malformed directive\n{msg}"),
+            ErrorKind::UnbalancedConditional(msg) => write!(f, "Unbalanced conditional\n{msg}"),
+            ErrorKind::InvalidExpression(msg) => write!(f, "Invalid expression\n{msg}"),
+            ErrorKind::ErrorDirective(msg) => write!(f, "#error {msg}"),
+        }
+    }
+}
+
+/// Line of original file output line comes from
+#[derive(Clone, Debug)]
+struct Origin {
+    file: Arc<str>,
+    line: usize,
+    offset: usize,
+    length: usize,
+    output: usize,
+}
+
+/// Preprocessed text with the way back to original files
+#[derive(Debug, Default)]
+pub struct Output {
+    pub text: String,
+    lines: Vec<Origin>,
+    sources: Vec<(Arc<str>, String)>,
+}
+
+impl Output {
+    fn push_line(self: &mut Self, text: &str, origin: Origin) {
+        self.lines.push(Origin { output: self.text.len(), ..origin });
+        self.text += text;
+        self.text.push('\n');
+    }
+
+    /// Move span in [`Output::text`] to the file and line it comes from
+    ///
+    /// Columns are kept, so they are off on lines with expanded macros.
+    pub fn remap(self: &Self, span: Span) -> Span {
+        let origin = span.line.checked_sub(1)
+            .and_then(|line| self.lines.get(line).or_else(|| self.lines.last()));
+
+        match origin {
+            Some(origin) => Span {
+                file: Some(origin.file.clone()),
+                offset: origin.offset + span.offset.saturating_sub(origin.output).min(origin.length),
+                line: origin.line,
+                ..span
+            },
+            None => span,
+        }
+    }
+
+    /// Text of original file, which took part in preprocessing
+    pub fn source(self: &Self, file: &str) -> Option<&str> {
+        self.sources.iter()
+            .find(|(name, _)| file == &**name)
+            .map(|(_, source)| source.as_str())
+    }
+}
+
+type Loader = Box<dyn Fn(&Path) -> std::io::Result<String>>;
+
+/// C preprocessor subset: `#include`, object-like `#define`, `#undef` and
+/// `#if`/`#ifdef`/`#ifndef`/`#elif`/`#else`/`#endif` conditionals
+pub struct Preprocessor {
+    include_paths: Vec<PathBuf>,
+    defines: HashMap<String, String>,
+    loader: Loader,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self {
+            include_paths: Vec::new(),
+            defines: HashMap::new(),
+            loader: Box::new(|path| std::fs::read_to_string(path)),
+        }
+    }
+
+    /// Directory searched for `#include <file>`, and for `#include "file"`
+    /// after directory of the including file
+    pub fn include_path(self: &mut Self, path: impl Into<PathBuf>) -> &mut Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    pub fn define(self: &mut Self, name: &str, value: &str) -> &mut Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Read included files with `loader` instead of filesystem
+    pub fn with_loader(
+        self: &mut Self,
+        loader: impl Fn(&Path) -> std::io::Result<String> + 'static,
+    ) -> &mut Self {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    /// Preprocess `source` of `file`, broken directives are reported and
+    /// skipped, so output contains everything that could be processed
+    pub fn process_str(self: &Self, file: &str, source: &str) -> (Output, Vec<Error>) {
+        let mut context = Context {
+            preprocessor: self,
+            defines: self.defines.clone(),
+            output: Output::default(),
+            errors: Vec::new(),
+        };
+
+        context.process(Arc::from(file), Path::new(file), source, 0);
+
+        (context.output, context.errors)
+    }
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Branch of conditional directive
+struct Frame {
+    /// Lines are taken
+    active: bool,
+    /// One of the branches was taken already
+    taken: bool,
+    /// Enclosing region is active
+    parent: bool,
+    /// `#else` was seen
+    otherwise: bool,
+    span: Span,
+}
+
+struct Context<'a> {
+    preprocessor: &'a Preprocessor,
+    defines: HashMap<String, String>,
+    output: Output,
+    errors: Vec<Error>,
+}
+
+impl<'a> Context<'a> {
+    fn process(self: &mut Self, file: Arc<str>, path: &Path, source: &str, depth: usize) {
+        if self.output.source(&file).is_none() {
+            self.output.sources.push((file.clone(), source.to_string()));
+        }
+
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut in_comment = false;
+        let mut offset = 0;
+        let mut lines = source.split_inclusive('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let start = offset;
+                offset += line.len();
+                (i + 1, start, line.trim_end_matches('\n').trim_end_matches('\r'))
+            });
+
+        while let Some((line, offset, text)) = lines.next() {
+            let active = frames.last().is_none_or(|frame| frame.active);
+            let indent = text.len() - text.trim_start().len();
+
+            if !in_comment && text.trim_start().starts_with('#') {
+                let mut joined = text.to_string();
+                while joined.ends_with('\\') {
+                    joined.pop();
+                    match lines.next() {
+                        Some((_, _, next)) => joined += next,
+                        None => break,
+                    }
+                }
+
+                let span = Span {
+                    file: Some(file.clone()),
+                    offset: offset + indent,
+                    line,
+                    column: text[..indent].chars().count() + 1,
+                    length: text.len() - indent,
+                };
+                let directive = strip_comments(&joined, &mut in_comment);
+
+                if let Err(kind) = self.directive(directive.trim()[1..].trim(), &span, &mut frames, path, depth) {
+                    self.errors.push(Error { kind, span: Some(span) });
+                }
+            } else if active {
+                let expanded = self.expand(text, &mut in_comment, &mut Vec::new());
+
+                self.output.push_line(&expanded, Origin {
+                    file: file.clone(),
+                    line,
+                    offset,
+                    length: text.len(),
+                    output: 0,
+                });
+            } else {
+                strip_comments(text, &mut in_comment);
+            }
+        }
+
+        self.errors.extend(frames.into_iter().map(|frame| Error {
+            kind: ErrorKind::UnbalancedConditional(String::from("Conditional isn't closed with `#endif`")),
+            span: Some(frame.span),
+        }));
+    }
+
+    fn directive(
+        self: &mut Self,
+        text: &str,
+        span: &Span,
+        frames: &mut Vec<Frame>,
+        path: &Path,
+        depth: usize,
+    ) -> Result<(), ErrorKind> {
+        let length = text.find(|c: char| !is_identifier(c)).unwrap_or(text.len());
+        let (name, rest) = (&text[..length], text[length..].trim());
+        let active = frames.last().is_none_or(|frame| frame.active);
+
+        match name {
+            "ifdef" | "ifndef" | "if" => {
+                let condition = match name {
+                    _ if !active => Ok(false),
+                    "if" => self.condition(rest),
+                    _ => identifier(rest).map(|id| self.defines.contains_key(id) == ("ifdef" == name)),
+                };
+
+                frames.push(Frame {
+                    active: active && *condition.as_ref().unwrap_or(&false),
+                    taken: *condition.as_ref().unwrap_or(&true),
+                    parent: active,
+                    otherwise: false,
+                    span: span.clone(),
+                });
+
+                condition.map(|_| ())
+            },
+            "elif" | "else" => {
+                let frame = match frames.last_mut() {
+                    None => return Err(ErrorKind::UnbalancedConditional(format!("`#{name}` without `#if`"))),
+                    Some(frame) if frame.otherwise => return Err(ErrorKind::UnbalancedConditional(
+                        format!("`#{name}` after `#else`")
+                    )),
+                    Some(frame) => frame,
+                };
+
+                let condition = match name {
+                    _ if !frame.parent || frame.taken => Ok(false),
+                    "elif" => self.condition(rest),
+                    _ => Ok(true),
+                };
+
+                frame.active = *condition.as_ref().unwrap_or(&false);
+                frame.taken |= *condition.as_ref().unwrap_or(&true);
+                frame.otherwise = "else" == name;
+
+                condition.map(|_| ())
+            },
+            "endif" => frames.pop()
+                .map(|_| ())
+                .ok_or_else(|| ErrorKind::UnbalancedConditional(String::from("`#endif` without `#if`"))),
+            _ if !active => Ok(()),
+            "include" => self.include(rest, path, depth),
+            "define" => {
+                let length = rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len());
+                let id = identifier(&rest[..length])?;
+
+                if rest[length..].starts_with('(') {
+                    return Err(ErrorKind::MalformedDirective(
+                        format!("Function-like macro `{id}` isn't supported")
+                    ));
+                }
+
+                self.defines.insert(id.to_string(), rest[length..].trim().to_string());
+                Ok(())
+            },
+            "undef" => identifier(rest).map(|id| {
+                self.defines.remove(id);
+            }),
+            "error" => Err(ErrorKind::ErrorDirective(rest.to_string())),
+            "pragma" | "" => Ok(()),
+            _ => Err(ErrorKind::UnknownDirective(name.to_string())),
+        }
+    }
+
+    fn condition(self: &Self, text: &str) -> Result<bool, ErrorKind> {
+        expression::evaluate(text, &self.defines)
+            .map(|value| 0 != value)
+            .map_err(ErrorKind::InvalidExpression)
+    }
+
+    fn include(self: &mut Self, text: &str, path: &Path, depth: usize) -> Result<(), ErrorKind> {
+        let (name, quoted) = match (text.strip_prefix('"'), text.strip_prefix('<')) {
+            (Some(rest), _) => (rest.strip_suffix('"'), true),
+            (_, Some(rest)) => (rest.strip_suffix('>'), false),
+            _ => (None, false),
+        };
+        let name = name.ok_or_else(|| ErrorKind::MalformedDirective(
+            String::from("Expected \"file\" or <file> after `#include`")
+        ))?;
+
+        if MAX_DEPTH <= depth {
+            return Err(ErrorKind::IncludeDepth(name.to_string()));
+        }
+
+        let preprocessor = self.preprocessor;
+        let candidates = path.parent()
+            .filter(|_| quoted)
+            .map(|dir| dir.join(name))
+            .into_iter()
+            .chain(preprocessor.include_paths.iter().map(|dir| dir.join(name)));
+
+        for candidate in candidates {
+            match (preprocessor.loader)(&candidate) {
+                Ok(source) => {
+                    let file = Arc::from(candidate.to_string_lossy().as_ref());
+                    self.process(file, &candidate, &source, depth + 1);
+                    return Ok(());
+                },
+                Err(error) if std::io::ErrorKind::NotFound == error.kind() => continue,
+                Err(error) => return Err(ErrorKind::Include(name.to_string(), error)),
+            }
+        }
+
+        Err(ErrorKind::Include(name.to_string(), std::io::ErrorKind::NotFound.into()))
+    }
+
+    /// Replace defined identifiers outside of comments, macro isn't expanded
+    /// inside of itself
+    fn expand(self: &Self, text: &str, in_comment: &mut bool, hidden: &mut Vec<String>) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            let length = if *in_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        *in_comment = false;
+                        end + 2
+                    },
+                    None => rest.len(),
+                }
+            } else if rest.starts_with("/*") {
+                *in_comment = true;
+                2
+            } else if rest.starts_with("//") {
+                rest.len()
+            } else if c.is_ascii_digit() {
+                rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len())
+            } else if is_identifier(c) {
+                let length = rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len());
+                let id = &rest[..length];
+
+                match self.defines.get(id) {
+                    Some(value) if !hidden.iter().any(|h| h == id) => {
+                        hidden.push(id.to_string());
+                        out += &self.expand(value, &mut false, hidden);
+                        hidden.pop();
+                        rest = &rest[length..];
+                        continue;
+                    },
+                    _ => length,
+                }
+            } else {
+                c.len_utf8()
+            };
+
+            out += &rest[..length];
+            rest = &rest[length..];
+        }
+
+        out
+    }
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || '_' == c
+}
+
+fn identifier(text: &str) -> Result<&str, ErrorKind> {
+    match text.chars().next() {
+        Some(c) if !c.is_ascii_digit() && text.chars().all(is_identifier) => Ok(text),
+        _ => Err(ErrorKind::MalformedDirective(format!("Expected macro name, got `{text}`"))),
+    }
+}
+
+/// Replace comments with spaces, keeping track of unclosed block comment
+fn strip_comments(text: &str, in_comment: &mut bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if *in_comment {
+            match rest.find("*/") {
+                Some(end) => {
+                    *in_comment = false;
+                    out.push(' ');
+                    rest = &rest[end + 2..];
+                },
+                None => rest = "",
+            }
+        } else {
+            match (rest.find("/*"), rest.find("//")) {
+                (Some(block), line) if line.is_none_or(|line| block < line) => {
+                    *in_comment = true;
+                    out += &rest[..block];
+                    rest = &rest[block + 2..];
+                },
+                (_, Some(line)) => {
+                    out += &rest[..line];
+                    rest = "";
+                },
+                _ => {
+                    out += rest;
+                    rest = "";
+                },
+            }
+        }
+    }
+
+    out
+}
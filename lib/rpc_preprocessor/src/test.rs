@@ -0,0 +1,198 @@
+use super::*;
+
+/// Preprocessor reading files from the list instead of filesystem
+fn preprocessor(files: &[(&str, &str)]) -> Preprocessor {
+    let files = files.iter()
+        .map(|(name, text)| (PathBuf::from(name), text.to_string()))
+        .collect::<HashMap<_, _>>();
+
+    let mut p = Preprocessor::new();
+    p.with_loader(move |path| files.get(path)
+        .cloned()
+        .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    );
+    p
+}
+
+fn codes(errors: &[Error]) -> Vec<&'static str> {
+    errors.iter().map(|e| e.kind.code()).collect()
+}
+
+#[test]
+fn defines() {
+    let p = preprocessor(&[]);
+    let (out, errors) = p.process_str("spec.x", "\
+#define SIZE 16
+#define TWICE (SIZE * 2)
+#define LOOP LOOP + 1
+const A = TWICE; /* SIZE */
+const B = LOOP; // SIZE
+const SIZE_1 = 0x10;
+#undef SIZE
+const C = SIZE;
+");
+
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(
+        "const A = (16 * 2); /* SIZE */\n\
+         const B = LOOP + 1; // SIZE\n\
+         const SIZE_1 = 0x10;\n\
+         const C = SIZE;\n",
+        out.text
+    );
+}
+
+#[test]
+fn conditionals() {
+    let p = {
+        let mut p = preprocessor(&[]);
+        p.define("RPC_HDR", "");
+        p
+    };
+    let (out, errors) = p.process_str("spec.x", "\
+#define LEVEL 2
+#ifdef RPC_HDR
+hdr
+#else
+not hdr
+#endif
+#if LEVEL > 2
+three
+#elif defined(LEVEL) && LEVEL == 2
+two
+#elif 1
+one
+#else
+zero
+#endif
+#ifndef RPC_HDR
+#error never
+#if garbage (
+#endif
+#endif
+/*
+#if 0
+*/
+comment
+");
+
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!("hdr\ntwo\n/*\n#if 0\n*/\ncomment\n", out.text);
+}
+
+#[test]
+fn short_circuit() {
+    let p = preprocessor(&[]);
+    let (out, errors) = p.process_str("spec.x", "\
+#if defined(X) && 10 / X
+and
+#endif
+#if 1 || 1 % 0
+or
+#endif
+#if 0 ? 1 / 0 : 2
+ternary
+#endif
+#if 1 ? 2 : (0 && 1 / 0)
+nested
+#endif
+");
+
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!("or\nternary\nnested\n", out.text);
+}
+
+#[test]
+fn includes() {
+    let p = {
+        let mut p = preprocessor(&[
+            ("dir/types.x", "#include \"common.x\"\nstruct a { int b; };\n"),
+            ("dir/common.x", "const COMMON = 1;\n"),
+            ("sys/common.x", "const SYSTEM = 1;\n"),
+            ("sys/limits.x", "#define LIMIT 8\n"),
+        ]);
+        p.include_path("sys");
+        p
+    };
+    let (out, errors) = p.process_str("dir/spec.x", "\
+#include \"types.x\"
+#include <common.x>
+#include <limits.x>
+const L = LIMIT;
+");
+
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(
+        "const COMMON = 1;\nstruct a { int b; };\nconst SYSTEM = 1;\nconst L = 8;\n",
+        out.text
+    );
+
+    let offset = out.text.find("struct").unwrap();
+    let span = out.remap(Span { file: None, offset, line: 2, column: 1, length: 6 });
+    assert_eq!(Some("dir/types.x"), span.file.as_deref());
+    assert_eq!((2, 20), (span.line, span.offset));
+    assert_eq!(Some("struct"), out.source("dir/types.x").map(|s| &s[20..26]));
+
+    let offset = out.text.find("8").unwrap();
+    let span = out.remap(Span { file: None, offset, line: 4, column: 11, length: 1 });
+    assert_eq!(Some("dir/spec.x"), span.file.as_deref());
+    assert_eq!((4, 69), (span.line, span.offset));
+}
+
+#[test]
+fn errors() {
+    let p = preprocessor(&[("self.x", "#include \"self.x\"\n")]);
+    let (out, errors) = p.process_str("spec.x", "\
+#include \"missing.x\"
+#include missing.x
+#define F(x) x
+#define 1
+#frobnicate
+#if 1 / 0
+#endif
+#else
+#if 1
+#else
+#elif 1
+#endif
+#error stop here
+#include \"self.x\"
+#ifdef X
+text
+");
+
+    assert_eq!(
+        vec![
+            "P0001", "P0004", "P0004", "P0004", "P0003", "P0006",
+            "P0005", "P0005", "P0007", "P0002", "P0005",
+        ],
+        codes(&errors)
+    );
+    assert_eq!("", out.text);
+    assert_eq!(Some(1), errors[0].span.as_ref().map(|s| s.line));
+    assert_eq!(Some(15), errors.last().unwrap().span.as_ref().map(|s| s.line));
+    assert_eq!(
+        "spec.x:13:1: #error stop here",
+        errors[8].to_string()
+    );
+}
+
+#[test]
+fn continuation() {
+    let p = preprocessor(&[]);
+    let (out, errors) = p.process_str("spec.x", "\
+#define LONG 1 + \\
+    2
+#if LONG == 3 \\
+    && 1
+yes
+#endif
+");
+
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!("yes\n", out.text);
+
+    let span = out.remap(Span { file: None, offset: 0, line: 1, column: 1, length: 3 });
+    assert_eq!((5, 50), (span.line, span.offset));
+}
+
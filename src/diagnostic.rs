@@ -0,0 +1,243 @@
+
+#[cfg(test)]
+mod test;
+
+use std::io::IsTerminal;
+
+use lexer::Span;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+}
+
+/// Format of diagnostics written to stderr
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Source snippets for humans
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+/// Single message reported to the user
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// First line of `message` becomes diagnostic message, the rest are notes
+    pub fn error(code: &'static str, message: String) -> Self {
+        let mut lines = message.lines().map(String::from);
+
+        Self {
+            severity: Severity::Error,
+            code,
+            message: lines.next().unwrap_or_default(),
+            span: None,
+            notes: lines.collect(),
+            hint: None,
+        }
+    }
+
+    pub fn with_span(self, span: Option<Span>) -> Self {
+        Self {
+            span: span.filter(Span::is_known),
+            ..self
+        }
+    }
+
+    pub fn with_hint(self, hint: Option<String>) -> Self {
+        Self { hint, ..self }
+    }
+}
+
+impl From<&lexer::Error> for Diagnostic {
+    fn from(error: &lexer::Error) -> Self {
+        Self::error(error.kind.code(), error.kind.to_string())
+            .with_span(error.span.clone())
+    }
+}
+
+impl From<&rpc_parser::Error> for Diagnostic {
+    fn from(error: &rpc_parser::Error) -> Self {
+        Self::error(error.kind.code(), error.kind.to_string())
+            .with_span(error.span.clone())
+            .with_hint(error.hint.clone())
+    }
+}
+
+impl From<&rpc_preprocessor::Error> for Diagnostic {
+    fn from(error: &rpc_preprocessor::Error) -> Self {
+        Self::error(error.kind.code(), error.kind.to_string())
+            .with_span(error.span.clone())
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+struct Style {
+    colour: bool,
+}
+
+impl Style {
+    fn paint(&self, code: &str, text: impl std::fmt::Display) -> String {
+        if self.colour {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity(&self, severity: Severity, text: impl std::fmt::Display) -> String {
+        match severity {
+            Severity::Error => self.paint("1;31", text),
+        }
+    }
+
+    fn bold(&self, text: impl std::fmt::Display) -> String {
+        self.paint("1", text)
+    }
+
+    fn margin(&self, text: impl std::fmt::Display) -> String {
+        self.paint("1;34", text)
+    }
+}
+
+/// Write diagnostics to stderr, colour is used only for terminal
+///
+/// Snippets are taken from the text `sources` returns for the span file.
+pub fn emit<'a>(
+    diagnostics: &[Diagnostic],
+    sources: impl Fn(&str) -> Option<&'a str>,
+    format: MessageFormat,
+) {
+    let stderr = std::io::stderr();
+    let style = Style { colour: stderr.is_terminal() };
+
+    let out = diagnostics.iter()
+        .map(|diagnostic| (diagnostic, diagnostic.span.as_ref()
+            .and_then(|span| span.file.as_deref())
+            .and_then(&sources)
+        ))
+        .map(|(diagnostic, source)| match format {
+            MessageFormat::Human => render(diagnostic, source, &style),
+            MessageFormat::Json => render_json(diagnostic, source) + "\n",
+        })
+        .collect::<String>();
+
+    eprint!("{out}");
+}
+
+fn render_json(diagnostic: &Diagnostic, source: Option<&str>) -> String {
+    let span = diagnostic.span.as_ref().map(|span| {
+        let (end_line, end_column) = end_position(span, source);
+
+        serde_json::json!({
+            "offset": span.offset,
+            "length": span.length,
+            "line": span.line,
+            "column": span.column,
+            "end_line": end_line,
+            "end_column": end_column,
+        })
+    });
+
+    let notes = diagnostic.notes.iter()
+        .map(|note| serde_json::json!({ "kind": "note", "message": note }))
+        .chain(diagnostic.hint.iter()
+            .map(|hint| serde_json::json!({ "kind": "hint", "message": hint }))
+        )
+        .collect::<Vec<_>>();
+
+    serde_json::json!({
+        "severity": diagnostic.severity.to_string(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "file": diagnostic.span.as_ref().and_then(|span| span.file.as_deref()),
+        "span": span,
+        "notes": notes,
+    }).to_string()
+}
+
+/// Line and column right after the span, exclusive
+fn end_position(span: &Span, source: Option<&str>) -> (usize, usize) {
+    match source.and_then(|source| source.get(span.offset..span.end())) {
+        Some(marked) => marked.chars().fold((span.line, span.column), |(line, column), c| {
+            if '\n' == c {
+                (line + 1, 1)
+            } else {
+                (line, column + 1)
+            }
+        }),
+        None => (span.line, span.column + span.length),
+    }
+}
+
+fn render(diagnostic: &Diagnostic, source: Option<&str>, style: &Style) -> String {
+    let mut out = format!(
+        "{}{}\n",
+        style.severity(diagnostic.severity, diagnostic.severity),
+        style.bold(format!("[{}]: {}", diagnostic.code, diagnostic.message)),
+    );
+
+    let line = diagnostic.span.as_ref()
+        .and_then(|span| source
+            .and_then(|source| source.lines().nth(span.line - 1))
+            .map(|line| (span, line))
+        );
+
+    let width = diagnostic.span.as_ref()
+        .map(|span| span.line.to_string().len())
+        .unwrap_or(0);
+    let pad = " ".repeat(width);
+
+    if let Some(span) = &diagnostic.span {
+        out += &format!("{pad}{} {span}\n", style.margin("-->"));
+    }
+
+    if let Some((span, line)) = line {
+        let before = line.chars().take(span.column - 1)
+            .map(|c| if '\t' == c { '\t' } else { ' ' })
+            .collect::<String>();
+        let start = line.char_indices()
+            .nth(span.column - 1)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len());
+        let length = line.get(start..line.len().min(start + span.length))
+            .map(|marked| marked.chars().count())
+            .unwrap_or(0)
+            .max(1);
+
+        out += &format!("{pad} {}\n", style.margin("|"));
+        out += &format!("{} {line}\n", style.margin(format!("{} |", span.line)));
+        out += &format!(
+            "{pad} {} {before}{}\n",
+            style.margin("|"),
+            style.severity(diagnostic.severity, "^".repeat(length)),
+        );
+    }
+
+    diagnostic.notes.iter().for_each(|note| {
+        out += &format!("{pad} {} note: {note}\n", style.margin("="));
+    });
+
+    if let Some(hint) = &diagnostic.hint {
+        out += &format!("{pad} {} hint: {hint}\n", style.margin("="));
+    }
+
+    out + "\n"
+}
+
@@ -0,0 +1,100 @@
+use super::*;
+
+const SOURCE: &str = "struct foo {\n    int a;\n};\n\ntypedef enum foo bar;\n";
+
+fn plain() -> Style {
+    Style { colour: false }
+}
+
+fn undefined_type() -> Diagnostic {
+    Diagnostic::error("E0005", String::from("Undefined type\nUnknown enum with identifier foo"))
+        .with_span(Some(Span {
+            file: Some(std::sync::Arc::from("spec.x")),
+            offset: 41,
+            line: 5,
+            column: 14,
+            length: 3,
+        }))
+        .with_hint(Some(String::from("did you mean `struct foo`?")))
+}
+
+#[test]
+fn human_caret() {
+    assert_eq!(
+        "error[E0005]: Undefined type\n\
+         \x20--> spec.x:5:14\n\
+         \x20 |\n\
+         5 | typedef enum foo bar;\n\
+         \x20 |              ^^^\n\
+         \x20 = note: Unknown enum with identifier foo\n\
+         \x20 = hint: did you mean `struct foo`?\n\
+         \n",
+        render(&undefined_type(), Some(SOURCE), &plain()),
+    );
+}
+
+#[test]
+fn human_tabs_and_unknown_source() {
+    let diagnostic = Diagnostic::error("E0002", String::from("Unexpected token"))
+        .with_span(Some(Span {
+            file: Some(std::sync::Arc::from("spec.x")),
+            offset: 1,
+            line: 1,
+            column: 2,
+            length: 0,
+        }));
+
+    assert_eq!(
+        "error[E0002]: Unexpected token\n\
+         \x20--> spec.x:1:2\n\
+         \x20 |\n\
+         1 | \tconst\n\
+         \x20 | \t^\n\
+         \n",
+        render(&diagnostic, Some("\tconst\n"), &plain()),
+    );
+    assert_eq!(
+        "error[E0002]: Unexpected token\n\
+         \x20--> spec.x:1:2\n\
+         \n",
+        render(&diagnostic, None, &plain()),
+    );
+}
+
+#[test]
+fn human_without_span() {
+    assert_eq!(
+        "error[G0001]: Can't generate module: denied\n\n",
+        render(&Diagnostic::error("G0001", String::from("Can't generate module: denied")), None, &plain()),
+    );
+}
+
+#[test]
+fn human_colour() {
+    let out = render(&undefined_type(), Some(SOURCE), &Style { colour: true });
+
+    assert!(out.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m[E0005]: Undefined type\x1b[0m\n"));
+    assert!(out.contains("\x1b[1;31m^^^\x1b[0m"));
+}
+
+#[test]
+fn json() {
+    assert_eq!(
+        concat!(
+            r#"{"code":"E0005","file":"spec.x","message":"Undefined type","notes":["#,
+            r#"{"kind":"note","message":"Unknown enum with identifier foo"},"#,
+            r#"{"kind":"hint","message":"did you mean `struct foo`?"}],"#,
+            r#""severity":"error","span":{"column":14,"end_column":17,"end_line":5,"#,
+            r#""length":3,"line":5,"offset":41}}"#,
+        ),
+        render_json(&undefined_type(), Some(SOURCE)),
+    );
+}
+
+#[test]
+fn json_without_span() {
+    assert_eq!(
+        r#"{"code":"F0001","file":null,"message":"Can't read specification","notes":[],"severity":"error","span":null}"#,
+        render_json(&Diagnostic::error("F0001", String::from("Can't read specification")), None),
+    );
+}
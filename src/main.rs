@@ -1,12 +1,17 @@
 
+mod diagnostic;
+
 use clap::Parser;
 
 use lexer::Lexer;
 
+use diagnostic::{Diagnostic, MessageFormat};
+
 enum Error {
     FS(std::io::Error),
-    Lexer(lexer::Error),
-    Parser(rpc_parser::Error),
+    Preprocessor(Vec<rpc_preprocessor::Error>),
+    Syntax(Vec<lexer::Error>, Vec<rpc_parser::Error>),
+    Generator(std::io::Error),
 }
 
 /// Program for generating minimal linux kernel RPC modules for client and
@@ -22,72 +27,132 @@ struct Args {
     #[arg(short, long, default_value_t = String::from("spec.x"))]
     specification: String,
 
+    /// Directory searched for included files, can be repeated
+    #[arg(short = 'I', long = "include")]
+    include: Vec<std::path::PathBuf>,
+
+    /// Macro definition as NAME or NAME=VALUE, can be repeated
+    #[arg(short = 'D', long = "define")]
+    define: Vec<String>,
+
     /// Constant value for maximum variable lenght array size
     #[arg(short, long, default_value_t = 1024)]
     vla_limit: usize,
+
+    /// Constant value for maximum amount of recursive type elements in one value
+    #[arg(short, long, default_value_t = 64)]
+    recursion_limit: usize,
+
+    /// Format of reported errors
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
 }
 
-impl Into<rpc_generator::config::Config<std::path::PathBuf>> for Args {
-    fn into(self) -> rpc_generator::config::Config<std::path::PathBuf> {
+impl From<Args> for rpc_generator::config::Config<std::path::PathBuf> {
+    fn from(args: Args) -> Self {
         let mut out = rpc_generator::config::Config::new();
 
-        out.path = Some(std::path::PathBuf::from(self.path));
-        out.vla_limit = Some(self.vla_limit);
+        out.path = Some(std::path::PathBuf::from(args.path));
+        out.vla_limit = Some(args.vla_limit);
+        out.recursion_limit = Some(args.recursion_limit);
 
         out
     }
 }
 
-fn main() -> Result<(), Error>{
+fn main() -> std::process::ExitCode {
     let args = Args::parse();
 
-    let file = std::fs::File::open(&args.specification)?;
-    let reader = std::io::BufReader::new(file);
-
-    let tokens = rpc_lexer::lexer().parse(reader)
-        .try_fold(Vec::new(), |mut out, t| t.map(|t| {
-            out.push(t);
-            out
-        }))?;
+    let source = match std::fs::read_to_string(&args.specification) {
+        Ok(source) => source,
+        Err(error) => {
+            diagnostic::emit(&Error::FS(error).diagnostics(), |_| None, args.message_format);
+            return std::process::ExitCode::FAILURE;
+        },
+    };
 
-    let defs = rpc_parser::parse(tokens.into_iter())?;
+    let format = args.message_format;
 
-    rpc_generator::generate(defs.definitions.into_iter(), Some(args.into()))?;
-
-    Ok(())
-}
+    let (output, errors) = preprocessor(&args).process_str(&args.specification, &source);
+    if !errors.is_empty() {
+        diagnostic::emit(&Error::Preprocessor(errors).diagnostics(), |file| output.source(file), format);
+        return std::process::ExitCode::FAILURE;
+    }
 
-impl std::fmt::Debug for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::FS(error) => write!(f,
-                "Filesystem error: {error}"
-            ),
-            Error::Lexer(error) => write!(f,
-                "Lexer error: {error}"
-            ),
-            Error::Parser(error) => write!(f,
-                "Parser error: {error}"
-            ),
-        }
+    match run(args, &output.text) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(error) => {
+            let diagnostics = error.diagnostics().into_iter()
+                .map(|d| Diagnostic {
+                    span: d.span.clone().map(|span| output.remap(span)),
+                    ..d
+                })
+                .collect::<Vec<_>>();
+
+            diagnostic::emit(&diagnostics, |file| output.source(file), format);
+            std::process::ExitCode::FAILURE
+        },
     }
 }
 
-impl From<lexer::Error> for Error {
-    fn from(value: lexer::Error) -> Self {
-        Self::Lexer(value)
-    }
+fn preprocessor(args: &Args) -> rpc_preprocessor::Preprocessor {
+    let mut out = rpc_preprocessor::Preprocessor::new();
+
+    args.include.iter().for_each(|path| {
+        out.include_path(path);
+    });
+    args.define.iter().for_each(|define| {
+        match define.split_once('=') {
+            Some((name, value)) => out.define(name, value),
+            None => out.define(define, "1"),
+        };
+    });
+
+    out
 }
 
-impl From<rpc_parser::Error> for Error {
-    fn from(value: rpc_parser::Error) -> Self {
-        Self::Parser(value)
-    }
+fn run(args: Args, source: &str) -> Result<(), Error> {
+    let mut lexer_errors = Vec::new();
+    let tokens = rpc_lexer::recovering_lexer().parse_slice_source(Some(&args.specification), source)
+        .filter_map(|t| match t {
+            Ok(t) => Some(lexer::Spanned::from(t)),
+            Err(error) => {
+                lexer_errors.push(error);
+                None
+            },
+        })
+        .collect::<Vec<_>>();
+
+    let defs = match rpc_parser::parse_recovering(tokens.into_iter()) {
+        (defs, errors) if errors.is_empty() && lexer_errors.is_empty() => defs,
+        (_, errors) => return Err(Error::Syntax(lexer_errors, errors)),
+    };
+
+    rpc_generator::generate(defs.definitions.into_iter(), Some(args.into()))
+        .map_err(Error::Generator)
 }
 
-impl From<std::io::Error> for Error {
-    fn from(value: std::io::Error) -> Self {
-        Self::FS(value)
+impl Error {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Error::FS(error) => vec![Diagnostic::error(
+                "F0001",
+                format!("Can't read specification: {error}"),
+            )],
+            Error::Preprocessor(errors) => errors.iter().map(Diagnostic::from).collect(),
+            Error::Syntax(lexer, parser) => {
+                let mut out = lexer.iter().map(Diagnostic::from)
+                    .chain(parser.iter().map(Diagnostic::from))
+                    .collect::<Vec<_>>();
+
+                out.sort_by_key(|d| d.span.as_ref().map(|span| span.offset));
+                out
+            },
+            Error::Generator(error) => vec![Diagnostic::error(
+                "G0001",
+                format!("Can't generate module: {error}"),
+            )],
+        }
     }
 }
 